@@ -0,0 +1,206 @@
+use anyhow::Result;
+use chrono::{Duration as DaysDuration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::storage;
+
+// Usage data only ever lives in the local data dir; nothing here is transmitted.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FeatureUsage {
+    pub count: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DailyActivity {
+    pub messages: u64,
+    pub tokens: u64,
+    pub searches: u64,
+    pub models: HashMap<String, u64>,
+    #[serde(default)]
+    pub tags: HashMap<String, u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct UsageStore {
+    since: Option<u64>,
+    features: HashMap<String, FeatureUsage>,
+    #[serde(default)]
+    daily: BTreeMap<NaiveDate, DailyActivity>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DayStats {
+    pub date: NaiveDate,
+    pub messages: u64,
+    pub tokens: u64,
+    pub searches: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NamedCount {
+    pub name: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DashboardData {
+    pub enabled: bool,
+    pub period: String,
+    pub days: Vec<DayStats>,
+    pub top_models: Vec<NamedCount>,
+    pub top_tags: Vec<NamedCount>,
+}
+
+// Number of days covered by a dashboard period; None means everything recorded
+pub fn period_days(period: &str) -> Result<Option<i64>> {
+    match period {
+        "week" => Ok(Some(7)),
+        "month" => Ok(Some(30)),
+        "year" => Ok(Some(365)),
+        "all" => Ok(None),
+        other => anyhow::bail!("Unknown period: {} (expected week, month, year or all)", other),
+    }
+}
+
+pub fn top_counts(counts: HashMap<String, u64>, limit: usize) -> Vec<NamedCount> {
+    let mut counts: Vec<NamedCount> = counts
+        .into_iter()
+        .map(|(name, count)| NamedCount { name, count })
+        .collect();
+    counts.sort_by_key(|c| std::cmp::Reverse(c.count));
+    counts.truncate(limit);
+    counts
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeatureReport {
+    pub feature: String,
+    pub count: u64,
+    pub avg_ms: u64,
+    pub max_ms: u64,
+}
+
+// Only aggregate counts and timings, so the report is safe to share as an anonymized export
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UsageReport {
+    pub enabled: bool,
+    pub since: Option<u64>,
+    pub features: Vec<FeatureReport>,
+}
+
+pub struct Analytics {
+    path: PathBuf,
+    store: UsageStore,
+}
+
+impl Analytics {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("analytics.json");
+        let store = storage::load_json(&path);
+        Self { path, store }
+    }
+
+    pub fn record(&mut self, feature: &str, elapsed: Duration) -> Result<()> {
+        let elapsed_ms = elapsed.as_millis() as u64;
+        self.store.since.get_or_insert_with(storage::now_secs);
+
+        let usage = self.store.features.entry(feature.to_string()).or_default();
+        usage.count += 1;
+        usage.total_ms += elapsed_ms;
+        usage.max_ms = usage.max_ms.max(elapsed_ms);
+
+        storage::save_json(&self.path, &self.store)
+    }
+
+    pub fn report(&self, enabled: bool) -> UsageReport {
+        let mut features: Vec<FeatureReport> = self
+            .store
+            .features
+            .iter()
+            .map(|(feature, usage)| FeatureReport {
+                feature: feature.clone(),
+                count: usage.count,
+                avg_ms: usage.total_ms / usage.count.max(1),
+                max_ms: usage.max_ms,
+            })
+            .collect();
+        features.sort_by_key(|f| std::cmp::Reverse(f.count));
+
+        UsageReport {
+            enabled,
+            since: self.store.since,
+            features,
+        }
+    }
+
+    fn today(&mut self) -> &mut DailyActivity {
+        self.store.since.get_or_insert_with(storage::now_secs);
+        self.store.daily.entry(Local::now().date_naive()).or_default()
+    }
+
+    pub fn record_chat(&mut self, model: &str, messages: u64, tokens: u64) -> Result<()> {
+        let today = self.today();
+        today.messages += messages;
+        today.tokens += tokens;
+        *today.models.entry(model.to_string()).or_default() += 1;
+        storage::save_json(&self.path, &self.store)
+    }
+
+    pub fn record_search(&mut self) -> Result<()> {
+        self.today().searches += 1;
+        storage::save_json(&self.path, &self.store)
+    }
+
+    // Counts tags newly assigned to a conversation
+    pub fn record_tags(&mut self, tags: &[String]) -> Result<()> {
+        let today = self.today();
+        for tag in tags {
+            *today.tags.entry(tag.clone()).or_default() += 1;
+        }
+        storage::save_json(&self.path, &self.store)
+    }
+
+    pub fn dashboard(&self, period: &str, enabled: bool) -> Result<DashboardData> {
+        let today = Local::now().date_naive();
+        let start = period_days(period)?.map(|days| today - DaysDuration::days(days - 1));
+
+        let mut days = Vec::new();
+        let mut models: HashMap<String, u64> = HashMap::new();
+        let mut tags: HashMap<String, u64> = HashMap::new();
+        for (date, activity) in &self.store.daily {
+            if start.is_some_and(|start| *date < start) {
+                continue;
+            }
+            days.push(DayStats {
+                date: *date,
+                messages: activity.messages,
+                tokens: activity.tokens,
+                searches: activity.searches,
+            });
+            for (model, count) in &activity.models {
+                *models.entry(model.clone()).or_default() += count;
+            }
+            for (tag, count) in &activity.tags {
+                *tags.entry(tag.clone()).or_default() += count;
+            }
+        }
+
+        Ok(DashboardData {
+            enabled,
+            period: period.to_string(),
+            days,
+            top_models: top_counts(models, 5),
+            top_tags: top_counts(tags, 5),
+        })
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.store = UsageStore::default();
+        storage::save_json(&self.path, &self.store)
+    }
+}
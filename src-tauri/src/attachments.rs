@@ -0,0 +1,61 @@
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Larger images are better resized first; vision models downscale them anyway
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("bmp", "image/bmp"),
+];
+
+// An image ready to send with a message; `data` is what goes into ChatMessage::images
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageAttachment {
+    pub name: String,
+    pub mime_type: String,
+    pub size: u64,
+    // Base64 without a data: URL prefix, as Ollama expects
+    pub data: String,
+}
+
+pub fn load_image(path: &Path) -> Result<ImageAttachment> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    let Some((_, mime_type)) = IMAGE_TYPES.iter().find(|(ext, _)| *ext == extension) else {
+        bail!("Not a supported image type: {}", path.display());
+    };
+
+    let size = fs::metadata(path)
+        .with_context(|| format!("Cannot read {}", path.display()))?
+        .len();
+    if size > MAX_IMAGE_BYTES {
+        bail!(
+            "{} is {} MB; images up to {} MB can be attached",
+            path.display(),
+            size / (1024 * 1024),
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        );
+    }
+
+    let bytes = fs::read(path)?;
+    Ok(ImageAttachment {
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        mime_type: mime_type.to_string(),
+        size,
+        data: base64::engine::general_purpose::STANDARD.encode(bytes),
+    })
+}
@@ -0,0 +1,125 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Receiver;
+
+use crate::ollama::{
+    ChatChunk, ChatMessage, ChatRequest, LocalModel, OllamaClient, OllamaError, DEFAULT_EMBED_MODEL, DEFAULT_MODEL,
+};
+use crate::openai::OpenAiSettings;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Ollama,
+    // Any OpenAI-compatible server; its key is the OpenAI provider key
+    OpenAi,
+    // Local servers, talked to through their OpenAI-compatible endpoints
+    LlamaCpp,
+    LmStudio,
+}
+
+impl BackendKind {
+    // Where each local server listens unless told otherwise
+    pub fn default_local_url(self) -> Option<&'static str> {
+        match self {
+            BackendKind::LlamaCpp => Some("http://127.0.0.1:8080/v1"),
+            BackendKind::LmStudio => Some("http://127.0.0.1:1234/v1"),
+            BackendKind::Ollama | BackendKind::OpenAi => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BackendSettings {
+    pub kind: BackendKind,
+    pub openai: OpenAiSettings,
+    // llama.cpp or LM Studio server, up to /v1; empty uses its usual port
+    pub local_url: String,
+    // Tried in order when the selected model fails to start a reply
+    pub fallbacks: Vec<FallbackTarget>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FallbackTarget {
+    pub model: String,
+    // None stays on the configured backend
+    #[serde(default)]
+    pub backend: Option<BackendKind>,
+}
+
+// The provider chat, tagging and embeddings go through. Ollama-only features such as
+// pulling, creating or deleting models stay on OllamaClient.
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    // Loopback servers; anything else counts as a remote provider
+    fn is_local(&self) -> bool;
+
+    // Selected when the app starts or the backend is switched
+    fn default_model(&self) -> String;
+
+    fn embed_model(&self) -> String;
+
+    // Fails if the server cannot be reached; errors once streaming has started
+    // arrive on the channel and end it
+    async fn chat_stream(
+        &self,
+        request: ChatRequest,
+    ) -> Result<Receiver<Result<ChatChunk, OllamaError>>, OllamaError>;
+
+    // Non-streaming; `request.stream` is ignored
+    async fn chat(&self, request: ChatRequest) -> Result<ChatMessage>;
+
+    async fn list_models(&self) -> Result<Vec<LocalModel>>;
+
+    // One vector per input text, in the same order
+    async fn embed(&self, texts: Vec<String>, model: String) -> Result<Vec<Vec<f32>>>;
+
+    fn boxed_clone(&self) -> Box<dyn LlmBackend>;
+}
+
+impl Clone for Box<dyn LlmBackend> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OllamaClient {
+    fn is_local(&self) -> bool {
+        OllamaClient::is_local(self)
+    }
+
+    fn default_model(&self) -> String {
+        DEFAULT_MODEL.to_string()
+    }
+
+    fn embed_model(&self) -> String {
+        DEFAULT_EMBED_MODEL.to_string()
+    }
+
+    async fn chat_stream(
+        &self,
+        request: ChatRequest,
+    ) -> Result<Receiver<Result<ChatChunk, OllamaError>>, OllamaError> {
+        OllamaClient::chat_stream(self, request).await
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatMessage> {
+        OllamaClient::chat(self, request).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<LocalModel>> {
+        OllamaClient::list_models(self).await
+    }
+
+    async fn embed(&self, texts: Vec<String>, model: String) -> Result<Vec<Vec<f32>>> {
+        OllamaClient::embed(self, texts, model).await
+    }
+
+    fn boxed_clone(&self) -> Box<dyn LlmBackend> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::search::{SearchProvider, SearchRequest};
+use crate::storage;
+
+// Where a bang sends the query: a dedicated provider, or DuckDuckGo restricted to a site
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BangTarget {
+    pub provider: SearchProvider,
+    #[serde(default)]
+    pub site: Option<String>,
+}
+
+// User-editable bang -> provider mapping, kept in bangs.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(transparent)]
+pub struct Bangs {
+    pub bangs: BTreeMap<String, BangTarget>,
+}
+
+impl Default for Bangs {
+    fn default() -> Self {
+        let provider = |provider| BangTarget { provider, site: None };
+        let site = |site: &str| BangTarget {
+            provider: SearchProvider::DuckDuckGo,
+            site: Some(site.to_string()),
+        };
+        let bangs = [
+            ("w", provider(SearchProvider::Wikipedia)),
+            ("wiki", provider(SearchProvider::Wikipedia)),
+            ("gh", provider(SearchProvider::GitHub)),
+            ("github", provider(SearchProvider::GitHub)),
+            ("ddg", provider(SearchProvider::DuckDuckGo)),
+            ("brave", provider(SearchProvider::Brave)),
+            ("so", site("stackoverflow.com")),
+            ("rs", site("docs.rs")),
+            ("crates", site("crates.io")),
+            ("mdn", site("developer.mozilla.org")),
+            ("r", site("reddit.com")),
+        ];
+        Self {
+            bangs: bangs.into_iter().map(|(name, target)| (name.to_string(), target)).collect(),
+        }
+    }
+}
+
+fn normalize_name(name: &str) -> Result<String> {
+    let name = name.trim().trim_start_matches('!');
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        bail!("Bangs may only contain letters, digits, '-' and '_'");
+    }
+    Ok(name.to_lowercase())
+}
+
+impl Bangs {
+    fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join("bangs.json")
+    }
+
+    // Writes the shipped defaults on first run so there is a file to edit
+    pub fn load(data_dir: &Path) -> Self {
+        let path = Self::path(data_dir);
+        if !path.exists() {
+            let defaults = Self::default();
+            if let Err(e) = defaults.save(data_dir) {
+                eprintln!("Failed to write default bangs: {:?}", e);
+            }
+            return defaults;
+        }
+        storage::load_json(&path)
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        storage::save_json(&Self::path(data_dir), self)
+    }
+
+    pub fn set(&mut self, name: &str, target: BangTarget) -> Result<()> {
+        if target.site.as_deref().is_some_and(|site| site.trim().is_empty()) {
+            bail!("Bang site may not be empty");
+        }
+        self.bangs.insert(normalize_name(name)?, target);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<bool> {
+        Ok(self.bangs.remove(&normalize_name(name)?).is_some())
+    }
+
+    // "!w rust" or "rust !w": the first known bang anywhere in the query picks the provider,
+    // otherwise `default` does. Unknown bangs are left in the query untouched.
+    pub fn route(&self, query: &str, max_results: usize, default: SearchProvider) -> SearchRequest {
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let found = words.iter().enumerate().find_map(|(index, word)| {
+            let name = word.strip_prefix('!')?.to_lowercase();
+            self.bangs.get(&name).map(|target| (index, target))
+        });
+
+        let Some((index, target)) = found else {
+            return SearchRequest {
+                query: query.to_string(),
+                max_results,
+                provider: default,
+                api_key: None,
+            };
+        };
+
+        let mut rest: Vec<String> = words
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, word)| word.to_string())
+            .collect();
+        if let Some(site) = &target.site {
+            rest.push(format!("site:{}", site.trim()));
+        }
+
+        SearchRequest {
+            query: rest.join(" "),
+            max_results,
+            provider: target.provider,
+            api_key: None,
+        }
+    }
+}
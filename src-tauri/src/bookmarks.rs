@@ -0,0 +1,135 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::search::SearchResult;
+use crate::storage;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Bookmark {
+    pub id: String,
+    pub result: SearchResult,
+    pub tags: Vec<String>,
+    // Knowledge base snapshot taken when the result was bookmarked
+    pub snapshot_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Bookmark {
+    // Every whitespace-separated word of the query must appear in the title, summary, URL or tags
+    fn matches_text(&self, query: &str) -> bool {
+        let haystack = format!(
+            "{} {} {} {}",
+            self.result.title,
+            self.result.summary,
+            self.result.url,
+            self.tags.join(" ")
+        )
+        .to_lowercase();
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.contains(word))
+    }
+}
+
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .into_iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+pub struct BookmarkStore {
+    path: PathBuf,
+    bookmarks: Vec<Bookmark>,
+}
+
+impl BookmarkStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("bookmarks.json");
+        let bookmarks = storage::load_json(&path);
+        Self { path, bookmarks }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.bookmarks)
+    }
+
+    // Bookmarking the same URL again updates the stored result and merges tags
+    pub fn add(
+        &mut self,
+        result: SearchResult,
+        tags: Vec<String>,
+        snapshot_id: Option<String>,
+    ) -> Result<Bookmark> {
+        let tags = normalize_tags(tags);
+        let bookmark = match self.bookmarks.iter_mut().find(|b| b.result.url == result.url) {
+            Some(existing) => {
+                existing.result = result;
+                existing.tags = normalize_tags([existing.tags.clone(), tags].concat());
+                if snapshot_id.is_some() {
+                    existing.snapshot_id = snapshot_id;
+                }
+                existing.clone()
+            }
+            None => {
+                let bookmark = Bookmark {
+                    id: Uuid::new_v4().to_string(),
+                    result,
+                    tags,
+                    snapshot_id,
+                    created_at: Utc::now(),
+                };
+                self.bookmarks.push(bookmark.clone());
+                bookmark
+            }
+        };
+        self.save()?;
+        Ok(bookmark)
+    }
+
+    // Newest first, optionally narrowed to a tag and/or a text query
+    pub fn search(&self, tag: Option<&str>, query: Option<&str>) -> Vec<Bookmark> {
+        let tag = tag.map(|t| t.trim().to_lowercase());
+        let mut bookmarks: Vec<Bookmark> = self
+            .bookmarks
+            .iter()
+            .filter(|b| tag.as_ref().is_none_or(|tag| b.tags.contains(tag)))
+            .filter(|b| query.is_none_or(|q| b.matches_text(q)))
+            .cloned()
+            .collect();
+        bookmarks.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+        bookmarks
+    }
+
+    pub fn set_tags(&mut self, id: &str, tags: Vec<String>) -> Result<Option<Bookmark>> {
+        let Some(bookmark) = self.bookmarks.iter_mut().find(|b| b.id == id) else {
+            return Ok(None);
+        };
+        bookmark.tags = normalize_tags(tags);
+        let bookmark = bookmark.clone();
+        self.save()?;
+        Ok(Some(bookmark))
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<bool> {
+        let before = self.bookmarks.len();
+        self.bookmarks.retain(|b| b.id != id);
+        let removed = self.bookmarks.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        normalize_tags(self.bookmarks.iter().flat_map(|b| b.tags.clone()).collect())
+    }
+}
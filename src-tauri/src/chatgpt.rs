@@ -0,0 +1,132 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::export::ConversationExport;
+use crate::ollama::ChatMessage;
+
+// conversations.json from ChatGPT's "Export data". Each conversation is a tree of
+// messages (one branch per regenerated answer); the branch ending at current_node is
+// the one the user last saw.
+#[derive(Debug, Deserialize)]
+struct ChatGptConversation {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    mapping: HashMap<String, Node>,
+    #[serde(default)]
+    current_node: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Node {
+    #[serde(default)]
+    message: Option<Message>,
+    #[serde(default)]
+    parent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    author: Author,
+    #[serde(default)]
+    content: Option<Content>,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Author {
+    role: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Content {
+    #[serde(default)]
+    content_type: String,
+    // Strings for text; images and other attachments are objects
+    #[serde(default)]
+    parts: Vec<Value>,
+}
+
+fn timestamp(secs: Option<f64>) -> Option<DateTime<Utc>> {
+    let secs = secs?;
+    Utc.timestamp_opt(secs.trunc() as i64, (secs.fract() * 1e9) as u32).single()
+}
+
+fn convert_message(message: Message) -> Option<ChatMessage> {
+    if message.author.role != "user" && message.author.role != "assistant" {
+        return None;
+    }
+    if message.metadata["is_visually_hidden_from_conversation"].as_bool() == Some(true) {
+        return None;
+    }
+    let content = message.content?;
+    if content.content_type != "text" && content.content_type != "multimodal_text" {
+        return None;
+    }
+    let text: Vec<&str> = content.parts.iter().filter_map(Value::as_str).collect();
+    let text = text.join("\n").trim().to_string();
+    if text.is_empty() {
+        return None;
+    }
+    Some(ChatMessage {
+        role: message.author.role,
+        content: text,
+        metadata: None,
+        images: None,
+        tool_calls: None,
+        created_at: timestamp(message.create_time),
+        model: message.metadata["model_slug"].as_str().map(str::to_string),
+        duration_ms: None,
+        pinned: false,
+    })
+}
+
+fn convert(conversation: ChatGptConversation) -> Option<ConversationExport> {
+    let mut mapping = conversation.mapping;
+    // Walk from the last shown message up to the root, then reverse
+    let mut messages = Vec::new();
+    let mut next = conversation.current_node;
+    while let Some(node) = next.and_then(|id| mapping.remove(&id)) {
+        messages.extend(node.message.and_then(convert_message));
+        next = node.parent;
+    }
+    if messages.is_empty() {
+        return None;
+    }
+    messages.reverse();
+
+    let name = conversation
+        .title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "Imported from ChatGPT".to_string());
+    let created_at = timestamp(conversation.create_time)
+        .or_else(|| messages.iter().find_map(|m| m.created_at))
+        .unwrap_or_else(Utc::now);
+    Some(ConversationExport::new(name, created_at, messages))
+}
+
+// Conversations without any text messages are skipped
+pub fn load(path: &Path) -> Result<Vec<ConversationExport>> {
+    let text = fs::read_to_string(path)?;
+    let conversations: Vec<ChatGptConversation> =
+        serde_json::from_str(&text).context("Not a ChatGPT conversations.json export")?;
+    let imported: Vec<ConversationExport> = conversations.into_iter().filter_map(convert).collect();
+    if imported.is_empty() {
+        bail!("The export contains no conversations with text messages");
+    }
+    for export in &imported {
+        export.validate()?;
+    }
+    Ok(imported)
+}
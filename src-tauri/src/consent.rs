@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+// Unanswered consent prompts are treated as "fetch nothing"
+pub const CONSENT_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConsentRequest {
+    pub request_id: String,
+    pub urls: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct ConsentBroker {
+    next_id: u64,
+    pending: HashMap<String, oneshot::Sender<Vec<String>>>,
+}
+
+impl ConsentBroker {
+    pub fn register(&mut self, urls: Vec<String>) -> (ConsentRequest, oneshot::Receiver<Vec<String>>) {
+        self.next_id += 1;
+        let request_id = format!("consent-{}", self.next_id);
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(request_id.clone(), tx);
+        (ConsentRequest { request_id, urls }, rx)
+    }
+
+    pub fn respond(&mut self, request_id: &str, approved_urls: Vec<String>) -> bool {
+        match self.pending.remove(request_id) {
+            Some(tx) => tx.send(approved_urls).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn cancel(&mut self, request_id: &str) {
+        self.pending.remove(request_id);
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+use crate::language;
+use crate::ollama::{ChatMessage, OllamaClient};
+use crate::template::PromptVariables;
+
+// Ollama's context size when num_ctx is not set; requests pin it so the budget matches
+pub const DEFAULT_CONTEXT_TOKENS: u32 = 4096;
+// Kept free for the reply, which shares the window with the prompt
+const REPLY_RESERVE_TOKENS: u64 = 1024;
+// Role markers and separators the chat template adds around each message
+const MESSAGE_OVERHEAD_TOKENS: u64 = 4;
+// Roughly what vision models spend on one image
+const IMAGE_TOKENS: u64 = 768;
+
+// Approximate token count: about four characters per token within a word, every
+// word and punctuation mark at least one, and CJK characters one each
+pub fn estimate_tokens(text: &str) -> u64 {
+    text.split_whitespace()
+        .map(|word| {
+            let (wide, narrow) = word.chars().fold((0u64, 0u64), |(wide, narrow), c| {
+                if c as u32 >= 0x2e80 {
+                    (wide + 1, narrow)
+                } else {
+                    (wide, narrow + 1)
+                }
+            });
+            let punctuation = word.chars().filter(|c| c.is_ascii_punctuation()).count() as u64;
+            wide + narrow.div_ceil(4).max(punctuation).max((narrow > 0) as u64)
+        })
+        .sum()
+}
+
+pub fn message_tokens(message: &ChatMessage) -> u64 {
+    let images = message.images.as_ref().map_or(0, |images| images.len() as u64);
+    estimate_tokens(&message.content) + images * IMAGE_TOKENS + MESSAGE_OVERHEAD_TOKENS
+}
+
+// First history message that still fits in `budget`, filling from the newest back.
+// A message that does not fit ends the history, so nothing in the middle goes missing.
+// Pinned messages go in regardless and are paid for before the budget is passed in.
+fn history_start(history: &[ChatMessage], budget: u64) -> usize {
+    let mut used = 0;
+    for (index, message) in history.iter().enumerate().rev() {
+        if message.pinned {
+            continue;
+        }
+        used += message_tokens(message);
+        if used > budget {
+            return index + 1;
+        }
+    }
+    0
+}
+
+// What goes into the system messages besides the base prompt
+pub struct SystemPrompt<'a> {
+    // Replaces SYSTEM_PROMPT for this conversation
+    pub custom: Option<&'a str>,
+    pub persona: Option<&'a str>,
+    pub variables: &'a PromptVariables,
+    // Language the reply has to be written in, if known
+    pub reply_language: Option<&'a str>,
+    // Rolling summary of messages no longer kept in the history
+    pub summary: Option<&'a str>,
+    // Saved facts relevant to the new message, for the FACTS_CHECK step
+    pub facts: Option<&'a str>,
+}
+
+impl SystemPrompt<'_> {
+    // The base prompt, the workspace persona and the reply language directive, with
+    // variables resolved; each paired with why it is sent
+    fn messages(&self) -> Vec<(ChatMessage, &'static str)> {
+        let system = |content: String| ChatMessage {
+            role: "system".to_string(),
+            content,
+            metadata: None,
+            images: None,
+            tool_calls: None,
+            created_at: None,
+            model: None,
+            duration_ms: None,
+            pinned: false,
+        };
+        let mut messages = match self.custom {
+            Some(prompt) => vec![(system(prompt.to_string()), "Custom system prompt for this conversation")],
+            None => vec![(OllamaClient::create_system_message(), "System prompt, always sent")],
+        };
+        if let Some(persona) = self.persona {
+            messages.push((system(persona.to_string()), "Workspace persona"));
+        }
+        for (message, _) in &mut messages {
+            message.content = self.variables.render(&message.content);
+        }
+        if let Some(summary) = self.summary {
+            let content = format!("Summary of the earlier conversation:\n{}", summary);
+            messages.push((system(content), "Summary of earlier messages"));
+        }
+        if let Some(facts) = self.facts {
+            messages.push((system(facts.to_string()), "Saved facts relevant to the message"));
+        }
+        if let Some(language) = self.reply_language {
+            messages.push((system(language::reply_directive(language)), "Reply language"));
+        }
+        messages
+    }
+}
+
+fn pinned_tokens(history: &[ChatMessage]) -> u64 {
+    history.iter().filter(|m| m.pinned).map(message_tokens).sum()
+}
+
+// Everything sent to the model for a new user message, in order. The system messages,
+// pinned history and the new message always go in, even when they alone overflow the
+// window; the rest of the history gets whatever room is left.
+pub fn build_messages(
+    history: &[ChatMessage],
+    user_message: ChatMessage,
+    system: &SystemPrompt,
+    context_tokens: u32,
+) -> Vec<ChatMessage> {
+    let mut messages: Vec<ChatMessage> = system.messages().into_iter().map(|(m, _)| m).collect();
+    let fixed: u64 =
+        messages.iter().map(message_tokens).sum::<u64>() + pinned_tokens(history) + message_tokens(&user_message);
+    let budget = (context_tokens as u64).saturating_sub(REPLY_RESERVE_TOKENS + fixed);
+    let start = history_start(history, budget);
+    messages.extend(
+        history
+            .iter()
+            .enumerate()
+            .filter(|(index, message)| *index >= start || message.pinned)
+            .map(|(_, message)| message.clone()),
+    );
+    messages.push(user_message);
+    messages
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContextEntry {
+    pub role: String,
+    pub content: String,
+    pub tokens: u64,
+    pub included: bool,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContextPreview {
+    pub conversation_id: String,
+    pub model: String,
+    // Whether personal data in the entries will be replaced by placeholders
+    pub redacted: bool,
+    pub context_tokens: u32,
+    pub entries: Vec<ContextEntry>,
+    // Included entries only; the new message comes on top of this
+    pub total_tokens: u64,
+}
+
+// What build_messages would send ahead of the next message, including the
+// history it leaves out and why. The next message itself is not known yet, so
+// slightly more history shows as included than will fit alongside a long one.
+pub fn preview_entries(history: &[ChatMessage], system: &SystemPrompt, context_tokens: u32) -> Vec<ContextEntry> {
+    let mut entries: Vec<ContextEntry> = system
+        .messages()
+        .into_iter()
+        .map(|(message, reason)| ContextEntry {
+            role: message.role.clone(),
+            tokens: message_tokens(&message),
+            content: message.content,
+            included: true,
+            reason: reason.to_string(),
+        })
+        .collect();
+
+    let fixed: u64 = entries.iter().map(|e| e.tokens).sum::<u64>() + pinned_tokens(history);
+    let budget = (context_tokens as u64).saturating_sub(REPLY_RESERVE_TOKENS + fixed);
+    let start = history_start(history, budget);
+    entries.extend(history.iter().enumerate().map(|(index, message)| {
+        let included = index >= start || message.pinned;
+        let reason = if message.pinned {
+            "Pinned to the context".to_string()
+        } else if included {
+            format!("Fits in the {}-token context window", context_tokens)
+        } else {
+            format!("Does not fit in the {}-token context window with newer messages", context_tokens)
+        };
+        ContextEntry {
+            role: message.role.clone(),
+            content: message.content.clone(),
+            tokens: message_tokens(message),
+            included,
+            reason,
+        }
+    }));
+    entries
+}
@@ -0,0 +1,287 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::encryption;
+use crate::history_search::{self, MessageMatch};
+use crate::ollama::{ChatMessage, GenerationOptions};
+use crate::redact::RedactionMap;
+use crate::summary::ConversationDigest;
+
+// Changed conversations are written on this interval, at the end of each reply and
+// before a search, so a crash loses at most this much
+pub const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+// What a conversation keeps across restarts; search results waiting for the next answer
+// and the summarizing flag are not worth keeping
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoredConversation {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub forked_from: Option<String>,
+    pub pinned: bool,
+    pub favorite: bool,
+    pub archived: bool,
+    pub messages: Vec<ChatMessage>,
+    pub redactions: RedactionMap,
+    pub no_web: bool,
+    pub reply_language: Option<String>,
+    pub system_prompt: Option<String>,
+    pub model: Option<String>,
+    pub options: GenerationOptions,
+    pub tags: Vec<String>,
+    pub exchanges: usize,
+    pub searches: usize,
+    pub summary: Option<String>,
+    pub digest: Option<ConversationDigest>,
+}
+
+impl StoredConversation {
+    // When the last message was added, or when the conversation was created
+    pub fn last_activity(&self) -> DateTime<Utc> {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|m| m.created_at)
+            .unwrap_or(self.created_at)
+    }
+}
+
+// A stored conversation as retention sees it, without decrypting it
+pub struct ConversationRow {
+    pub id: String,
+    pub pinned: bool,
+    pub last_activity: DateTime<Utc>,
+}
+
+// Conversations in conversations.db, one row each, with the JSON sealed when storage
+// encryption is on. The full-text index lives in the temp schema, kept in memory and
+// rebuilt on load, so no plaintext copy of the messages ever reaches the disk.
+pub struct ConversationStore {
+    conn: Connection,
+    // Hash of what was last written or indexed for each conversation, to skip unchanged ones
+    synced: HashMap<String, [u8; 32]>,
+}
+
+impl ConversationStore {
+    pub fn open(data_dir: &Path) -> Result<Self> {
+        let conn = Connection::open(data_dir.join("conversations.db"))?;
+        conn.execute_batch(
+            "PRAGMA secure_delete = ON;
+             PRAGMA temp_store = MEMORY;
+             CREATE TABLE IF NOT EXISTS conversations (
+                 id TEXT PRIMARY KEY,
+                 data BLOB NOT NULL,
+                 pinned INTEGER NOT NULL,
+                 last_activity TEXT NOT NULL
+             );
+             CREATE VIRTUAL TABLE temp.messages_fts USING fts5(
+                 conversation_id UNINDEXED,
+                 conversation_name UNINDEXED,
+                 position UNINDEXED,
+                 role UNINDEXED,
+                 created_at UNINDEXED,
+                 content
+             );",
+        )?;
+        Ok(Self {
+            conn,
+            synced: HashMap::new(),
+        })
+    }
+
+    // Every stored conversation, indexed for search. Fails while the storage is locked;
+    // a row that cannot be read is skipped so the others still load.
+    pub fn load_all(&mut self) -> Result<Vec<StoredConversation>> {
+        let rows: Vec<(String, Vec<u8>)> = self
+            .conn
+            .prepare("SELECT id, data FROM conversations")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        let mut conversations = Vec::new();
+        for (id, data) in rows {
+            let json = match encryption::open(&data) {
+                Ok(json) => json,
+                Err(e) if e.is::<encryption::Locked>() => return Err(e),
+                Err(e) => {
+                    eprintln!("Failed to decrypt conversation {}: {:?}", id, e);
+                    continue;
+                }
+            };
+            match serde_json::from_slice::<StoredConversation>(&json) {
+                Ok(conversation) => {
+                    self.index(&conversation)?;
+                    self.synced.insert(id, fingerprint(&json));
+                    conversations.push(conversation);
+                }
+                Err(e) => eprintln!("Failed to load conversation {}: {:?}", id, e),
+            }
+        }
+        Ok(conversations)
+    }
+
+    // Writes the conversation if it changed since the last sync. With `persist` false
+    // (incognito) it is only indexed. A conversation that never had a message is not
+    // written until it gets one.
+    pub fn sync(&mut self, conversation: &StoredConversation, persist: bool) -> Result<()> {
+        let json = serde_json::to_vec(conversation)?;
+        let hash = fingerprint(&json);
+        if self.synced.get(&conversation.id) == Some(&hash) {
+            return Ok(());
+        }
+        if persist && (!conversation.messages.is_empty() || self.is_stored(&conversation.id)?) {
+            self.conn.execute(
+                "INSERT INTO conversations (id, data, pinned, last_activity) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(id) DO UPDATE SET data = ?2, pinned = ?3, last_activity = ?4",
+                params![
+                    conversation.id,
+                    encryption::seal(&json)?,
+                    conversation.pinned,
+                    conversation.last_activity().to_rfc3339(),
+                ],
+            )?;
+        }
+        self.index(conversation)?;
+        self.synced.insert(conversation.id.clone(), hash);
+        Ok(())
+    }
+
+    fn is_stored(&self, id: &str) -> Result<bool> {
+        let row: Option<i64> = self
+            .conn
+            .query_row("SELECT 1 FROM conversations WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?;
+        Ok(row.is_some())
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM conversations WHERE id = ?1", [id])?;
+        self.conn.execute("DELETE FROM temp.messages_fts WHERE conversation_id = ?1", [id])?;
+        self.synced.remove(id);
+        Ok(())
+    }
+
+    pub fn rows(&self) -> Result<Vec<ConversationRow>> {
+        let rows: Vec<(String, bool, String)> = self
+            .conn
+            .prepare("SELECT id, pinned, last_activity FROM conversations")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<_, _>>()?;
+        rows.into_iter()
+            .map(|(id, pinned, last_activity)| {
+                Ok(ConversationRow {
+                    id,
+                    pinned,
+                    last_activity: DateTime::parse_from_rfc3339(&last_activity)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    // Seals rows written before encryption was enabled, then vacuums so the plaintext
+    // pages do not linger in the file
+    pub fn encrypt_all(&mut self) -> Result<usize> {
+        let rows: Vec<(String, Vec<u8>)> = self
+            .conn
+            .prepare("SELECT id, data FROM conversations")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        let mut migrated = 0;
+        for (id, data) in rows.into_iter().filter(|(_, data)| !encryption::is_sealed(data)) {
+            self.conn.execute(
+                "UPDATE conversations SET data = ?2 WHERE id = ?1",
+                params![id, encryption::seal(&data)?],
+            )?;
+            migrated += 1;
+        }
+        if migrated > 0 {
+            self.conn.execute_batch("VACUUM")?;
+        }
+        Ok(migrated)
+    }
+
+    // Drops the in-memory index, for when the storage is locked
+    pub fn clear_index(&mut self) -> Result<()> {
+        self.conn.execute("DELETE FROM temp.messages_fts", [])?;
+        self.synced.clear();
+        Ok(())
+    }
+
+    fn index(&mut self, conversation: &StoredConversation) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM temp.messages_fts WHERE conversation_id = ?1", [&conversation.id])?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO temp.messages_fts (conversation_id, conversation_name, position, role, created_at, content)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for (position, message) in conversation.messages.iter().enumerate() {
+                if message.role != "user" && message.role != "assistant" {
+                    continue;
+                }
+                insert.execute(params![
+                    conversation.id,
+                    conversation.name,
+                    position as i64,
+                    message.role,
+                    message.created_at.map(|t| t.to_rfc3339()),
+                    message.content,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Best matches first, then the newest
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<MessageMatch>> {
+        let Some(query) = history_search::fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let mut statement = self.conn.prepare(
+            "SELECT conversation_id, conversation_name, position, role, created_at,
+                    snippet(messages_fts, 5, ?2, ?3, '…', ?4), bm25(messages_fts)
+             FROM temp.messages_fts WHERE messages_fts MATCH ?1
+             ORDER BY bm25(messages_fts), created_at DESC LIMIT ?5",
+        )?;
+        let matches = statement
+            .query_map(
+                params![
+                    query,
+                    history_search::MARK_START,
+                    history_search::MARK_END,
+                    history_search::SNIPPET_TOKENS,
+                    limit as i64,
+                ],
+                |row| {
+                    let created_at: Option<String> = row.get(4)?;
+                    let snippet: String = row.get(5)?;
+                    let rank: f64 = row.get(6)?;
+                    Ok(MessageMatch {
+                        conversation_id: row.get(0)?,
+                        conversation_name: row.get(1)?,
+                        message_index: row.get::<_, i64>(2)? as usize,
+                        role: row.get(3)?,
+                        created_at: created_at
+                            .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+                            .map(|t| t.with_timezone(&Utc)),
+                        snippet: history_search::highlight(&snippet),
+                        // bm25 is lower for better matches
+                        score: -rank,
+                    })
+                },
+            )?
+            .collect::<Result<_, _>>()?;
+        Ok(matches)
+    }
+}
+
+fn fingerprint(json: &[u8]) -> [u8; 32] {
+    Sha256::digest(json).into()
+}
@@ -0,0 +1,32 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+// Ollama and OpenAI both cap the alternatives per token at 20
+pub const MAX_TOP_LOGPROBS: u32 = 20;
+
+// Opt-in tools for inspecting model output; everything here is off unless enabled
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DeveloperSettings {
+    // Ask for token log probabilities on chat replies and allow raw completions
+    pub enabled: bool,
+    // Alternatives reported per token; 0 reports only the chosen one
+    pub top_logprobs: u32,
+}
+
+impl DeveloperSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.top_logprobs > MAX_TOP_LOGPROBS {
+            bail!("top_logprobs must be at most {}", MAX_TOP_LOGPROBS);
+        }
+        Ok(())
+    }
+
+    // Request fields: (logprobs, top_logprobs)
+    pub fn logprobs(&self) -> (Option<bool>, Option<u32>) {
+        if !self.enabled {
+            return (None, None);
+        }
+        (Some(true), (self.top_logprobs > 0).then_some(self.top_logprobs))
+    }
+}
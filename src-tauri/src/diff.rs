@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffSegment {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageDiff {
+    pub segments: Vec<DiffSegment>,
+    pub words_added: usize,
+    pub words_removed: usize,
+}
+
+// Word-level diff; consecutive tokens with the same op are merged into one segment
+pub fn diff_words(a: &str, b: &str) -> MessageDiff {
+    let diff = TextDiff::from_words(a, b);
+    let mut segments: Vec<DiffSegment> = Vec::new();
+    let mut words_added = 0;
+    let mut words_removed = 0;
+
+    for change in diff.iter_all_changes() {
+        let op = match change.tag() {
+            ChangeTag::Equal => DiffOp::Equal,
+            ChangeTag::Insert => DiffOp::Insert,
+            ChangeTag::Delete => DiffOp::Delete,
+        };
+        let text = change.value();
+        let is_word = !text.trim().is_empty();
+        match op {
+            DiffOp::Insert if is_word => words_added += 1,
+            DiffOp::Delete if is_word => words_removed += 1,
+            _ => {}
+        }
+
+        match segments.last_mut() {
+            Some(last) if last.op == op => last.text.push_str(text),
+            _ => segments.push(DiffSegment {
+                op,
+                text: text.to_string(),
+            }),
+        }
+    }
+
+    MessageDiff {
+        segments,
+        words_added,
+        words_removed,
+    }
+}
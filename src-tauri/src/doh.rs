@@ -0,0 +1,104 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+// Addressed by IP so resolving the DoH endpoint itself never hits the local DNS
+const DEFAULT_ENDPOINT: &str = "https://1.1.1.1/dns-query";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DohSettings {
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+}
+
+impl DohSettings {
+    pub fn endpoint(&self) -> String {
+        self.endpoint
+            .clone()
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DnsAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DnsResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DnsAnswer>,
+}
+
+const RECORD_A: u16 = 1;
+const RECORD_AAAA: u16 = 28;
+
+// reqwest resolver override that uses the JSON DoH API (Cloudflare/Google style)
+pub struct DohResolver {
+    client: Client,
+    endpoint: String,
+}
+
+impl DohResolver {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap(),
+            endpoint,
+        }
+    }
+
+    async fn query(client: &Client, endpoint: &str, name: &str, record: &str) -> Vec<IpAddr> {
+        let response = client
+            .get(endpoint)
+            .query(&[("name", name), ("type", record)])
+            .header("accept", "application/dns-json")
+            .send()
+            .await;
+
+        let Ok(response) = response else {
+            return Vec::new();
+        };
+        let Ok(dns) = response.json::<DnsResponse>().await else {
+            return Vec::new();
+        };
+        if dns.status != 0 {
+            return Vec::new();
+        }
+
+        dns.answer
+            .iter()
+            .filter(|a| a.record_type == RECORD_A || a.record_type == RECORD_AAAA)
+            .filter_map(|a| a.data.parse().ok())
+            .collect()
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let client = self.client.clone();
+        let endpoint = self.endpoint.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let mut addrs = Self::query(&client, &endpoint, &host, "A").await;
+            addrs.extend(Self::query(&client, &endpoint, &host, "AAAA").await);
+
+            if addrs.is_empty() {
+                return Err(format!("DNS-over-HTTPS lookup failed for {}", host).into());
+            }
+
+            // reqwest replaces the port with the one from the request URL
+            let addrs: Addrs = Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
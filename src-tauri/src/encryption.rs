@@ -0,0 +1,264 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::secrets;
+use crate::storage;
+
+// Sealed files start with this, so plaintext stores from before encryption still load
+const MAGIC: &[u8] = b"SFENC1\n";
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+// Sealed with the key when encryption is enabled; opening it checks a passphrase
+const VERIFIER: &[u8] = b"sofragment-storage";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    // Random key kept in the OS keychain; unlocks automatically at startup
+    Keychain,
+    // Derived from a passphrase with PBKDF2; starts locked
+    Passphrase,
+}
+
+// Stored in plaintext next to the stores; it holds nothing that reveals the key
+#[derive(Serialize, Deserialize)]
+struct Config {
+    source: KeySource,
+    salt: String,
+    iterations: u32,
+    verifier: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageStatus {
+    pub enabled: bool,
+    pub locked: bool,
+    pub source: Option<KeySource>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Storage is locked")]
+pub struct Locked;
+
+enum KeyState {
+    Disabled,
+    Locked(KeySource),
+    Unlocked(KeySource, Box<LessSafeKey>),
+}
+
+static KEY: RwLock<KeyState> = RwLock::new(KeyState::Disabled);
+
+fn config_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("encryption.json")
+}
+
+fn load_config(data_dir: &Path) -> Result<Option<Config>> {
+    match fs::read(config_path(data_dir)) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn set_state(state: KeyState) {
+    *KEY.write().unwrap_or_else(|e| e.into_inner()) = state;
+}
+
+// Called before any store is loaded; a keychain key unlocks straight away
+pub fn init(data_dir: &Path) -> Result<()> {
+    let Some(config) = load_config(data_dir)? else {
+        set_state(KeyState::Disabled);
+        return Ok(());
+    };
+    set_state(KeyState::Locked(config.source));
+    if config.source == KeySource::Keychain {
+        unlock(data_dir, None)?;
+    }
+    Ok(())
+}
+
+pub fn status() -> StorageStatus {
+    match &*KEY.read().unwrap_or_else(|e| e.into_inner()) {
+        KeyState::Disabled => StorageStatus { enabled: false, locked: false, source: None },
+        KeyState::Locked(source) => StorageStatus { enabled: true, locked: true, source: Some(*source) },
+        KeyState::Unlocked(source, _) => StorageStatus { enabled: true, locked: false, source: Some(*source) },
+    }
+}
+
+// Without a passphrase the key is random and kept in the keychain. Existing plaintext
+// files are encrypted before this returns.
+pub fn enable(data_dir: &Path, passphrase: Option<&str>) -> Result<usize> {
+    if load_config(data_dir)?.is_some() {
+        bail!("Storage encryption is already enabled");
+    }
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    rng.fill(&mut salt).map_err(|_| anyhow!("No randomness available"))?;
+
+    let (source, key) = match passphrase {
+        Some(passphrase) => {
+            if passphrase.is_empty() {
+                bail!("The passphrase cannot be empty");
+            }
+            (KeySource::Passphrase, derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?)
+        }
+        None => {
+            let mut key = [0u8; KEY_LEN];
+            rng.fill(&mut key).map_err(|_| anyhow!("No randomness available"))?;
+            secrets::set_storage_key(&key)?;
+            (KeySource::Keychain, key)
+        }
+    };
+    let key = aead_key(&key)?;
+    let config = Config {
+        source,
+        salt: STANDARD.encode(salt),
+        iterations: PBKDF2_ITERATIONS,
+        verifier: STANDARD.encode(seal_with(&key, VERIFIER)?),
+    };
+    storage::save_plain_json(&config_path(data_dir), &config)?;
+    set_state(KeyState::Unlocked(source, Box::new(key)));
+    storage::encrypt_all(data_dir)
+}
+
+// The passphrase is ignored for a keychain key
+pub fn unlock(data_dir: &Path, passphrase: Option<&str>) -> Result<()> {
+    let config = load_config(data_dir)?.context("Storage encryption is not enabled")?;
+    let key = match config.source {
+        KeySource::Passphrase => {
+            let passphrase = passphrase.context("A passphrase is required to unlock storage")?;
+            let salt = STANDARD.decode(&config.salt)?;
+            derive_key(passphrase, &salt, config.iterations)?
+        }
+        KeySource::Keychain => {
+            let key = secrets::get_storage_key()?.context("The storage key is missing from the keychain")?;
+            key.try_into().map_err(|_| anyhow!("The storage key in the keychain is invalid"))?
+        }
+    };
+    let key = aead_key(&key)?;
+    let verifier = STANDARD.decode(&config.verifier)?;
+    if open_with(&key, &verifier).is_err() {
+        bail!("Wrong passphrase");
+    }
+    set_state(KeyState::Unlocked(config.source, Box::new(key)));
+    // Finishes a migration that was interrupted before every file was encrypted
+    storage::encrypt_all(data_dir)?;
+    Ok(())
+}
+
+pub fn lock() -> Result<()> {
+    let mut state = KEY.write().unwrap_or_else(|e| e.into_inner());
+    let source = match &*state {
+        KeyState::Disabled => bail!("Storage encryption is not enabled"),
+        KeyState::Locked(source) | KeyState::Unlocked(source, _) => *source,
+    };
+    *state = KeyState::Locked(source);
+    Ok(())
+}
+
+pub fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+// Plaintext passes through while encryption is off; nothing can be written while locked
+pub fn seal(plaintext: &[u8]) -> Result<Vec<u8>> {
+    match &*KEY.read().unwrap_or_else(|e| e.into_inner()) {
+        KeyState::Disabled => Ok(plaintext.to_vec()),
+        KeyState::Locked(_) => Err(Locked.into()),
+        KeyState::Unlocked(_, key) => seal_with(key, plaintext),
+    }
+}
+
+// Plaintext files load as they are, so stores written before encryption still open
+pub fn open(bytes: &[u8]) -> Result<Vec<u8>> {
+    if !is_sealed(bytes) {
+        return Ok(bytes.to_vec());
+    }
+    match &*KEY.read().unwrap_or_else(|e| e.into_inner()) {
+        KeyState::Unlocked(_, key) => open_with(key, bytes),
+        KeyState::Disabled | KeyState::Locked(_) => Err(Locked.into()),
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<[u8; KEY_LEN]> {
+    let iterations = NonZeroU32::new(iterations).context("Invalid key derivation settings")?;
+    let mut key = [0u8; KEY_LEN];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut key);
+    Ok(key)
+}
+
+fn aead_key(bytes: &[u8]) -> Result<LessSafeKey> {
+    let key = UnboundKey::new(&CHACHA20_POLY1305, bytes).map_err(|_| anyhow!("Invalid storage key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+// MAGIC || nonce || ciphertext and tag
+fn seal_with(key: &LessSafeKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("No randomness available"))?;
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + in_out.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend(in_out);
+    Ok(sealed)
+}
+
+fn open_with(key: &LessSafeKey, sealed: &[u8]) -> Result<Vec<u8>> {
+    let body = sealed.strip_prefix(MAGIC).context("Not an encrypted file")?;
+    if body.len() < NONCE_LEN {
+        bail!("Encrypted file is truncated");
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Invalid nonce"))?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| anyhow!("The file could not be decrypted"))?;
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(passphrase: &str) -> LessSafeKey {
+        aead_key(&derive_key(passphrase, b"0123456789abcdef", 1_000).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sealed_data_opens_with_the_same_key() {
+        let sealed = seal_with(&key("correct horse"), b"{\"facts\":[]}").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_eq!(open_with(&key("correct horse"), &sealed).unwrap(), b"{\"facts\":[]}");
+    }
+
+    #[test]
+    fn wrong_passphrase_does_not_open() {
+        let sealed = seal_with(&key("correct horse"), VERIFIER).unwrap();
+        assert!(open_with(&key("battery staple"), &sealed).is_err());
+    }
+
+    #[test]
+    fn tampered_data_does_not_open() {
+        let mut sealed = seal_with(&key("correct horse"), VERIFIER).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(open_with(&key("correct horse"), &sealed).is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use crate::ollama::{ChatMessage, GenerationOptions};
+use crate::search::SearchClient;
+use crate::storage;
+
+// Bumped when a field changes meaning; newer files are refused rather than misread
+const EXPORT_VERSION: u32 = 1;
+
+// Everything needed to restore a conversation, metadata and search results included.
+// Redaction maps are left out: the history already holds the original text.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationExport {
+    pub version: u32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub exported_at: DateTime<Utc>,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub options: GenerationOptions,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub no_web: bool,
+    #[serde(default)]
+    pub reply_language: Option<String>,
+}
+
+impl ConversationExport {
+    pub fn new(name: String, created_at: DateTime<Utc>, messages: Vec<ChatMessage>) -> Self {
+        Self {
+            version: EXPORT_VERSION,
+            name,
+            created_at,
+            exported_at: Utc::now(),
+            messages,
+            system_prompt: None,
+            summary: None,
+            options: GenerationOptions::default(),
+            tags: Vec::new(),
+            no_web: false,
+            reply_language: None,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        storage::save_plain_json(path, self)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let export: Self = serde_json::from_str(&text).context("Not a conversation export")?;
+        export.validate()?;
+        Ok(export)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.version == 0 || self.version > EXPORT_VERSION {
+            bail!("Unsupported export version {}", self.version);
+        }
+        if self.name.trim().is_empty() {
+            bail!("The export has no conversation name");
+        }
+        for (i, message) in self.messages.iter().enumerate() {
+            match message.role.as_str() {
+                "user" | "assistant" => {}
+                other => bail!("Message {} has an unexpected role: {}", i + 1, other),
+            }
+            if message.tool_calls.is_some() && message.role != "assistant" {
+                bail!("Message {} has tool calls but is not from the assistant", i + 1);
+            }
+        }
+        self.options.validate()
+    }
+}
+
+fn role_heading(role: &str) -> &str {
+    match role {
+        "user" => "You",
+        "assistant" => "Assistant",
+        "system" => "System",
+        other => other,
+    }
+}
+
+// Local time, for headings next to each message
+fn message_time(message: &ChatMessage) -> Option<String> {
+    message
+        .created_at
+        .map(|at| at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+}
+
+// Message content is Markdown already, so code blocks are kept as the model wrote them
+pub fn render_markdown(messages: &[ChatMessage], title: &str) -> String {
+    let mut markdown = format!("# {}\n\n", title);
+
+    for message in messages {
+        let heading = match message_time(message) {
+            Some(time) => format!("{} · {}", role_heading(&message.role), time),
+            None => role_heading(&message.role).to_string(),
+        };
+        markdown.push_str(&format!("## {}\n\n{}\n\n", heading, message.content.trim()));
+
+        let sources = message
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.search_results.as_ref())
+            .filter(|results| !results.is_empty());
+        if let Some(results) = sources {
+            markdown.push_str("**Sources**\n\n");
+            for result in results {
+                match &result.favicon_url {
+                    Some(icon) => markdown.push_str(&format!(
+                        "- ![]({}) [{}]({})\n",
+                        icon, result.title, result.url
+                    )),
+                    None => markdown.push_str(&format!("- [{}]({})\n", result.title, result.url)),
+                }
+            }
+            markdown.push('\n');
+        }
+    }
+
+    markdown
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Code fences become <pre> blocks, everything else keeps its line breaks
+fn content_to_html(content: &str) -> String {
+    content
+        .split("```")
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                // Drop the language tag on the opening fence line
+                let code = part.split_once('\n').map_or(part, |(_, code)| code);
+                format!("<pre><code>{}</code></pre>", escape_html(code.trim_end()))
+            } else if part.trim().is_empty() {
+                String::new()
+            } else {
+                format!("<p>{}</p>", escape_html(part.trim()).replace('\n', "<br>"))
+            }
+        })
+        .collect()
+}
+
+const PRINT_CSS: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Roboto, sans-serif; font-size: 11pt; margin: 0; }
+header { position: fixed; top: 0; left: 0; right: 0; padding: 6pt 0; border-bottom: 1px solid #ccc;
+         font-size: 9pt; color: #555; display: flex; justify-content: space-between; background: white; }
+main { margin-top: 36pt; }
+.message { page-break-inside: avoid; margin-bottom: 14pt; }
+.role { font-weight: bold; margin-bottom: 4pt; }
+pre { background: #f4f4f4; padding: 6pt; white-space: pre-wrap; font-size: 9pt; }
+.footnotes { font-size: 8pt; color: #444; border-top: 1px dotted #bbb; padding-top: 4pt; }
+@page { margin: 18mm 15mm; }
+"#;
+
+// Print layout with a repeating page header and numbered citation footnotes
+pub fn render_html(messages: &[ChatMessage], title: &str, exported_at: &str) -> String {
+    let mut body = String::new();
+    let mut footnote = 0;
+
+    for message in messages {
+        body.push_str(&format!(
+            "<section class=\"message\"><div class=\"role\">{}</div>{}",
+            escape_html(role_heading(&message.role)),
+            content_to_html(&message.content)
+        ));
+
+        let sources = message
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.search_results.as_ref())
+            .filter(|results| !results.is_empty());
+        if let Some(results) = sources {
+            body.push_str("<div class=\"footnotes\">");
+            for result in results {
+                footnote += 1;
+                body.push_str(&format!(
+                    "<div><sup>{}</sup> {} — {}</div>",
+                    footnote,
+                    escape_html(&result.title),
+                    escape_html(&result.url)
+                ));
+            }
+            body.push_str("</div>");
+        }
+
+        body.push_str("</section>");
+    }
+
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <style>{css}</style></head><body>\
+         <header><span>{title}</span><span>Exported {exported_at}</span></header>\
+         <main>{body}</main></body></html>",
+        title = escape_html(title),
+        css = PRINT_CSS,
+        exported_at = escape_html(exported_at),
+        body = body
+    )
+}
+
+fn extension_for(content_type: &str) -> &'static str {
+    match content_type.split(';').next().unwrap_or_default().trim() {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        _ => "img",
+    }
+}
+
+fn asset_file_name(url: &str, content_type: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    format!("{:016x}.{}", hasher.finish(), extension_for(content_type))
+}
+
+// Download remote images into "<name>_assets/" next to the export and point the links there.
+// Images that cannot be fetched keep their original remote link.
+pub async fn bundle_assets(markdown: &str, export_path: &Path, client: &SearchClient) -> Result<String> {
+    let image_re = Regex::new(r"!\[([^\]]*)\]\((https?://[^)\s]+)\)")?;
+
+    let mut urls: Vec<String> = image_re
+        .captures_iter(markdown)
+        .map(|caps| caps[2].to_string())
+        .collect();
+    urls.sort();
+    urls.dedup();
+
+    let stem = export_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "conversation".to_string());
+    let assets_dir_name = format!("{}_assets", stem);
+    let assets_dir = export_path.with_file_name(&assets_dir_name);
+
+    let mut local_paths: HashMap<String, String> = HashMap::new();
+    for url in urls {
+        match client.fetch_image(&url).await {
+            Ok(Some((bytes, content_type))) => {
+                fs::create_dir_all(&assets_dir)?;
+                let file_name = asset_file_name(&url, &content_type);
+                fs::write(assets_dir.join(&file_name), bytes)?;
+                local_paths.insert(url, format!("{}/{}", assets_dir_name, file_name));
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to download asset {}: {:?}", url, e),
+        }
+    }
+
+    Ok(image_re
+        .replace_all(markdown, |caps: &Captures| match local_paths.get(&caps[2]) {
+            Some(path) => format!("![{}]({})", &caps[1], path),
+            None => caps[0].to_string(),
+        })
+        .into_owned())
+}
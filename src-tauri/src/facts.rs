@@ -0,0 +1,294 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::storage;
+
+const DEFAULT_TOPIC: &str = "General";
+// Saved facts offered to the model with each message
+pub const RELEVANT_FACTS: usize = 5;
+// Shorter words ("the", "is") say nothing about relevance
+const MIN_KEYWORD_LEN: usize = 4;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Fact {
+    pub id: String,
+    pub text: String,
+    pub topic: String,
+    // Where the fact came from, e.g. a URL or "user"
+    pub source: Option<String>,
+    pub conversation_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct FactStore {
+    path: PathBuf,
+    facts: Vec<Fact>,
+}
+
+// "Rust / Async" -> "rust-async", used for note file names
+fn topic_slug(topic: &str) -> String {
+    let slug: String = topic
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "general".to_string()
+    } else {
+        slug
+    }
+}
+
+// The three LEARNING items SYSTEM_PROMPT asks for; only the first holds facts, the other
+// two are about how the answer was made
+#[derive(Clone, Copy, PartialEq)]
+enum LearningItem {
+    NewInformation,
+    Context,
+    Searches,
+}
+
+// Whole lines that say there is nothing to learn
+const NOTHING_NEW: [&str; 5] = ["none", "nothing", "nothing new", "no new information", "n/a"];
+
+// The item a label or an echoed placeholder ("What context was most useful") starts
+fn learning_item(label: &str) -> Option<LearningItem> {
+    let label = label.to_lowercase();
+    // Longer than any label, so a fact with a colon in it is not taken for one
+    if label.split_whitespace().count() > 8 {
+        return None;
+    }
+    if label.contains("new information") {
+        Some(LearningItem::NewInformation)
+    } else if label.contains("context") {
+        Some(LearningItem::Context)
+    } else if label.contains("search") {
+        Some(LearningItem::Searches)
+    } else {
+        None
+    }
+}
+
+// The new information in a reply's LEARNING section, one fact per line or bullet. Lines
+// before any label count as new information.
+pub fn parse_learning(learning: &str) -> Vec<String> {
+    let mut item = LearningItem::NewInformation;
+    let mut facts = Vec::new();
+    for line in learning.lines() {
+        let line = line.trim().trim_start_matches(['-', '*', '•']).trim();
+        let line = line.trim_start_matches('[').trim_end_matches(']').trim();
+        let text = match line.split_once(':') {
+            Some((label, rest)) if learning_item(label).is_some() => {
+                item = learning_item(label).unwrap_or(item);
+                rest.trim()
+            }
+            _ if line.to_lowercase().starts_with("what ") && learning_item(line).is_some() => {
+                item = learning_item(line).unwrap_or(item);
+                continue;
+            }
+            _ => line,
+        };
+        let nothing = NOTHING_NEW.contains(&text.trim_end_matches('.').to_lowercase().as_str());
+        if item == LearningItem::NewInformation && !text.is_empty() && !nothing {
+            facts.push(text.to_string());
+        }
+    }
+    facts
+}
+
+fn keywords(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_KEYWORD_LEN)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// For the FACTS_CHECK step; None when nothing was found
+pub fn facts_prompt(facts: &[Fact]) -> Option<String> {
+    if facts.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = facts.iter().map(|f| format!("- {} ({})", f.text, f.topic)).collect();
+    Some(format!("Facts from your database:\n{}", lines.join("\n")))
+}
+
+impl FactStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("facts.json");
+        let facts = storage::load_json(&path);
+        Self { path, facts }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.facts)
+    }
+
+    pub fn add(
+        &mut self,
+        text: &str,
+        topic: Option<&str>,
+        source: Option<String>,
+        conversation_id: Option<String>,
+    ) -> Result<Fact> {
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("Fact text is empty");
+        }
+        let topic = topic
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .unwrap_or(DEFAULT_TOPIC);
+        let fact = Fact {
+            id: Uuid::new_v4().to_string(),
+            text: text.to_string(),
+            topic: topic.to_string(),
+            source,
+            conversation_id,
+            created_at: Utc::now(),
+        };
+        self.facts.push(fact.clone());
+        self.save()?;
+        Ok(fact)
+    }
+
+    // Skips facts already stored with the same text; saves once for the whole batch
+    pub fn add_learned(&mut self, texts: Vec<String>, conversation_id: &str) -> Result<Vec<Fact>> {
+        let mut added = Vec::new();
+        for text in texts {
+            let known = self.facts.iter().any(|f| f.text.eq_ignore_ascii_case(&text));
+            if known {
+                continue;
+            }
+            let fact = Fact {
+                id: Uuid::new_v4().to_string(),
+                text,
+                topic: DEFAULT_TOPIC.to_string(),
+                source: Some("learning".to_string()),
+                conversation_id: Some(conversation_id.to_string()),
+                created_at: Utc::now(),
+            };
+            self.facts.push(fact.clone());
+            added.push(fact);
+        }
+        if !added.is_empty() {
+            self.save()?;
+        }
+        Ok(added)
+    }
+
+    // The facts sharing the most keywords with `query`, best first
+    pub fn relevant(&self, query: &str, limit: usize) -> Vec<Fact> {
+        let query = keywords(query);
+        let mut scored: Vec<(usize, &Fact)> = self
+            .facts
+            .iter()
+            .map(|fact| {
+                let words = keywords(&format!("{} {}", fact.text, fact.topic));
+                (query.iter().filter(|k| words.contains(k)).count(), fact)
+            })
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        scored.sort_by_key(|(score, fact)| (std::cmp::Reverse(*score), std::cmp::Reverse(fact.created_at)));
+        scored.into_iter().take(limit).map(|(_, fact)| fact.clone()).collect()
+    }
+
+    pub fn list(&self) -> Vec<Fact> {
+        let mut facts = self.facts.clone();
+        facts.sort_by(|a, b| a.topic.cmp(&b.topic).then(a.created_at.cmp(&b.created_at)));
+        facts
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<bool> {
+        let before = self.facts.len();
+        self.facts.retain(|f| f.id != id);
+        let removed = self.facts.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    // One Markdown note per topic plus an index linking them; existing notes are overwritten
+    pub fn export_markdown(&self, folder: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(folder)?;
+
+        let mut topics: BTreeMap<String, (String, Vec<&Fact>)> = BTreeMap::new();
+        for fact in &self.facts {
+            topics
+                .entry(topic_slug(&fact.topic))
+                .or_insert_with(|| (fact.topic.clone(), Vec::new()))
+                .1
+                .push(fact);
+        }
+
+        let mut written = Vec::new();
+        let mut index = String::from("# Facts\n\n");
+        for (slug, (topic, mut facts)) in topics {
+            facts.sort_by_key(|f| f.created_at);
+
+            let mut note = format!("# {}\n\n", topic);
+            for fact in &facts {
+                note.push_str(&format!("- {}\n", fact.text.replace('\n', " ")));
+                if let Some(source) = &fact.source {
+                    note.push_str(&format!("  - Source: {}\n", source));
+                }
+                note.push_str(&format!("  - Date: {}\n", fact.created_at.format("%Y-%m-%d")));
+                if let Some(conversation_id) = &fact.conversation_id {
+                    note.push_str(&format!("  - Conversation: {}\n", conversation_id));
+                }
+            }
+
+            let file_name = format!("{}.md", slug);
+            let path = folder.join(&file_name);
+            fs::write(&path, note)?;
+            written.push(path);
+            index.push_str(&format!("- [{}]({}) ({})\n", topic, file_name, facts.len()));
+        }
+
+        // Slugs never start with an underscore, so this cannot clash with a topic note
+        let index_path = folder.join("_index.md");
+        fs::write(&index_path, index)?;
+        written.push(index_path);
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_new_information_becomes_facts() {
+        let learning = "- New information: The user's cluster runs Kubernetes 1.29\n\
+                        - Useful context: the earlier answer about node pools\n\
+                        - Helpful searches: none";
+        assert_eq!(parse_learning(learning), vec!["The user's cluster runs Kubernetes 1.29"]);
+    }
+
+    #[test]
+    fn nothing_new_only_matches_whole_lines() {
+        assert!(parse_learning("New information: None.\nNothing new").is_empty());
+        assert_eq!(
+            parse_learning("The none-of-the-above option was removed in v2"),
+            vec!["The none-of-the-above option was removed in v2"]
+        );
+    }
+
+    #[test]
+    fn echoed_placeholders_are_not_facts() {
+        let learning = "[What new information should be saved to facts]\n\
+                        [What context was most useful]\n\
+                        The project docs";
+        assert!(parse_learning(learning).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::storage;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+// A rated answer; the reply and its prompt are copied because conversations are not kept
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Feedback {
+    pub conversation_id: String,
+    // Where the answer was when rated; deletes and edits move messages, so this is
+    // only used to tell answers apart when they have no timestamp
+    pub message_index: usize,
+    // Identifies the answer within its conversation
+    #[serde(default)]
+    pub message_created_at: Option<DateTime<Utc>>,
+    pub rating: Rating,
+    pub comment: Option<String>,
+    pub model: Option<String>,
+    pub prompt: Option<String>,
+    pub response: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Feedback {
+    fn same_message(&self, other: &Feedback) -> bool {
+        if self.conversation_id != other.conversation_id {
+            return false;
+        }
+        match (self.message_created_at, other.message_created_at) {
+            (Some(a), Some(b)) => a == b,
+            (None, None) => self.message_index == other.message_index,
+            _ => false,
+        }
+    }
+}
+
+pub struct FeedbackStore {
+    path: PathBuf,
+    feedback: Vec<Feedback>,
+}
+
+impl FeedbackStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("feedback.json");
+        let feedback = storage::load_json(&path);
+        Self { path, feedback }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.feedback)
+    }
+
+    // Rating the same message again replaces the earlier rating
+    pub fn rate(&mut self, feedback: Feedback) -> Result<Feedback> {
+        self.feedback.retain(|f| !f.same_message(&feedback));
+        self.feedback.push(feedback.clone());
+        self.save()?;
+        Ok(feedback)
+    }
+
+    pub fn count_for(&self, conversation_ids: &HashSet<&str>) -> usize {
+        self.feedback
+            .iter()
+            .filter(|f| conversation_ids.contains(f.conversation_id.as_str()))
+            .count()
+    }
+
+    pub fn delete_for(&mut self, conversation_ids: &HashSet<&str>) -> Result<usize> {
+        let before = self.feedback.len();
+        self.feedback.retain(|f| !conversation_ids.contains(f.conversation_id.as_str()));
+        let removed = before - self.feedback.len();
+        if removed > 0 {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    // Newest first
+    pub fn list(&self, rating: Option<Rating>, model: Option<&str>) -> Vec<Feedback> {
+        let mut feedback: Vec<Feedback> = self
+            .feedback
+            .iter()
+            .filter(|f| rating.is_none_or(|r| f.rating == r))
+            .filter(|f| model.is_none_or(|m| f.model.as_deref() == Some(m)))
+            .cloned()
+            .collect();
+        feedback.sort_by_key(|f| std::cmp::Reverse(f.created_at));
+        feedback
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// Matching runs in the conversation store's FTS5 index; this is the query syntax and
+// snippet formatting around it
+
+pub const DEFAULT_LIMIT: usize = 50;
+// Tokens of context FTS5 keeps around the hits in a snippet
+pub const SNIPPET_TOKENS: i64 = 24;
+// Put around each hit by FTS5; control characters never appear in a message
+pub const MARK_START: &str = "\u{2}";
+pub const MARK_END: &str = "\u{3}";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MessageMatch {
+    pub conversation_id: String,
+    pub conversation_name: String,
+    // Position in the conversation's history
+    pub message_index: usize,
+    pub role: String,
+    pub created_at: Option<DateTime<Utc>>,
+    // HTML-escaped excerpt with the matching words wrapped in <mark>
+    pub snippet: String,
+    pub score: f64,
+}
+
+// Words of the query, split the way FTS5's unicode61 tokenizer splits them
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Every term has to appear, as a whole word or the start of one. Terms are quoted so
+// operators typed by the user (OR, NEAR, column filters) are searched as plain words.
+// None when the query has no searchable words.
+pub fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = words(query).into_iter().map(|w| format!("\"{}\"*", w)).collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+// FTS5 snippet with markers -> escaped HTML with <mark>
+pub fn highlight(snippet: &str) -> String {
+    let snippet = escape_html(snippet)
+        .replace(MARK_START, "<mark>")
+        .replace(MARK_END, "</mark>");
+    snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_terms_are_quoted_prefixes() {
+        assert_eq!(fts_query("rust OR \"tokio\"").as_deref(), Some("\"rust\"* \"or\"* \"tokio\"*"));
+        assert_eq!(fts_query("  -- ?"), None);
+    }
+
+    #[test]
+    fn snippet_is_escaped_around_marks() {
+        let snippet = format!("a <b> {}match{} &\n c", MARK_START, MARK_END);
+        assert_eq!(highlight(&snippet), "a &lt;b&gt; <mark>match</mark> &amp; c");
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::storage;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+// What a job does, with everything needed to pick it up again after a restart
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobKind {
+    Snapshot { urls: Vec<String> },
+    // Progress is tracked in percent; Ollama resumes partial downloads itself
+    ModelPull { model: String },
+}
+
+impl JobKind {
+    fn total_steps(&self) -> usize {
+        match self {
+            JobKind::Snapshot { urls } => urls.len(),
+            JobKind::ModelPull { .. } => 100,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    // 0-100
+    pub progress: f32,
+    pub completed_steps: usize,
+    pub total_steps: usize,
+    pub message: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Held by the task doing the work
+pub struct JobWorker {
+    pub id: String,
+    cancel: CancellationToken,
+    paused: watch::Receiver<bool>,
+}
+
+impl JobWorker {
+    // Call between steps: waits while the job is paused, false once it is cancelled
+    pub async fn checkpoint(&mut self) -> bool {
+        loop {
+            if self.cancel.is_cancelled() {
+                return false;
+            }
+            if !*self.paused.borrow() {
+                return true;
+            }
+            tokio::select! {
+                changed = self.paused.changed() => {
+                    if changed.is_err() {
+                        return false;
+                    }
+                }
+                _ = self.cancel.cancelled() => return false,
+            }
+        }
+    }
+}
+
+// Held by the manager for each job with a live worker
+struct JobControl {
+    cancel: CancellationToken,
+    paused: watch::Sender<bool>,
+}
+
+pub struct JobManager {
+    path: PathBuf,
+    jobs: Vec<Job>,
+    controls: HashMap<String, JobControl>,
+}
+
+impl JobManager {
+    // Jobs interrupted by a restart come back paused, to be resumed explicitly
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("jobs.json");
+        let mut jobs: Vec<Job> = storage::load_json(&path);
+        for job in jobs.iter_mut().filter(|j| j.status == JobStatus::Running) {
+            job.status = JobStatus::Paused;
+            job.message = Some("Interrupted by restart".to_string());
+        }
+        Self {
+            path,
+            jobs,
+            controls: HashMap::new(),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.jobs)
+    }
+
+    fn job_mut(&mut self, id: &str) -> Result<&mut Job> {
+        self.jobs
+            .iter_mut()
+            .find(|j| j.id == id)
+            .with_context(|| format!("No job: {}", id))
+    }
+
+    fn attach_worker(&mut self, id: &str) -> JobWorker {
+        let cancel = CancellationToken::new();
+        let (paused, paused_rx) = watch::channel(false);
+        self.controls.insert(
+            id.to_string(),
+            JobControl {
+                cancel: cancel.clone(),
+                paused,
+            },
+        );
+        JobWorker {
+            id: id.to_string(),
+            cancel,
+            paused: paused_rx,
+        }
+    }
+
+    pub fn create(&mut self, kind: JobKind) -> Result<(Job, JobWorker)> {
+        let now = Utc::now();
+        let job = Job {
+            id: Uuid::new_v4().to_string(),
+            total_steps: kind.total_steps(),
+            kind,
+            status: JobStatus::Running,
+            progress: 0.0,
+            completed_steps: 0,
+            message: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.jobs.push(job.clone());
+        self.save()?;
+        let worker = self.attach_worker(&job.id);
+        Ok((job, worker))
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.iter().find(|j| j.id == id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        let mut jobs = self.jobs.clone();
+        jobs.sort_by_key(|j| std::cmp::Reverse(j.created_at));
+        jobs
+    }
+
+    pub fn pause(&mut self, id: &str) -> Result<Job> {
+        let job = self.job_mut(id)?;
+        if job.status != JobStatus::Running {
+            bail!("Job {} is not running", id);
+        }
+        job.status = JobStatus::Paused;
+        job.updated_at = Utc::now();
+        let job = job.clone();
+        if let Some(control) = self.controls.get(id) {
+            control.paused.send_replace(true);
+        }
+        self.save()?;
+        Ok(job)
+    }
+
+    // Returns a new worker when the job has none (it was interrupted by a restart)
+    pub fn resume(&mut self, id: &str) -> Result<(Job, Option<JobWorker>)> {
+        let job = self.job_mut(id)?;
+        if job.status != JobStatus::Paused {
+            bail!("Job {} is not paused", id);
+        }
+        job.status = JobStatus::Running;
+        job.message = None;
+        job.updated_at = Utc::now();
+        let job = job.clone();
+        let worker = match self.controls.get(id) {
+            Some(control) => {
+                control.paused.send_replace(false);
+                None
+            }
+            None => Some(self.attach_worker(id)),
+        };
+        self.save()?;
+        Ok((job, worker))
+    }
+
+    pub fn cancel(&mut self, id: &str) -> Result<Job> {
+        let job = self.job_mut(id)?;
+        if job.status.is_finished() {
+            bail!("Job {} has already finished", id);
+        }
+        job.status = JobStatus::Cancelled;
+        job.updated_at = Utc::now();
+        let job = job.clone();
+        if let Some(control) = self.controls.remove(id) {
+            control.cancel.cancel();
+        }
+        self.save()?;
+        Ok(job)
+    }
+
+    pub fn set_progress(&mut self, id: &str, completed_steps: usize, message: Option<String>) -> Result<Job> {
+        let job = self.job_mut(id)?;
+        job.completed_steps = completed_steps.min(job.total_steps);
+        job.progress = if job.total_steps == 0 {
+            100.0
+        } else {
+            job.completed_steps as f32 * 100.0 / job.total_steps as f32
+        };
+        job.message = message;
+        job.updated_at = Utc::now();
+        let job = job.clone();
+        self.save()?;
+        Ok(job)
+    }
+
+    // Completed or Failed; a cancelled job keeps its status
+    pub fn finish(&mut self, id: &str, error: Option<String>) -> Result<Job> {
+        self.controls.remove(id);
+        let job = self.job_mut(id)?;
+        if !job.status.is_finished() {
+            job.status = if error.is_some() {
+                JobStatus::Failed
+            } else {
+                JobStatus::Completed
+            };
+            job.error = error;
+            job.updated_at = Utc::now();
+        }
+        let job = job.clone();
+        self.save()?;
+        Ok(job)
+    }
+
+    pub fn clear_finished(&mut self) -> Result<()> {
+        self.jobs.retain(|j| !j.status.is_finished());
+        self.save()
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::storage;
+
+// The knowledge base: sources saved locally so they stay readable even if the page disappears
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub saved_at: DateTime<Utc>,
+    pub html_file: String,
+    pub markdown_file: String,
+    pub word_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotContent {
+    pub snapshot: Snapshot,
+    pub markdown: String,
+}
+
+pub struct KnowledgeBase {
+    dir: PathBuf,
+    index_path: PathBuf,
+    snapshots: Vec<Snapshot>,
+}
+
+impl KnowledgeBase {
+    pub fn load(data_dir: &Path) -> Self {
+        let dir = data_dir.join("snapshots");
+        let index_path = dir.join("index.json");
+        let snapshots = storage::load_json(&index_path);
+        Self {
+            dir,
+            index_path,
+            snapshots,
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.index_path, &self.snapshots)
+    }
+
+    pub fn add_snapshot(&mut self, url: &str, page_html: &str) -> Result<Snapshot> {
+        let page = clean_page(page_html);
+        let id = Uuid::new_v4().to_string();
+        let html_file = format!("{}.html", id);
+        let markdown_file = format!("{}.md", id);
+
+        storage::write(&self.dir.join(&html_file), page.html.as_bytes())?;
+        storage::write(
+            &self.dir.join(&markdown_file),
+            format!("# {}\n\nSource: <{}>\n\n{}\n", page.title, url, page.markdown).as_bytes(),
+        )?;
+
+        let snapshot = Snapshot {
+            id,
+            url: url.to_string(),
+            title: page.title,
+            saved_at: Utc::now(),
+            html_file,
+            markdown_file,
+            word_count: page.markdown.split_whitespace().count(),
+        };
+        self.snapshots.push(snapshot.clone());
+        self.save()?;
+        Ok(snapshot)
+    }
+
+    pub fn list(&self) -> Vec<Snapshot> {
+        let mut snapshots = self.snapshots.clone();
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.saved_at));
+        snapshots
+    }
+
+    pub fn get(&self, id: &str) -> Result<SnapshotContent> {
+        let snapshot = self
+            .snapshots
+            .iter()
+            .find(|s| s.id == id)
+            .cloned()
+            .with_context(|| format!("No snapshot: {}", id))?;
+        let markdown = storage::read_to_string(&self.dir.join(&snapshot.markdown_file))?;
+        Ok(SnapshotContent { snapshot, markdown })
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<bool> {
+        let Some(index) = self.snapshots.iter().position(|s| s.id == id) else {
+            return Ok(false);
+        };
+        let snapshot = self.snapshots.remove(index);
+        let _ = fs::remove_file(self.dir.join(&snapshot.html_file));
+        let _ = fs::remove_file(self.dir.join(&snapshot.markdown_file));
+        self.save()?;
+        Ok(true)
+    }
+}
+
+struct CleanPage {
+    title: String,
+    html: String,
+    markdown: String,
+}
+
+// Page chrome and active content that never belongs in a snapshot
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "iframe", "form", "nav", "footer", "header",
+    "aside", "svg", "button", "template",
+];
+
+const KEPT_TAGS: &[&str] = &[
+    "h1", "h2", "h3", "h4", "h5", "h6", "p", "a", "ul", "ol", "li", "blockquote",
+    "pre", "code", "strong", "b", "em", "i", "img", "br", "table", "thead", "tbody",
+    "tr", "th", "td", "figure", "figcaption",
+];
+
+fn clean_page(page_html: &str) -> CleanPage {
+    let document = Html::parse_document(page_html);
+
+    let title = Selector::parse("title")
+        .ok()
+        .and_then(|sel| document.select(&sel).next())
+        .map(|el| el.text().collect::<String>().trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| "Untitled page".to_string());
+
+    // Prefer the main content element, as extraction does
+    let root = ["article", "main", "[role='main']", ".content", "body"]
+        .iter()
+        .filter_map(|selector| Selector::parse(selector).ok())
+        .find_map(|sel| document.select(&sel).next());
+
+    let mut body = String::new();
+    let mut markdown = String::new();
+    if let Some(root) = root {
+        write_clean_html(root, &mut body);
+        write_markdown(root, &mut markdown);
+    }
+
+    let html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title></head><body>{}</body></html>",
+        escape(&title),
+        body
+    );
+
+    CleanPage {
+        title,
+        html,
+        markdown: tidy_markdown(&markdown),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn is_skipped(element: &ElementRef) -> bool {
+    SKIPPED_TAGS.contains(&element.value().name())
+}
+
+// Re-serialize only structural tags, dropping attributes other than links and image sources
+fn write_clean_html(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape(text)),
+            Node::Element(_) => {
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                if is_skipped(&child) {
+                    continue;
+                }
+
+                let name = child.value().name();
+                if !KEPT_TAGS.contains(&name) {
+                    write_clean_html(child, out);
+                    continue;
+                }
+
+                out.push('<');
+                out.push_str(name);
+                for attr in ["href", "src", "alt"] {
+                    if let Some(value) = child.value().attr(attr) {
+                        out.push_str(&format!(" {}=\"{}\"", attr, escape(value)));
+                    }
+                }
+                out.push('>');
+                if name != "img" && name != "br" {
+                    write_clean_html(child, out);
+                    out.push_str(&format!("</{}>", name));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_markdown(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => {
+                let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !collapsed.is_empty() {
+                    if text.starts_with(char::is_whitespace) && !out.ends_with([' ', '\n']) {
+                        out.push(' ');
+                    }
+                    out.push_str(&collapsed);
+                    if text.ends_with(char::is_whitespace) {
+                        out.push(' ');
+                    }
+                }
+            }
+            Node::Element(_) => {
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                if !is_skipped(&child) {
+                    write_markdown_element(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_markdown_element(element: ElementRef, out: &mut String) {
+    let name = element.value().name();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            out.push_str(&format!("\n\n{} ", "#".repeat(level)));
+            write_markdown(element, out);
+            out.push_str("\n\n");
+        }
+        "p" | "div" | "section" | "figure" | "table" => {
+            out.push_str("\n\n");
+            write_markdown(element, out);
+            out.push_str("\n\n");
+        }
+        "br" | "tr" => out.push('\n'),
+        "li" => {
+            out.push_str("\n- ");
+            write_markdown(element, out);
+        }
+        "blockquote" => {
+            out.push_str("\n\n> ");
+            write_markdown(element, out);
+            out.push_str("\n\n");
+        }
+        "pre" => {
+            let code = element.text().collect::<String>();
+            out.push_str(&format!("\n\n```\n{}\n```\n\n", code.trim_end()));
+        }
+        "code" => out.push_str(&format!("`{}`", element.text().collect::<String>())),
+        "strong" | "b" => {
+            out.push_str("**");
+            write_markdown(element, out);
+            out.push_str("**");
+        }
+        "em" | "i" => {
+            out.push('*');
+            write_markdown(element, out);
+            out.push('*');
+        }
+        "a" => match element.value().attr("href") {
+            Some(href) => {
+                out.push('[');
+                write_markdown(element, out);
+                out.push_str(&format!("]({})", href));
+            }
+            None => write_markdown(element, out),
+        },
+        "img" => {
+            if let Some(src) = element.value().attr("src") {
+                let alt = element.value().attr("alt").unwrap_or_default();
+                out.push_str(&format!("![{}]({})", alt, src));
+            }
+        }
+        "td" | "th" => {
+            write_markdown(element, out);
+            out.push_str(" | ");
+        }
+        _ => write_markdown(element, out),
+    }
+}
+
+// Collapse runs of blank lines and stray indentation left by the tree walk,
+// leaving code blocks untouched
+fn tidy_markdown(markdown: &str) -> String {
+    let mut tidy = String::new();
+    let mut blank_lines = 0;
+    let mut in_code = false;
+    for line in markdown.lines().map(str::trim_end) {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            tidy.push_str(line.trim_start());
+            tidy.push('\n');
+            continue;
+        }
+        if in_code {
+            tidy.push_str(line);
+            tidy.push('\n');
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            blank_lines += 1;
+            if blank_lines > 1 {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+        tidy.push_str(line.trim_start_matches(' '));
+        tidy.push('\n');
+    }
+    tidy.trim().to_string()
+}
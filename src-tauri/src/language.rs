@@ -0,0 +1,87 @@
+// Lightweight language detection for incoming messages: the writing system
+// settles most languages, common function words tell Latin-script ones apart
+
+// Below this many letters there is too little to go on
+const MIN_LETTERS: usize = 8;
+// Function-word hits needed before a Latin-script language is chosen
+const MIN_WORD_HITS: usize = 2;
+
+const LATIN_LANGUAGES: &[(&str, &[&str])] = &[
+    ("English", &["the", "and", "is", "are", "what", "how", "you", "this", "that", "with", "of", "to", "can", "do", "i", "my", "it", "for"]),
+    ("Spanish", &["el", "la", "los", "las", "que", "es", "y", "de", "en", "por", "para", "cómo", "qué", "una", "con", "mi", "está", "puedes"]),
+    ("French", &["le", "la", "les", "et", "est", "de", "des", "un", "une", "que", "pour", "avec", "je", "vous", "comment", "pourquoi", "c'est", "dans"]),
+    ("German", &["der", "die", "das", "und", "ist", "nicht", "ich", "du", "wie", "was", "mit", "ein", "eine", "für", "auf", "kannst", "warum", "zu"]),
+    ("Portuguese", &["o", "os", "as", "e", "é", "de", "do", "da", "que", "não", "um", "uma", "para", "com", "como", "você", "em", "por"]),
+    ("Italian", &["il", "lo", "gli", "e", "è", "di", "che", "non", "un", "una", "per", "con", "come", "perché", "sono", "mi", "puoi", "della"]),
+    ("Dutch", &["de", "het", "een", "en", "is", "van", "niet", "ik", "je", "wat", "hoe", "met", "voor", "op", "dat", "kun", "waarom", "zijn"]),
+];
+
+fn script_language(letters: &[char]) -> Option<&'static str> {
+    let count = |range: std::ops::RangeInclusive<char>| letters.iter().filter(|c| range.contains(c)).count();
+    let kana = count('\u{3040}'..='\u{30ff}');
+    let han = count('\u{4e00}'..='\u{9fff}');
+    let threshold = letters.len().div_ceil(3);
+
+    // Japanese mixes kana with Han characters; Han alone means Chinese
+    if kana > 0 && kana + han >= threshold {
+        return Some("Japanese");
+    }
+    let scripts = [
+        (han, "Chinese"),
+        (count('\u{ac00}'..='\u{d7af}'), "Korean"),
+        (count('\u{0400}'..='\u{04ff}'), "Russian"),
+        (count('\u{0600}'..='\u{06ff}'), "Arabic"),
+        (count('\u{0590}'..='\u{05ff}'), "Hebrew"),
+        (count('\u{0370}'..='\u{03ff}'), "Greek"),
+        (count('\u{0e00}'..='\u{0e7f}'), "Thai"),
+        (count('\u{0900}'..='\u{097f}'), "Hindi"),
+    ];
+    let (_, language) = scripts.into_iter().find(|(hits, _)| *hits >= threshold)?;
+    if language == "Russian" && letters.iter().any(|c| matches!(c, 'і' | 'ї' | 'є' | 'ґ')) {
+        return Some("Ukrainian");
+    }
+    Some(language)
+}
+
+fn latin_language(text: &str) -> Option<&'static str> {
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphabetic() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut best: Option<(&str, usize)> = None;
+    for (language, function_words) in LATIN_LANGUAGES {
+        let hits = words.iter().filter(|w| function_words.contains(&w.as_str())).count();
+        if best.is_none_or(|(_, most)| hits > most) {
+            best = Some((language, hits));
+        }
+    }
+    best.filter(|(_, hits)| *hits >= MIN_WORD_HITS).map(|(language, _)| language)
+}
+
+// English name of the message's language, or None when it is too short or unclear.
+// Code blocks and URLs are skipped since they are mostly English keywords.
+pub fn detect(text: &str) -> Option<&'static str> {
+    let prose: String = text
+        .split("```")
+        .step_by(2)
+        .flat_map(|part| part.split_whitespace())
+        .filter(|word| !word.contains("://"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let letters: Vec<char> = prose.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() < MIN_LETTERS {
+        return None;
+    }
+    script_language(&letters).or_else(|| latin_language(&prose))
+}
+
+// Sent after the structured system prompt, which would otherwise pull answers into English
+pub fn reply_directive(language: &str) -> String {
+    format!(
+        "Write every section of your answer in {}. Keep the section headings (CONTEXT_CHECK:, FACTS_CHECK:, SEARCH_CHECK:, REASONING:, RESPONSE:, LEARNING:) exactly as written.",
+        language
+    )
+}
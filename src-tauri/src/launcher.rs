@@ -0,0 +1,86 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use url::Url;
+
+// How long a freshly started daemon gets to answer, and how often it is asked
+pub const READY_TIMEOUT: Duration = Duration::from_secs(20);
+pub const READY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AutostartSettings {
+    // Start `ollama serve` when a local Ollama is not running
+    pub enabled: bool,
+    // Path to the ollama binary; None looks it up on PATH
+    pub binary_path: Option<String>,
+}
+
+impl Default for AutostartSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            binary_path: None,
+        }
+    }
+}
+
+// "http://localhost:11434" -> "localhost:11434", the form OLLAMA_HOST takes
+pub fn serve_host(base_url: &str) -> Option<String> {
+    let url = Url::parse(base_url).ok()?;
+    Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?))
+}
+
+// The `ollama serve` process started by the app, if any; killed when the launcher is dropped
+#[derive(Default)]
+pub struct OllamaLauncher {
+    child: Option<Child>,
+}
+
+impl OllamaLauncher {
+    // False once the started process has exited, e.g. because the port was taken
+    pub fn is_running(&mut self) -> bool {
+        match &mut self.child {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => false,
+        }
+    }
+
+    // `host` is what OLLAMA_HOST expects, e.g. "127.0.0.1:11434"
+    pub fn start(&mut self, settings: &AutostartSettings, host: &str) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+        let binary = settings.binary_path.as_deref().unwrap_or("ollama");
+        let child = Command::new(binary)
+            .arg("serve")
+            .env("OLLAMA_HOST", host)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Could not start {} serve; is Ollama installed?", binary))?;
+        self.child = Some(child);
+        Ok(())
+    }
+
+    // Fails if the started process has already exited
+    pub fn check_exited(&mut self) -> Result<()> {
+        if let Some(child) = &mut self.child {
+            if let Ok(Some(status)) = child.try_wait() {
+                self.child = None;
+                bail!("ollama serve exited early ({})", status);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OllamaLauncher {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use futures_util::future;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::backend::BackendKind;
+
+// A closed port fails immediately; this only bounds servers that accept and then hang
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Ports people commonly run each server on
+const PROBES: &[(BackendKind, u16)] = &[
+    (BackendKind::LlamaCpp, 8080),
+    (BackendKind::LlamaCpp, 8081),
+    (BackendKind::LmStudio, 1234),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalServer {
+    pub kind: BackendKind,
+    // What BackendSettings::local_url should be set to
+    pub base_url: String,
+    // False while llama.cpp is still loading its model
+    pub ready: bool,
+    pub models: Vec<String>,
+}
+
+async fn model_ids(client: &reqwest::Client, base_url: &str) -> Option<Vec<String>> {
+    let response = client.get(format!("{}/models", base_url)).send().await.ok()?;
+    let body: Value = response.error_for_status().ok()?.json().await.ok()?;
+    let models = body["data"].as_array()?;
+    Some(models.iter().filter_map(|m| m["id"].as_str().map(str::to_string)).collect())
+}
+
+async fn probe(client: &reqwest::Client, kind: BackendKind, port: u16) -> Option<LocalServer> {
+    let host = format!("http://127.0.0.1:{}", port);
+    let base_url = format!("{}/v1", host);
+    let ready = match kind {
+        // /health answers 200 once the model is loaded and 503 until then
+        BackendKind::LlamaCpp => {
+            let status = client.get(format!("{}/health", host)).send().await.ok()?.status();
+            match status.as_u16() {
+                200 => true,
+                503 => false,
+                _ => return None,
+            }
+        }
+        _ => true,
+    };
+    let models = match model_ids(client, &base_url).await {
+        Some(models) => models,
+        // A loading llama.cpp may not list its model yet
+        None if !ready => Vec::new(),
+        None => return None,
+    };
+    Some(LocalServer {
+        kind,
+        base_url,
+        ready,
+        models,
+    })
+}
+
+// All probes run at once, so detection takes at most PROBE_TIMEOUT
+pub async fn detect() -> Result<Vec<LocalServer>> {
+    let client = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build()?;
+    let probes = PROBES.iter().map(|&(kind, port)| probe(&client, kind, port));
+    Ok(future::join_all(probes).await.into_iter().flatten().collect())
+}
@@ -1,44 +1,661 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+mod analytics;
+mod attachments;
+mod backend;
+mod bangs;
+mod bookmarks;
+mod chatgpt;
+mod consent;
+mod context;
+mod conversation_store;
+mod developer;
+mod diff;
+mod doh;
+mod encryption;
+mod export;
+mod facts;
+mod feedback;
+mod history_search;
+mod jobs;
+mod knowledge;
+mod language;
+mod launcher;
+mod local_servers;
+mod model_updates;
 mod ollama;
+mod openai;
+mod operators;
+mod power;
+mod providers;
+mod reasoning;
+mod redact;
+mod reminders;
+mod replay;
+mod retention;
+mod safety;
+mod scheduler;
 mod search;
-use tauri::Emitter;
-use ollama::{ChatMessage, ChatRequest, OllamaClient, SYSTEM_PROMPT};
+mod search_history;
+mod secrets;
+mod slash;
+mod snippets;
+mod settings;
+mod starters;
+mod storage;
+mod summary;
+mod tagging;
+mod template;
+mod titles;
+mod tls;
+mod tools;
+mod trash;
+mod workspaces;
+use chrono::{DateTime, Local, Utc};
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::webview::PageLoadEvent;
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use analytics::{Analytics, DashboardData, UsageReport};
+use attachments::ImageAttachment;
+use backend::{BackendKind, LlmBackend};
+use bangs::{BangTarget, Bangs};
+use bookmarks::{Bookmark, BookmarkStore};
+use context::{ContextPreview, SystemPrompt, DEFAULT_CONTEXT_TOKENS};
+use consent::{ConsentBroker, CONSENT_TIMEOUT};
+use conversation_store::{ConversationStore, StoredConversation};
+use encryption::StorageStatus;
+use export::ConversationExport;
+use facts::{Fact, FactStore};
+use feedback::{Feedback, FeedbackStore, Rating};
+use history_search::MessageMatch;
+use jobs::{Job, JobKind, JobManager, JobStatus, JobWorker};
+use knowledge::{KnowledgeBase, Snapshot, SnapshotContent};
+use launcher::OllamaLauncher;
+use local_servers::LocalServer;
+use diff::MessageDiff;
+use power::{PowerSettings, PowerStatus};
+use providers::{Provider, ProviderKeyStatus, ProviderKeys, ValidationResult};
+use model_updates::ModelUpdate;
+use ollama::{
+    ChatChunk, ChatMessage, ChatProgress, ChatRequest, ChatStats, CreateModelRequest, GenerateRequest, GenerationOptions, LocalModel, ModelDiskUsage,
+    Completion, ModelInfo, OllamaClient, OllamaError, OllamaStatus, PullProgress, RunningModel, Section, SectionParser, TokenLogprob, ToolCall, DEFAULT_EMBED_MODEL, DEFAULT_MODEL,
+    PRELOAD_KEEP_ALIVE, SYSTEM_PROMPT,
+};
+use openai::{OpenAiClient, OpenAiSettings};
+use reasoning::{Split, ThinkFilter};
+use redact::{RedactionMap, Redactor};
+use reminders::{Reminder, ReminderStore};
+use safety::{ReplyGate, BLOCKED_PLACEHOLDER};
+use search_history::{SearchAnalytics, SearchHistory};
+use scheduler::{ScheduledMessage, Scheduler};
+use slash::{SlashCommand, SlashCommandInfo, SlashCommandResult};
+use snippets::{Snippet, SnippetStore};
+use summary::ConversationDigest;
+use settings::Settings;
+use starters::{ConversationTemplate, TemplateStore};
+use tagging::{ConversationTags, TagCount, TagStore};
+use tools::Tool;
+use replay::{ReplayFinished, ReplayMessage};
+use retention::{ExpiredConversation, RetentionReport};
+use trash::{ClearedHistory, Trash, TrashEntry};
+use template::PromptVariables;
+use titles::ConversationTitled;
 use tauri::State;
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::mpsc::Receiver;
 use tokio::sync::Mutex;
-use crate::search::{SearchClient, SearchRequest, SearchResult};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+use workspaces::{Workspace, WorkspaceConfig, WorkspaceStore};
+use crate::operators::ParsedQuery;
+use crate::search::{ExtractionSelectors, SearchClient, SearchProvider, SearchResult, SelectorKind};
+
+// Number of result pages fetched in parallel during enrichment
+const ENRICH_CONCURRENCY: usize = 4;
+
+// How often the Ollama connection is checked for the status indicator
+const OLLAMA_STATUS_INTERVAL: Duration = Duration::from_secs(15);
+
+// Minimum gap between chat-progress events while streaming
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+// Name of conversations created without one
+const DEFAULT_CONVERSATION_NAME: &str = "New conversation";
 
 // State management for conversation context
 struct ConversationState {
+    id: String,
+    name: String,
+    created_at: DateTime<Utc>,
+    // The conversation this one was forked from
+    forked_from: Option<String>,
+    // Listed first
+    pinned: bool,
+    favorite: bool,
+    // Left out of the default listing; still searchable
+    archived: bool,
     messages: Vec<ChatMessage>,
+    redactions: RedactionMap,
+    // Privacy flag: no search, URL fetching or remote providers for this conversation
+    no_web: bool,
+    // Incognito: nothing about the conversation is written to disk, so it is gone once
+    // the app closes. Set when the conversation is created.
+    ephemeral: bool,
+    // Replies always use this language instead of the one detected in each message
+    reply_language: Option<String>,
+    // Sent instead of the default SYSTEM_PROMPT
+    system_prompt: Option<String>,
+    // Chat model for this conversation instead of the workspace or selected one
+    model: Option<String>,
+    options: GenerationOptions,
+    tags: Vec<String>,
+    // Completed exchanges, counted separately since history is summarized
+    exchanges: usize,
+    // Web searches run for this conversation, by the user or by tools
+    searches: usize,
+    // Results of searches since the last answer; stored with the next one as its sources
+    search_results: Vec<SearchResult>,
+    // Stands in for the messages folded into it
+    summary: Option<String>,
+    summarizing: bool,
+    // Written on request by summarize_conversation
+    digest: Option<ConversationDigest>,
+}
+
+impl ConversationState {
+    fn new(name: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            created_at: Utc::now(),
+            forked_from: None,
+            pinned: false,
+            favorite: false,
+            archived: false,
+            messages: Vec::new(),
+            redactions: RedactionMap::default(),
+            no_web: false,
+            ephemeral: false,
+            reply_language: None,
+            system_prompt: None,
+            model: None,
+            options: GenerationOptions::default(),
+            tags: Vec::new(),
+            exchanges: 0,
+            searches: 0,
+            search_results: Vec::new(),
+            summary: None,
+            summarizing: false,
+            digest: None,
+        }
+    }
+
+    fn stored(&self) -> StoredConversation {
+        StoredConversation {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            created_at: self.created_at,
+            forked_from: self.forked_from.clone(),
+            pinned: self.pinned,
+            favorite: self.favorite,
+            archived: self.archived,
+            messages: self.messages.clone(),
+            redactions: self.redactions.clone(),
+            no_web: self.no_web,
+            reply_language: self.reply_language.clone(),
+            system_prompt: self.system_prompt.clone(),
+            model: self.model.clone(),
+            options: self.options.clone(),
+            tags: self.tags.clone(),
+            exchanges: self.exchanges,
+            searches: self.searches,
+            summary: self.summary.clone(),
+            digest: self.digest.clone(),
+        }
+    }
+
+    fn from_stored(stored: StoredConversation) -> Self {
+        Self {
+            id: stored.id,
+            name: stored.name,
+            created_at: stored.created_at,
+            forked_from: stored.forked_from,
+            pinned: stored.pinned,
+            favorite: stored.favorite,
+            archived: stored.archived,
+            messages: stored.messages,
+            redactions: stored.redactions,
+            no_web: stored.no_web,
+            ephemeral: false,
+            reply_language: stored.reply_language,
+            system_prompt: stored.system_prompt,
+            model: stored.model,
+            options: stored.options,
+            tags: stored.tags,
+            exchanges: stored.exchanges,
+            searches: stored.searches,
+            search_results: Vec::new(),
+            summary: stored.summary,
+            summarizing: false,
+            digest: stored.digest,
+        }
+    }
+
+    // When the last message was added, or when the conversation was created
+    fn last_activity(&self) -> DateTime<Utc> {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|m| m.created_at)
+            .unwrap_or(self.created_at)
+    }
+
+    fn info(&self, current: bool) -> ConversationInfo {
+        ConversationInfo {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            created_at: self.created_at,
+            forked_from: self.forked_from.clone(),
+            pinned: self.pinned,
+            favorite: self.favorite,
+            archived: self.archived,
+            ephemeral: self.ephemeral,
+            tags: self.tags.clone(),
+            message_count: self.messages.len(),
+            current,
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct ConversationInfo {
+    id: String,
+    name: String,
+    created_at: DateTime<Utc>,
+    forked_from: Option<String>,
+    pinned: bool,
+    favorite: bool,
+    archived: bool,
+    ephemeral: bool,
+    tags: Vec<String>,
+    message_count: usize,
+    // The one used when a command is called without a conversation_id
+    current: bool,
+}
+
+// Open conversations, each behind its own lock so a stream generating in one
+// never blocks another conversation or window
+struct Conversations {
+    default_id: String,
+    open: HashMap<String, Arc<Mutex<ConversationState>>>,
+}
+
+impl Conversations {
+    fn new() -> Self {
+        let mut conversations = Self {
+            default_id: String::new(),
+            open: HashMap::new(),
+        };
+        conversations.default_id = conversations.open_new(DEFAULT_CONVERSATION_NAME.to_string());
+        conversations
+    }
+
+    fn open_new(&mut self, name: String) -> String {
+        let conversation = ConversationState::new(name);
+        let id = conversation.id.clone();
+        self.open.insert(id.clone(), Arc::new(Mutex::new(conversation)));
+        id
+    }
+
+    // None means the default conversation, for callers that predate multiple conversations
+    fn get(&self, id: Option<&str>) -> Result<Arc<Mutex<ConversationState>>, String> {
+        let id = id.unwrap_or(&self.default_id);
+        self.open
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown conversation: {}", id))
+    }
+
+    fn close(&mut self, id: &str) -> Result<(), String> {
+        if id == self.default_id {
+            return Err("The default conversation cannot be closed".to_string());
+        }
+        self.open
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| format!("Unknown conversation: {}", id))
+    }
+
+    // Unlike close, the current conversation can go too; a fresh one takes its place
+    fn delete(&mut self, id: &str) -> Result<(), String> {
+        self.open
+            .remove(id)
+            .ok_or_else(|| format!("Unknown conversation: {}", id))?;
+        if id == self.default_id {
+            self.default_id = self.open_new(DEFAULT_CONVERSATION_NAME.to_string());
+        }
+        Ok(())
+    }
+
+    // Conversations read back from the store; ones already open are left as they are
+    fn restore(&mut self, stored: Vec<StoredConversation>) {
+        for conversation in stored {
+            if !self.open.contains_key(&conversation.id) {
+                let id = conversation.id.clone();
+                self.open.insert(id, Arc::new(Mutex::new(ConversationState::from_stored(conversation))));
+            }
+        }
+    }
+
+    fn switch(&mut self, id: &str) -> Result<(), String> {
+        if !self.open.contains_key(id) {
+            return Err(format!("Unknown conversation: {}", id));
+        }
+        self.default_id = id.to_string();
+        Ok(())
+    }
+}
+
+// Where chat events go: the window that started the stream, or every window
+// for background runs such as scheduled messages
+#[derive(Clone)]
+struct EventSink {
+    app: AppHandle,
+    window: Option<String>,
+    // Added to every event so parallel streams can be told apart
+    stream_id: Option<String>,
+}
+
+impl EventSink {
+    fn broadcast(app: &AppHandle) -> Self {
+        Self {
+            app: app.clone(),
+            window: None,
+            stream_id: None,
+        }
+    }
+
+    fn window(window: &tauri::Window) -> Self {
+        Self {
+            app: window.app_handle().clone(),
+            window: Some(window.label().to_string()),
+            stream_id: None,
+        }
+    }
+
+    fn with_stream(mut self, stream_id: String) -> Self {
+        self.stream_id = Some(stream_id);
+        self
+    }
+
+    // Background callers leave the id to be generated here
+    fn for_stream(&self) -> Self {
+        match &self.stream_id {
+            Some(_) => self.clone(),
+            None => self.clone().with_stream(Uuid::new_v4().to_string()),
+        }
+    }
+
+    // Object payloads get a stream_id field; anything else is wrapped as
+    // {"stream_id": ..., "content": payload}
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) -> Result<(), String> {
+        let mut payload = serde_json::to_value(payload).map_err(|e| e.to_string())?;
+        if let Some(stream_id) = &self.stream_id {
+            if !payload.is_object() {
+                payload = serde_json::json!({ "content": payload });
+            }
+            payload["stream_id"] = serde_json::json!(stream_id);
+        }
+        match &self.window {
+            Some(label) => self.app.emit_to(label.as_str(), event, payload),
+            None => self.app.emit(event, payload),
+        }
+        .map_err(|e| e.to_string())
+    }
+}
+
+// A chat stream in flight; each invocation gets its own id
+#[derive(Serialize, Clone)]
+struct ActiveStream {
+    stream_id: String,
+    conversation_id: String,
+    started_at: DateTime<Utc>,
+    #[serde(skip)]
+    cancel: CancellationToken,
+}
+
+#[derive(Serialize, Clone)]
+struct ChatStarted {
+    conversation_id: String,
 }
 
 struct SearchState {
     client: SearchClient,
+    selectors: ExtractionSelectors,
+    bangs: Bangs,
+}
+
+// Whether low-power mode is in effect, and the jobs it paused so they can be resumed
+#[derive(Default)]
+struct PowerState {
+    low_power: bool,
+    paused_jobs: Vec<String>,
 }
 
 // Combined state management
 struct AppState {
+    // Model management; chat goes through `backend`
     ollama: Mutex<OllamaClient>,
-    conversation: Mutex<ConversationState>,
+    backend: Mutex<Box<dyn LlmBackend>>,
+    // Chat model chosen at runtime; a workspace model takes precedence
+    model: Mutex<String>,
+    conversations: Mutex<Conversations>,
+    // Where open conversations are written, and the search index over them
+    conversation_store: Mutex<ConversationStore>,
+    // Histories removed by clear_conversation, until they expire
+    trash: Mutex<Trash>,
+    // In-flight chat streams, keyed by stream id
+    streams: Mutex<HashMap<String, ActiveStream>>,
     search: Mutex<SearchState>,
+    settings: Mutex<Settings>,
+    analytics: Mutex<Analytics>,
+    consent: Mutex<ConsentBroker>,
+    scheduler: Mutex<Scheduler>,
+    reminders: Mutex<ReminderStore>,
+    snippets: Mutex<SnippetStore>,
+    templates: Mutex<TemplateStore>,
+    knowledge: Mutex<KnowledgeBase>,
+    provider_keys: Mutex<ProviderKeys>,
+    bookmarks: Mutex<BookmarkStore>,
+    tags: Mutex<TagStore>,
+    search_history: Mutex<SearchHistory>,
+    jobs: Mutex<JobManager>,
+    // Model -> registry digest already announced, so each update is only announced once
+    announced_updates: Mutex<HashMap<String, String>>,
+    facts: Mutex<FactStore>,
+    feedback: Mutex<FeedbackStore>,
+    power: Mutex<PowerState>,
+    launcher: Mutex<OllamaLauncher>,
+    workspaces: Mutex<WorkspaceStore>,
+    data_dir: PathBuf,
+}
+
+// Unknown conversations count as regular ones; the caller reports the error if it matters
+async fn is_ephemeral(state: &AppState, conversation_id: Option<&str>) -> bool {
+    match conversation(state, conversation_id).await {
+        Ok(handle) => handle.lock().await.ephemeral,
+        Err(_) => false,
+    }
+}
+
+async fn conversation(state: &AppState, id: Option<&str>) -> Result<Arc<Mutex<ConversationState>>, String> {
+    state.conversations.lock().await.get(id)
+}
+
+// The workspace a conversation belongs to; None for ungrouped conversations
+async fn conversation_workspace(state: &AppState, conversation_id: Option<&str>) -> Option<Workspace> {
+    let conversation_id = match conversation_id {
+        Some(id) => id.to_string(),
+        None => state.conversations.lock().await.default_id.clone(),
+    };
+    state.workspaces.lock().await.for_conversation(&conversation_id).cloned()
+}
+
+// The workspace model if it sets one, otherwise the selected model
+async fn chat_model(state: &AppState, workspace: Option<&Workspace>) -> String {
+    match workspace.and_then(|w| w.model.clone()) {
+        Some(model) => model,
+        None => state.model.lock().await.clone(),
+    }
+}
+
+// Only installed models can be selected
+async fn select_model(state: &AppState, model: &str) -> Result<String, String> {
+    let model = model.trim();
+    let client = state.backend.lock().await.clone();
+    let installed = client.list_models().await.map_err(|e| e.to_string())?;
+    // "llama3" is stored by Ollama as "llama3:latest"
+    let found = installed
+        .into_iter()
+        .map(|m| m.name)
+        .find(|name| name == model || name.strip_suffix(":latest") == Some(model))
+        .ok_or_else(|| format!("Model {} is not installed", model))?;
+    *state.model.lock().await = found.clone();
+    Ok(found)
+}
+
+async fn prompt_variables(state: &AppState, workspace: Option<&Workspace>) -> PromptVariables {
+    let user_name = state.settings.lock().await.user_name.clone();
+    PromptVariables::collect(&user_name, workspace.map(|w| w.name.as_str()))
+}
+
+// Backend enforcement of the per-conversation "no web" flag
+async fn ensure_web_allowed(state: &AppState, conversation_id: Option<&str>) -> Result<(), String> {
+    if conversation(state, conversation_id).await?.lock().await.no_web {
+        return Err("Web access is disabled for this conversation".to_string());
+    }
+    Ok(())
+}
+
+// In consent mode, ask the frontend which URLs may be fetched and wait for the answer
+async fn approve_fetches(
+    app: &AppHandle,
+    state: &AppState,
+    urls: Vec<String>,
+) -> Result<Vec<String>, String> {
+    if urls.is_empty() || !state.settings.lock().await.ask_before_fetching {
+        return Ok(urls);
+    }
+
+    let (request, receiver) = state.consent.lock().await.register(urls);
+    app
+        .emit("fetch-consent-requested", &request)
+        .map_err(|e| e.to_string())?;
+
+    match tokio::time::timeout(CONSENT_TIMEOUT, receiver).await {
+        Ok(Ok(approved)) => Ok(request
+            .urls
+            .into_iter()
+            .filter(|url| approved.contains(url))
+            .collect()),
+        _ => {
+            state.consent.lock().await.cancel(&request.request_id);
+            Ok(Vec::new())
+        }
+    }
+}
+
+// Record a feature use locally, but only if the user opted in
+async fn track_usage(state: &AppState, feature: &str, started: Instant) {
+    track_activity(state, |analytics| analytics.record(feature, started.elapsed())).await;
+}
+
+async fn track_activity(state: &AppState, record: impl FnOnce(&mut Analytics) -> anyhow::Result<()>) {
+    if !state.settings.lock().await.analytics_enabled {
+        return;
+    }
+    if let Err(e) = record(&mut *state.analytics.lock().await) {
+        eprintln!("Failed to record usage: {:?}", e);
+    }
+}
+
+// Searches and the results used from them, recorded under the same opt-in as usage data
+async fn track_search(state: &AppState, record: impl FnOnce(&mut SearchHistory) -> anyhow::Result<()>) {
+    if !state.settings.lock().await.analytics_enabled {
+        return;
+    }
+    if let Err(e) = record(&mut *state.search_history.lock().await) {
+        eprintln!("Failed to record search history: {:?}", e);
+    }
 }
 
 #[tauri::command]
 async fn perform_search(
     window: tauri::Window,
     query: String,
+    conversation_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    // Clone what we need before spawning
-    let search_client = {
-        let search_state = state.search.lock().await;
-        search_state.client.clone()
+    run_search(window.app_handle(), &state, conversation_id.as_deref(), query)
+        .await
+        .map(|_| ())
+}
+
+// Shared by the perform_search command and the /search slash command. Returns every result
+// found, enriched where its page was fetched, the same list the conversation keeps as sources
+async fn run_search(
+    app: &AppHandle,
+    state: &AppState,
+    conversation_id: Option<&str>,
+    query: String,
+) -> Result<Vec<SearchResult>, String> {
+    let started = Instant::now();
+    ensure_web_allowed(state, conversation_id).await?;
+    let handle = conversation(state, conversation_id).await.ok();
+    let mut ephemeral = false;
+    if let Some(handle) = &handle {
+        let mut conversation = handle.lock().await;
+        conversation.searches += 1;
+        ephemeral = conversation.ephemeral;
+    }
+
+    // A stored Brave key makes Brave the default instead of DuckDuckGo
+    let brave_key = Provider::Brave.api_key().unwrap_or_else(|e| {
+        eprintln!("Failed to read the Brave Search key: {:?}", e);
+        None
+    });
+    let default = match brave_key {
+        Some(_) => SearchProvider::Brave,
+        None => SearchProvider::DuckDuckGo,
     };
 
-    let request = SearchRequest {
-        query,
-        max_results: 5,
+    // Clone what we need before spawning; a bang in the query picks the provider
+    let (search_client, mut request) = {
+        let search_state = state.search.lock().await;
+        (search_state.client.clone(), search_state.bangs.route(&query, 5, default))
     };
+    if request.provider == SearchProvider::Brave {
+        request.api_key = brave_key;
+    }
+
+    // Workspace domain filters apply unless the query, or its bang, already names a site
+    if let Some(workspace) = conversation_workspace(state, conversation_id).await {
+        if matches!(request.provider, SearchProvider::DuckDuckGo | SearchProvider::Brave)
+            && !workspace.search_sites.is_empty()
+            && ParsedQuery::parse(&request.query).sites.is_empty()
+        {
+            for site in &workspace.search_sites {
+                request.query.push_str(&format!(" site:{}", site));
+            }
+        }
+    }
+
+    let provider = request.provider;
 
     // Use cloned client instead of state reference
     let mut receiver = search_client
@@ -46,112 +663,3928 @@ async fn perform_search(
         .await
         .map_err(|e| e.to_string())?;
 
+    let mut results = Vec::new();
     while let Some(result) = receiver.recv().await {
-        window.emit("search-result", &result)
+        app.emit("search-result", &result)
             .map_err(|e| e.to_string())?;
+        results.push(result);
     }
 
-    Ok(())
+    if !ephemeral {
+        let result_urls = results.iter().map(|r| r.url.clone()).collect();
+        track_search(state, |history| history.record(&query, provider, result_urls)).await;
+    }
+
+    // Enrich results by fetching the pages themselves, limited to approved URLs
+    let urls = results.iter().map(|r| r.url.clone()).collect();
+    let approved = approve_fetches(app, state, urls).await?;
+    let to_enrich: Vec<SearchResult> = results
+        .iter()
+        .filter(|r| approved.contains(&r.url))
+        .cloned()
+        .collect();
+    let mut sources = results;
+
+    let concurrency = if is_low_power(state).await {
+        power::ENRICH_CONCURRENCY
+    } else {
+        ENRICH_CONCURRENCY
+    };
+    let mut enriched = stream::iter(to_enrich)
+        .map(|result| search_client.enrich_result(result))
+        .buffer_unordered(concurrency);
+    let mut results = Vec::new();
+    while let Some(result) = enriched.next().await {
+        app.emit("search-result-enriched", &result)
+            .map_err(|e| e.to_string())?;
+        results.push(result);
+    }
+
+    // Every result found is a source, in its enriched form where there is one
+    for source in &mut sources {
+        if let Some(result) = results.iter().find(|r| r.url == source.url) {
+            *source = result.clone();
+        }
+    }
+    if let Some(handle) = &handle {
+        let mut conversation = handle.lock().await;
+        for source in &sources {
+            if !conversation.search_results.iter().any(|r| r.url == source.url) {
+                conversation.search_results.push(source.clone());
+            }
+        }
+    }
+
+    if !ephemeral {
+        track_usage(state, "search", started).await;
+        track_activity(state, |analytics| analytics.record_search()).await;
+    }
+    Ok(sources)
 }
 
 #[tauri::command]
 async fn chat_stream(
     window: tauri::Window,
     message: String,
+    conversation_id: Option<String>,
+    images: Option<Vec<String>>,
+    // Lets the UI know the id before the first event arrives; generated when missing
+    stream_id: Option<String>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let mut conversation = state.conversation.lock().await;
-    
-    // Create new user message
-    let user_message = OllamaClient::create_user_message(message);
-    
-    // Build messages array starting with system prompt
-    let mut messages = vec![
-        OllamaClient::create_system_message(),
-    ];
-
-    // Add relevant conversation history
-    // We'll take the last few messages to maintain context
-    let history_start = conversation.messages.len().saturating_sub(5);
-    messages.extend(conversation.messages[history_start..].iter().cloned());
-    
-    // Add the new user message
-    messages.push(user_message.clone());
-
-    // Create request with full context in messages
-    let request = ChatRequest {
-        model: "granite3-moe".to_string(),
-        messages,
-        stream: true,
+) -> Result<String, String> {
+    let conversation_id = match conversation_id {
+        Some(id) => id,
+        None => state.conversations.lock().await.default_id.clone(),
     };
+    let images = images.filter(|images| !images.is_empty());
+    let stream_id = stream_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let sink = EventSink::window(&window).with_stream(stream_id.clone());
+    run_chat(&sink, &state, &conversation_id, message, images).await?;
+    Ok(stream_id)
+}
 
-    // Get client and send request
-    let client = {
-        let client = state.ollama.lock().await;
-        client.clone()
+#[derive(Serialize, Clone)]
+struct ToolCallRequested {
+    conversation_id: String,
+    calls: Vec<ToolCall>,
+}
+
+// Runs a tool the model asked for; the text returned is what the model sees
+async fn run_tool(sink: &EventSink, state: &AppState, conversation_id: &str, tool: Tool) -> Result<String, String> {
+    match tool {
+        Tool::WebSearch { query } => {
+            let results = run_search(&sink.app, state, Some(conversation_id), query).await?;
+            if results.is_empty() {
+                return Ok("No results found".to_string());
+            }
+            Ok(results
+                .iter()
+                .map(|r| format!("{} ({})\n{}", r.title, r.url, r.summary))
+                .collect::<Vec<_>>()
+                .join("\n\n"))
+        }
+        Tool::CurrentTime => Ok(Local::now().format("%A, %B %-d, %Y %H:%M %Z").to_string()),
+        Tool::SaveFact { text, topic } => {
+            if is_ephemeral(state, Some(conversation_id)).await {
+                return Ok("Not saved: this conversation is incognito".to_string());
+            }
+            let fact = state
+                .facts
+                .lock()
+                .await
+                .add(&text, topic.as_deref(), Some("assistant".to_string()), Some(conversation_id.to_string()))
+                .map_err(|e| e.to_string())?;
+            Ok(format!("Saved under {}", fact.topic))
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct ChatError {
+    conversation_id: String,
+    // OllamaError::kind, so the UI can offer a fix such as pulling the model
+    kind: &'static str,
+    message: String,
+}
+
+// Per-token probabilities for one chunk of the reply, in developer mode
+#[derive(Serialize, Clone)]
+struct ChatConfidence {
+    conversation_id: String,
+    tokens: Vec<TokenConfidence>,
+}
+
+#[derive(Serialize, Clone)]
+struct TokenConfidence {
+    token: String,
+    logprob: f64,
+    // exp(logprob), between 0 and 1
+    confidence: f64,
+    top_logprobs: Vec<TokenLogprob>,
+}
+
+impl From<TokenLogprob> for TokenConfidence {
+    fn from(token: TokenLogprob) -> Self {
+        Self {
+            confidence: token.confidence(),
+            token: token.token,
+            logprob: token.logprob,
+            top_logprobs: token.top_logprobs,
+        }
+    }
+}
+
+// A piece of a reasoning model's <think> span, kept out of the reply
+#[derive(Serialize, Clone)]
+struct ChatReasoning {
+    conversation_id: String,
+    content: String,
+}
+
+// A completed line of one section of the structured reply, for collapsible panels
+#[derive(Serialize, Clone)]
+struct ChatSection {
+    conversation_id: String,
+    section: Section,
+    content: String,
+}
+
+// The selected model failed and a fallback answered instead
+#[derive(Serialize, Clone)]
+struct ChatFallback {
+    conversation_id: String,
+    failed_model: String,
+    error: String,
+    model: String,
+    backend: BackendKind,
+}
+
+#[derive(Serialize, Clone)]
+struct ChatStatsEvent {
+    conversation_id: String,
+    #[serde(flatten)]
+    stats: ChatStats,
+}
+
+// Sent once a stopped response has been saved to the history
+#[derive(Serialize, Clone)]
+struct ChatCancelled {
+    conversation_id: String,
+    partial_response: String,
+}
+
+// Stops the response currently streaming into a conversation; what was generated so far is kept
+#[tauri::command]
+async fn cancel_chat_stream(conversation_id: Option<String>, state: State<'_, AppState>) -> Result<(), String> {
+    let conversation_id = match conversation_id {
+        Some(id) => id,
+        None => state.conversations.lock().await.default_id.clone(),
     };
+    let streams = state.streams.lock().await;
+    match streams.values().find(|s| s.conversation_id == conversation_id) {
+        Some(stream) => {
+            stream.cancel.cancel();
+            Ok(())
+        }
+        None => Err(format!("No active stream for conversation: {}", conversation_id)),
+    }
+}
 
-    // Add user message to conversation history
-    conversation.messages.push(user_message);
+// Deprecated alias of cancel_chat_stream, kept for callers that use the older name
+#[tauri::command]
+async fn stop_generation(conversation_id: Option<String>, state: State<'_, AppState>) -> Result<(), String> {
+    cancel_chat_stream(conversation_id, state).await
+}
+
+#[tauri::command]
+async fn cancel_stream(stream_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    match state.streams.lock().await.get(&stream_id) {
+        Some(stream) => {
+            stream.cancel.cancel();
+            Ok(())
+        }
+        None => Err(format!("No active stream: {}", stream_id)),
+    }
+}
+
+#[tauri::command]
+async fn list_streams(state: State<'_, AppState>) -> Result<Vec<ActiveStream>, String> {
+    let mut streams: Vec<ActiveStream> = state.streams.lock().await.values().cloned().collect();
+    streams.sort_by_key(|s| s.started_at);
+    Ok(streams)
+}
+
+// Shared by the chat_stream command and background tasks such as the scheduler
+async fn run_chat(
+    sink: &EventSink,
+    state: &AppState,
+    conversation_id: &str,
+    message: String,
+    images: Option<Vec<String>>,
+) -> Result<(), String> {
+    let sink = &sink.for_stream();
+    let workspace = conversation_workspace(state, Some(conversation_id)).await;
+    let variables = prompt_variables(state, workspace.as_ref()).await;
+    let message = state.snippets.lock().await.expand(&message, &variables);
+
+    if let Some(command) = slash::parse(&message) {
+        return run_slash_command(sink, state, conversation_id, command?).await;
+    }
+
+    let handle = conversation(state, Some(conversation_id)).await?;
+    let (stream_id, cancel) = start_stream(sink, state, conversation_id).await?;
+    // Only once the message is sure to be answered, so a retry cannot save it twice
+    let result = match add_chat_reminder(sink, state, conversation_id, &message).await {
+        Ok(()) => {
+            let mut user_message = OllamaClient::create_user_message(message);
+            user_message.images = images;
+            stream_reply(sink, state, &handle, user_message, None, &variables, &cancel).await
+        }
+        Err(e) => Err(e),
+    };
+    state.streams.lock().await.remove(&stream_id);
+    result
+}
 
-    let mut receiver = client
-        .chat_stream(request)
+// "remind me Thursday to ..." becomes a real reminder; the model still answers normally.
+// Reminders are saved, so incognito conversations don't get them.
+async fn add_chat_reminder(sink: &EventSink, state: &AppState, conversation_id: &str, message: &str) -> Result<(), String> {
+    if is_ephemeral(state, Some(conversation_id)).await {
+        return Ok(());
+    }
+    let Some((due, text)) = reminders::parse_reminder(message, Local::now()) else {
+        return Ok(());
+    };
+    let reminder = state
+        .reminders
+        .lock()
         .await
+        .add(text, due)
         .map_err(|e| e.to_string())?;
+    sink.emit("reminder-created", &reminder)
+}
 
-    drop(conversation); // Release the lock before entering the loop
-
-    let mut complete_message = String::new();
+// One stream per conversation; other conversations stream independently. The caller
+// removes the stream from AppState::streams once it has finished.
+async fn start_stream(
+    sink: &EventSink,
+    state: &AppState,
+    conversation_id: &str,
+) -> Result<(String, CancellationToken), String> {
+    let stream_id = sink.stream_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancel = register_stream(state, &stream_id, conversation_id).await?;
+    let started = ChatStarted {
+        conversation_id: conversation_id.to_string(),
+    };
+    sink.emit("chat-started", &started)?;
+    Ok((stream_id, cancel))
+}
 
-    while let Some(chunk) = receiver.recv().await {
-        window
-            .emit("chat-response", &chunk)
-            .map_err(|e| e.to_string())?;
-        complete_message.push_str(&chunk);
+async fn register_stream(state: &AppState, stream_id: &str, conversation_id: &str) -> Result<CancellationToken, String> {
+    let cancel = CancellationToken::new();
+    let mut streams = state.streams.lock().await;
+    if streams.values().any(|s| s.conversation_id == conversation_id) {
+        return Err("A response is already streaming for this conversation".to_string());
     }
+    let stream = ActiveStream {
+        stream_id: stream_id.to_string(),
+        conversation_id: conversation_id.to_string(),
+        started_at: Utc::now(),
+        cancel: cancel.clone(),
+    };
+    streams.insert(stream_id.to_string(), stream);
+    Ok(cancel)
+}
 
-    // Once streaming is complete, add assistant's response to conversation history
-    if !complete_message.is_empty() {
-        let mut conversation = state.conversation.lock().await; // Re-acquire the lock
-        let context_len = conversation.messages.len();
-        
-        let assistant_message = OllamaClient::create_assistant_message(complete_message);
-        
-        if context_len > 10 {
-            conversation.messages.drain(0..context_len - 10);
-        }
-        
-        conversation.messages.push(assistant_message);
+// Emits the history again as "replay-message" events, one at a time, so the UI can play
+// a past session back. Runs as a stream, so cancel_stream stops it.
+#[tauri::command]
+async fn replay_conversation(
+    window: tauri::Window,
+    conversation_id: Option<String>,
+    // Follow the gaps between the original messages, shortened where they were long
+    original_pacing: Option<bool>,
+    // 2.0 plays back twice as fast
+    speed: Option<f32>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let speed = speed.unwrap_or(1.0);
+    replay::validate_speed(speed).map_err(|e| e.to_string())?;
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let (conversation_id, messages) = {
+        let conversation = handle.lock().await;
+        (conversation.id.clone(), conversation.messages.clone())
+    };
+    let stream_id = stream_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let sink = EventSink::window(&window).with_stream(stream_id.clone());
+    let cancel = register_stream(&state, &stream_id, &conversation_id).await?;
+
+    let delays = replay::delays(&messages, original_pacing.unwrap_or(false), speed);
+    let total = messages.len();
+    let mut replayed = 0;
+    let mut result = Ok(());
+    for ((index, message), delay) in messages.into_iter().enumerate().zip(delays) {
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = cancel.cancelled() => break,
+        }
+        let event = ReplayMessage {
+            conversation_id: conversation_id.clone(),
+            index,
+            total,
+            message,
+        };
+        result = sink.emit("replay-message", &event);
+        if result.is_err() {
+            break;
+        }
+        replayed += 1;
     }
+    state.streams.lock().await.remove(&stream_id);
+    result?;
 
-    Ok(())
+    let finished = ReplayFinished {
+        conversation_id,
+        replayed,
+        cancelled: cancel.is_cancelled(),
+    };
+    sink.emit("replay-finished", &finished)?;
+    Ok(stream_id)
 }
 
+// Drops the last answer and asks again with the same context, optionally at another temperature
 #[tauri::command]
-async fn clear_conversation(state: State<'_, AppState>) -> Result<(), String> {
-    let mut conversation = state.conversation.lock().await;
-    conversation.messages.clear();
-    Ok(())
+async fn regenerate_response(
+    window: tauri::Window,
+    conversation_id: Option<String>,
+    temperature: Option<f32>,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let options = GenerationOptions {
+        temperature,
+        ..Default::default()
+    };
+    options.validate().map_err(|e| e.to_string())?;
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let conversation_id = handle.lock().await.id.clone();
+    let stream_id = stream_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let sink = EventSink::window(&window).with_stream(stream_id.clone());
+
+    let (stream_id, cancel) = start_stream(&sink, &state, &conversation_id).await?;
+    let result = resubmit(&sink, &state, &handle, Resubmit::Last, temperature, &cancel).await;
+    state.streams.lock().await.remove(&stream_id);
+    result.map(|_| stream_id)
 }
 
-fn main() {
-    let app_state = AppState {
-        ollama: Mutex::new(OllamaClient::new()),
-        conversation: Mutex::new(ConversationState {
-            messages: Vec::new(),
-        }),
-        search: Mutex::new(SearchState {
-            client: SearchClient::new(),
-        }),
+// Replaces an earlier user message, drops everything after it and answers the new text
+#[tauri::command]
+async fn edit_message(
+    window: tauri::Window,
+    conversation_id: Option<String>,
+    message_index: usize,
+    content: String,
+    stream_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    if content.trim().is_empty() {
+        return Err("Message is empty".to_string());
+    }
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let conversation_id = handle.lock().await.id.clone();
+    let stream_id = stream_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let sink = EventSink::window(&window).with_stream(stream_id.clone());
+
+    let (stream_id, cancel) = start_stream(&sink, &state, &conversation_id).await?;
+    let edit = Resubmit::Edit {
+        index: message_index,
+        content,
     };
+    let result = resubmit(&sink, &state, &handle, edit, None, &cancel).await;
+    state.streams.lock().await.remove(&stream_id);
+    result.map(|_| stream_id)
+}
 
-    tauri::Builder::default()
-        .manage(app_state)
+// Which user message gets answered again
+enum Resubmit {
+    Last,
+    Edit { index: usize, content: String },
+}
+
+async fn resubmit(
+    sink: &EventSink,
+    state: &AppState,
+    handle: &Mutex<ConversationState>,
+    resubmit: Resubmit,
+    temperature: Option<f32>,
+    cancel: &CancellationToken,
+) -> Result<(), String> {
+    let conversation_id = handle.lock().await.id.clone();
+    let workspace = conversation_workspace(state, Some(&conversation_id)).await;
+    let variables = prompt_variables(state, workspace.as_ref()).await;
+
+    // stream_reply adds the user message back along with the new answer
+    let user_message = {
+        let mut conversation = handle.lock().await;
+        match resubmit {
+            Resubmit::Last => {
+                let roles: Vec<&str> = conversation.messages.iter().rev().take(2).map(|m| m.role.as_str()).collect();
+                if roles != ["assistant", "user"] {
+                    return Err("There is no response to regenerate".to_string());
+                }
+                let len = conversation.messages.len();
+                conversation.messages.split_off(len - 2).swap_remove(0)
+            }
+            Resubmit::Edit { index, content } => {
+                if conversation.messages.get(index).map(|m| m.role.as_str()) != Some("user") {
+                    return Err(format!("Message {} is not a user message", index));
+                }
+                // Images stay attached to the edited message
+                let mut message = conversation.messages.split_off(index).swap_remove(0);
+                message.content = state.snippets.lock().await.expand(&content, &variables);
+                message.created_at = Some(Utc::now());
+                message
+            }
+        }
+    };
+    stream_reply(sink, state, handle, user_message, temperature, &variables, cancel).await
+}
+
+async fn stream_reply(
+    sink: &EventSink,
+    state: &AppState,
+    handle: &Mutex<ConversationState>,
+    user_message: ChatMessage,
+    // Overrides the conversation's temperature for this reply only
+    temperature: Option<f32>,
+    variables: &PromptVariables,
+    cancel: &CancellationToken,
+) -> Result<(), String> {
+    let started = Instant::now();
+    let mut conversation = handle.lock().await;
+    let conversation_id = conversation.id.clone();
+    let ephemeral = conversation.ephemeral;
+    let workspace = state.workspaces.lock().await.for_conversation(&conversation_id).cloned();
+    let mut model = match &conversation.model {
+        Some(model) => model.clone(),
+        None => chat_model(state, workspace.as_ref()).await,
+    };
+    let reply_language = conversation
+        .reply_language
+        .clone()
+        .or_else(|| language::detect(&user_message.content).map(str::to_string));
+    let relevant_facts = state.facts.lock().await.relevant(&user_message.content, facts::RELEVANT_FACTS);
+    let facts_prompt = facts::facts_prompt(&relevant_facts);
+    let system = SystemPrompt {
+        custom: conversation.system_prompt.as_deref(),
+        persona: workspace.as_ref().and_then(|w| w.persona.as_deref()),
+        variables,
+        reply_language: reply_language.as_deref(),
+        summary: conversation.summary.as_deref(),
+        facts: facts_prompt.as_deref(),
+    };
+
+    // System prompt, the most recent history and the new user message
+    let context_tokens = conversation.options.num_ctx.unwrap_or(DEFAULT_CONTEXT_TOKENS);
+    let mut messages = context::build_messages(&conversation.messages, user_message.clone(), &system, context_tokens);
+
+    // Get client and send request
+    let mut client = state.backend.lock().await.clone();
+
+    if conversation.no_web && !client.is_local() {
+        return Err("Remote providers are disabled for this conversation".to_string());
+    }
+
+    // Scrub PII before it leaves the machine; local history keeps the original text
+    let redaction = state.settings.lock().await.redaction.clone();
+    if redaction.enabled && !client.is_local() {
+        let redactor = Redactor::new(&redaction).map_err(|e| e.to_string())?;
+        for message in messages.iter_mut().filter(|m| m.role != "system") {
+            message.content = redactor.redact(&message.content, &mut conversation.redactions);
+        }
+    }
+
+    // Create request with full context in messages
+    let (tool_settings, stop_sequences, developer) = {
+        let settings = state.settings.lock().await;
+        (settings.tools.clone(), settings.stop_sequences.clone(), settings.developer.clone())
+    };
+    let (logprobs, top_logprobs) = developer.logprobs();
+    let mut options = conversation.options.clone();
+    options.num_ctx = Some(context_tokens);
+    if temperature.is_some() {
+        options.temperature = temperature;
+    }
+    if options.stop.is_empty() {
+        options.stop = stop_sequences.into_iter().filter(|s| !s.is_empty()).collect();
+    }
+    let mut request = ChatRequest {
+        model: model.clone(),
+        messages,
+        stream: true,
+        keep_alive: is_low_power(state).await.then(|| power::KEEP_ALIVE.to_string()),
+        options: options.for_request(),
+        tools: tool_settings
+            .enabled
+            .then(|| tools::definitions(!conversation.no_web)),
+        logprobs,
+        top_logprobs,
+    };
+    let prompt_text: String = request.messages.iter().map(|m| m.content.as_str()).collect();
+    // Unredacted messages must not reach a remote fallback
+    let remote_allowed = !conversation.no_web && (!redaction.enabled || !client.is_local());
+
+    // Add user message to conversation history
+    conversation.messages.push(user_message);
+
+    // Release the lock before streaming; tools such as web_search need it
+    drop(conversation);
+
+    let mut complete_message = String::new();
+    let generation_started = Instant::now();
+    let mut last_progress = generation_started;
+    let mut tokens = 0;
+    let mut stats = None;
+    let mut sections = SectionParser::default();
+    let safety = state.settings.lock().await.safety.clone();
+    let mut gate = ReplyGate::new(&safety);
+
+    // A reply asking for tools is answered with their results and requested again
+    for round in 0..=tools::MAX_TOOL_ROUNDS {
+        let mut result = match client.chat_stream(request.clone()).await {
+            Err(OllamaError::ConnectionRefused(_)) if start_ollama(sink, state).await => {
+                client.chat_stream(request.clone()).await
+            }
+            result => result,
+        };
+        // Later rounds carry tool results meant for the model that asked for them
+        if let (0, Err(e)) = (round, &result) {
+            if let Some((backend, kind, receiver)) = open_fallback_stream(state, &mut request, remote_allowed).await {
+                let event = ChatFallback {
+                    conversation_id: conversation_id.clone(),
+                    failed_model: model.clone(),
+                    error: e.to_string(),
+                    model: request.model.clone(),
+                    backend: kind,
+                };
+                client = backend;
+                model = request.model.clone();
+                sink.emit("chat-fallback", &event)?;
+                result = Ok(receiver);
+            }
+        }
+        let mut receiver = match result {
+            Ok(receiver) => receiver,
+            Err(e) => {
+                let event = ChatError {
+                    conversation_id,
+                    kind: e.kind(),
+                    message: e.to_string(),
+                };
+                sink.emit("chat-error", &event)?;
+                return Err(event.message);
+            }
+        };
+
+        let mut round_content = String::new();
+        let mut tool_calls = Vec::new();
+        let mut think = ThinkFilter::default();
+        loop {
+            let chunk = tokio::select! {
+                chunk = receiver.recv() => chunk,
+                _ = cancel.cancelled() => None,
+            };
+            let chunk = match chunk {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    // Keep what arrived before the failure
+                    let event = ChatError {
+                        conversation_id: conversation_id.clone(),
+                        kind: e.kind(),
+                        message: format!("The response was interrupted: {}", e),
+                    };
+                    sink.emit("chat-error", &event)?;
+                    break;
+                }
+                None => break,
+            };
+
+            let split = think.push(&chunk.content);
+            emit_reply(sink, &conversation_id, split, &mut round_content, &mut sections, &mut gate)?;
+            tokens += 1;
+            if chunk.stats.is_some() {
+                stats = chunk.stats;
+            }
+            tool_calls.extend(chunk.tool_calls);
+            // Token confidences carry the text itself, so they are dropped while it is held back
+            if !chunk.logprobs.is_empty() && !gate.is_holding() {
+                let event = ChatConfidence {
+                    conversation_id: conversation_id.clone(),
+                    tokens: chunk.logprobs.into_iter().map(TokenConfidence::from).collect(),
+                };
+                sink.emit("chat-token-confidence", &event)?;
+            }
+
+            if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                last_progress = Instant::now();
+                let progress = ChatProgress::new(generation_started.elapsed(), tokens, false);
+                sink.emit("chat-progress", &progress)?;
+            }
+        }
+        emit_reply(sink, &conversation_id, think.finish(), &mut round_content, &mut sections, &mut gate)?;
+        // Dropping the receiver makes the client drop the response, which stops Ollama generating
+        drop(receiver);
+        complete_message.push_str(&round_content);
+
+        if tool_calls.is_empty() || cancel.is_cancelled() || round == tools::MAX_TOOL_ROUNDS {
+            break;
+        }
+
+        let event = ToolCallRequested {
+            conversation_id: conversation_id.clone(),
+            calls: tool_calls.clone(),
+        };
+        sink.emit("tool-call-requested", &event)?;
+
+        let mut assistant_message = OllamaClient::create_assistant_message(round_content);
+        assistant_message.metadata = None;
+        assistant_message.tool_calls = Some(tool_calls.clone());
+        request.messages.push(assistant_message);
+        for call in &tool_calls {
+            let output = match tools::parse(call) {
+                Ok(tool) => run_tool(sink, state, &conversation_id, tool).await,
+                Err(e) => Err(e.to_string()),
+            };
+            let content = output.unwrap_or_else(|e| format!("Error: {}", e));
+            request.messages.push(ChatMessage {
+                role: "tool".to_string(),
+                content,
+                metadata: None,
+                images: None,
+                tool_calls: None,
+                created_at: None,
+                model: None,
+                duration_ms: None,
+                pinned: false,
+            });
+        }
+    }
+
+    // Post-generation safety pass; a blocked response is saved to the history as
+    // BLOCKED_PLACEHOLDER, and with a blocking category its text never reaches the window
+    let mut blocked = false;
+    if safety.enabled && !complete_message.is_empty() {
+        let text = gate.text_to_check(&complete_message);
+        let verdict = safety.check(client.as_ref(), &model, &text).await;
+        if !verdict.flagged.is_empty() {
+            sink.emit("chat-safety", &verdict)?;
+        }
+        if verdict.blocked {
+            blocked = true;
+            complete_message = BLOCKED_PLACEHOLDER.to_string();
+        }
+    }
+    emit_split(sink, &conversation_id, gate.release(blocked), &mut sections)?;
+    if let Some(update) = sections.finish() {
+        emit_section(sink, &conversation_id, update)?;
+    }
+    let cancelled = cancel.is_cancelled();
+
+    let progress = ChatProgress::new(generation_started.elapsed(), tokens, true);
+    sink.emit("chat-progress", &progress)?;
+    if let Some(stats) = &stats {
+        let event = ChatStatsEvent {
+            conversation_id: conversation_id.clone(),
+            stats: stats.clone(),
+        };
+        sink.emit("chat-stats", &event)?;
+    }
+
+    // Once streaming is complete, add assistant's response to conversation history
+    let sources = std::mem::take(&mut handle.lock().await.search_results);
+    if !complete_message.is_empty() {
+        let mut conversation = handle.lock().await; // Re-acquire the lock
+        let complete_message = conversation.redactions.restore(&complete_message);
+        let mut assistant_message = OllamaClient::create_assistant_message(complete_message);
+        if let Some(metadata) = assistant_message.metadata.as_mut() {
+            metadata.search_results = (!sources.is_empty()).then(|| sources.into_iter().map(Into::into).collect());
+        }
+        assistant_message.model = Some(model.clone());
+        assistant_message.duration_ms = Some(generation_started.elapsed().as_millis() as u64);
+        let learning = assistant_message.metadata.as_ref().and_then(|m| m.learning.clone());
+        conversation.messages.push(assistant_message);
+
+        // What the reply says is worth remembering becomes facts for later FACTS_CHECKs
+        if let Some(learning) = learning.filter(|_| !conversation.ephemeral) {
+            let learned = facts::parse_learning(&learning);
+            match state.facts.lock().await.add_learned(learned, &conversation_id) {
+                Ok(added) if !added.is_empty() => sink.emit("facts-learned", &added)?,
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to save learned facts: {:?}", e),
+            }
+        }
+
+        if !conversation.summarizing && summary::messages_to_summarize(&conversation.messages).is_some() {
+            conversation.summarizing = true;
+            let id = conversation.id.clone();
+            tauri::async_runtime::spawn(roll_up_history(sink.app.clone(), id));
+        }
+
+        conversation.exchanges += 1;
+        // Names given by hand or brought in by an import are kept
+        if conversation.exchanges == 1 && conversation.name == DEFAULT_CONVERSATION_NAME {
+            let id = conversation.id.clone();
+            tauri::async_runtime::spawn(auto_title_conversation(sink.app.clone(), id));
+        }
+        if conversation.exchanges % tagging::AUTO_TAG_EVERY == 0 && !conversation.ephemeral {
+            let id = conversation.id.clone();
+            tauri::async_runtime::spawn(auto_tag_conversation(sink.app.clone(), id));
+        }
+    }
+    flush_conversation(state, &*handle.lock().await).await;
+
+    if cancelled {
+        let event = ChatCancelled {
+            conversation_id,
+            partial_response: handle.lock().await.redactions.restore(&complete_message),
+        };
+        sink.emit("chat-cancelled", &event)?;
+    }
+
+    if ephemeral {
+        return Ok(());
+    }
+    track_usage(state, "chat", started).await;
+    track_activity(state, |analytics| {
+        let messages = if complete_message.is_empty() { 1 } else { 2 };
+        // Real counts when Ollama reported them, otherwise an estimate
+        let tokens = match &stats {
+            Some(stats) => stats.prompt_tokens + stats.completion_tokens,
+            None => context::estimate_tokens(&prompt_text) + context::estimate_tokens(&complete_message),
+        };
+        analytics.record_chat(&model, messages, tokens)
+    })
+    .await;
+    Ok(())
+}
+
+// Reasoning goes out on its own channel; only the answer is streamed as the reply and kept
+fn emit_reply(
+    sink: &EventSink,
+    conversation_id: &str,
+    split: Split,
+    reply: &mut String,
+    sections: &mut SectionParser,
+    gate: &mut ReplyGate,
+) -> Result<(), String> {
+    reply.push_str(&split.visible);
+    emit_split(sink, conversation_id, gate.pass(split), sections)
+}
+
+fn emit_split(sink: &EventSink, conversation_id: &str, split: Split, sections: &mut SectionParser) -> Result<(), String> {
+    if !split.reasoning.is_empty() {
+        let event = ChatReasoning {
+            conversation_id: conversation_id.to_string(),
+            content: split.reasoning,
+        };
+        sink.emit("chat-reasoning", &event)?;
+    }
+    if !split.visible.is_empty() {
+        sink.emit("chat-response", &split.visible)?;
+        for update in sections.push(&split.visible) {
+            emit_section(sink, conversation_id, update)?;
+        }
+    }
+    Ok(())
+}
+
+fn emit_section(sink: &EventSink, conversation_id: &str, (section, content): (Section, String)) -> Result<(), String> {
+    let event = ChatSection {
+        conversation_id: conversation_id.to_string(),
+        section,
+        content,
+    };
+    sink.emit("chat-section", &event)
+}
+
+// Tries each configured fallback in turn. On success `request` names the model that
+// answered and the backend it runs on is returned with the stream.
+async fn open_fallback_stream(
+    state: &AppState,
+    request: &mut ChatRequest,
+    remote_allowed: bool,
+) -> Option<(Box<dyn LlmBackend>, BackendKind, Receiver<Result<ChatChunk, OllamaError>>)> {
+    let settings = state.settings.lock().await.clone();
+    for target in &settings.backend.fallbacks {
+        let kind = target.backend.unwrap_or(settings.backend.kind);
+        if kind == settings.backend.kind && target.model == request.model {
+            continue;
+        }
+        let mut target_settings = settings.clone();
+        target_settings.backend.kind = kind;
+        let Ok(backend) = llm_backend(&target_settings) else {
+            continue;
+        };
+        if !remote_allowed && !backend.is_local() {
+            continue;
+        }
+        let mut attempt = request.clone();
+        attempt.model = target.model.clone();
+        if let Ok(receiver) = backend.chat_stream(attempt).await {
+            request.model = target.model.clone();
+            return Some((backend, kind, receiver));
+        }
+    }
+    None
+}
+
+#[derive(Serialize, Clone)]
+struct ConversationSummarized {
+    conversation_id: String,
+    summary: String,
+    summarized_messages: usize,
+}
+
+// Background rolling summary: the oldest messages are folded into the conversation
+// summary and dropped from the history
+async fn roll_up_history(app: AppHandle, conversation_id: String) {
+    let state = app.state::<AppState>();
+    let Ok(handle) = conversation(&state, Some(&conversation_id)).await else {
+        return;
+    };
+    let (older, previous, no_web) = {
+        let mut conversation = handle.lock().await;
+        let Some(count) = summary::messages_to_summarize(&conversation.messages) else {
+            conversation.summarizing = false;
+            return;
+        };
+        (conversation.messages[..count].to_vec(), conversation.summary.clone(), conversation.no_web)
+    };
+
+    // Pinned messages stay in the history verbatim, so they are left out of the summary
+    let unpinned: Vec<ChatMessage> = older.iter().filter(|m| !m.pinned).cloned().collect();
+    if unpinned.is_empty() {
+        handle.lock().await.summarizing = false;
+        return;
+    }
+    let result = rolling_summary(&state, &conversation_id, &unpinned, previous.as_deref(), no_web).await;
+    let mut conversation = handle.lock().await;
+    conversation.summarizing = false;
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Summarizing {} skipped: {}", conversation_id, e);
+            return;
+        }
+    };
+    // The history may have been cleared or edited while the summary was written
+    let unchanged = conversation.messages.len() >= older.len()
+        && conversation.messages.iter().zip(&older).all(|(a, b)| a.content == b.content);
+    if !unchanged {
+        return;
+    }
+    let pinned: Vec<ChatMessage> = conversation.messages.drain(..older.len()).filter(|m| m.pinned).collect();
+    conversation.messages.splice(..0, pinned);
+    conversation.summary = Some(summary.clone());
+    drop(conversation);
+
+    let payload = ConversationSummarized {
+        conversation_id,
+        summary,
+        summarized_messages: unpinned.len(),
+    };
+    let _ = app.emit("conversation-summarized", &payload);
+}
+
+async fn rolling_summary(
+    state: &AppState,
+    conversation_id: &str,
+    messages: &[ChatMessage],
+    previous: Option<&str>,
+    no_web: bool,
+) -> Result<String, String> {
+    // Background work; skipped entirely in low-power mode
+    if is_low_power(state).await {
+        return Err("low-power mode is on".to_string());
+    }
+    // The transcript would go out unredacted, so remote providers only get it with redaction off
+    let client = state.backend.lock().await.clone();
+    if !client.is_local() && (no_web || state.settings.lock().await.redaction.enabled) {
+        return Err("the chat backend is remote".to_string());
+    }
+    let workspace = conversation_workspace(state, Some(conversation_id)).await;
+    let model = chat_model(state, workspace.as_ref()).await;
+    summary::summarize(client.as_ref(), &model, previous, messages)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Bullet summary and action items for the whole conversation, stored with it
+#[tauri::command]
+async fn summarize_conversation(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ConversationDigest, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let client = state.backend.lock().await.clone();
+    let redaction = state.settings.lock().await.redaction.clone();
+    let (id, mut messages, previous) = {
+        let mut conversation = handle.lock().await;
+        if conversation.messages.is_empty() {
+            return Err("The conversation has no messages yet".to_string());
+        }
+        if conversation.no_web && !client.is_local() {
+            return Err("Remote providers are disabled for this conversation".to_string());
+        }
+        let mut messages = conversation.messages.clone();
+        let mut previous = conversation.summary.clone();
+        // Same scrubbing as chat requests; the digest gets the originals back below
+        if redaction.enabled && !client.is_local() {
+            let redactor = Redactor::new(&redaction).map_err(|e| e.to_string())?;
+            for message in messages.iter_mut() {
+                message.content = redactor.redact(&message.content, &mut conversation.redactions);
+            }
+            previous = previous.map(|s| redactor.redact(&s, &mut conversation.redactions));
+        }
+        (conversation.id.clone(), messages, previous)
+    };
+    messages.retain(|m| m.role != "system");
+
+    let workspace = conversation_workspace(&state, Some(&id)).await;
+    let model = chat_model(&state, workspace.as_ref()).await;
+    let mut digest = summary::digest(client.as_ref(), &model, previous.as_deref(), &messages)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut conversation = handle.lock().await;
+    for item in digest.summary.iter_mut().chain(digest.action_items.iter_mut()) {
+        *item = conversation.redactions.restore(item);
+    }
+    conversation.digest = Some(digest.clone());
+    Ok(digest)
+}
+
+#[tauri::command]
+async fn get_conversation_digest(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<ConversationDigest>, String> {
+    Ok(conversation(&state, conversation_id.as_deref()).await?.lock().await.digest.clone())
+}
+
+// Background naming after the first exchange, under the same limits as summaries
+async fn auto_title_conversation(app: AppHandle, conversation_id: String) {
+    let state = app.state::<AppState>();
+    let Ok(handle) = conversation(&state, Some(&conversation_id)).await else {
+        return;
+    };
+    let (messages, no_web) = {
+        let conversation = handle.lock().await;
+        (conversation.messages.clone(), conversation.no_web)
+    };
+    if is_low_power(&state).await {
+        return;
+    }
+    let client = state.backend.lock().await.clone();
+    if !client.is_local() && (no_web || state.settings.lock().await.redaction.enabled) {
+        return;
+    }
+
+    let workspace = conversation_workspace(&state, Some(&conversation_id)).await;
+    let model = chat_model(&state, workspace.as_ref()).await;
+    let name = match titles::suggest_title(client.as_ref(), &model, &messages).await {
+        Ok(name) => name,
+        Err(e) => {
+            eprintln!("Titling {} skipped: {:?}", conversation_id, e);
+            return;
+        }
+    };
+
+    // The user may have renamed it in the meantime
+    let mut conversation = handle.lock().await;
+    if conversation.name != DEFAULT_CONVERSATION_NAME {
+        return;
+    }
+    conversation.name = name.clone();
+    drop(conversation);
+
+    let payload = ConversationTitled { conversation_id, name };
+    let _ = app.emit("conversation-titled", &payload);
+}
+
+// Background topic tagging; new tags are merged into the conversation's existing ones
+async fn auto_tag_conversation(app: AppHandle, conversation_id: String) {
+    let state = app.state::<AppState>();
+    let Ok(handle) = conversation(&state, Some(&conversation_id)).await else {
+        return;
+    };
+    let (messages, existing, no_web) = {
+        let conversation = handle.lock().await;
+        (conversation.messages.clone(), conversation.tags.clone(), conversation.no_web)
+    };
+
+    // Background work; skipped entirely in low-power mode
+    if is_low_power(&state).await {
+        return;
+    }
+
+    // The transcript would go out unredacted, so remote providers only get it with redaction off
+    let client = state.backend.lock().await.clone();
+    if !client.is_local() && (no_web || state.settings.lock().await.redaction.enabled) {
+        return;
+    }
+
+    let workspace = conversation_workspace(&state, Some(&conversation_id)).await;
+    let model = chat_model(&state, workspace.as_ref()).await;
+    let suggested = match tagging::suggest_tags(client.as_ref(), &model, &messages, &existing).await {
+        Ok(tags) => tags,
+        Err(e) => {
+            eprintln!("Auto-tagging failed for {}: {:?}", conversation_id, e);
+            return;
+        }
+    };
+
+    let mut conversation = handle.lock().await;
+    let added: Vec<String> = suggested
+        .into_iter()
+        .filter(|tag| !conversation.tags.contains(tag))
+        .collect();
+    if added.is_empty() {
+        return;
+    }
+    tagging::merge_tags(&mut conversation.tags, added.clone());
+    let payload = ConversationTags {
+        conversation_id,
+        tags: conversation.tags.clone(),
+    };
+    drop(conversation);
+
+    track_activity(&state, |analytics| analytics.record_tags(&added)).await;
+    let _ = app.emit("conversation-tagged", &payload);
+}
+
+#[tauri::command]
+async fn get_conversation_tags(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    Ok(conversation(&state, conversation_id.as_deref()).await?.lock().await.tags.clone())
+}
+
+// Replaces the tags by hand; later auto-tagging passes only add to them
+#[tauri::command]
+async fn set_conversation_tags(
+    conversation_id: Option<String>,
+    tags: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let mut normalized = Vec::new();
+    tagging::merge_tags(&mut normalized, tags.iter().filter_map(|t| tagging::normalize_tag(t)));
+    handle.lock().await.tags = normalized.clone();
+    Ok(normalized)
+}
+
+#[tauri::command]
+async fn create_tag(name: String, state: State<'_, AppState>) -> Result<String, String> {
+    state.tags.lock().await.create(&name).map_err(|e| e.to_string())
+}
+
+// Also takes the tag off every conversation
+#[tauri::command]
+async fn delete_tag(tag: String, state: State<'_, AppState>) -> Result<(), String> {
+    state.tags.lock().await.delete(&tag).map_err(|e| e.to_string())?;
+    let handles: Vec<_> = state.conversations.lock().await.open.values().cloned().collect();
+    for handle in handles {
+        handle.lock().await.tags.retain(|t| *t != tag);
+    }
+    Ok(())
+}
+
+// Created tags and those in use, with how many conversations carry each
+#[tauri::command]
+async fn list_tags(state: State<'_, AppState>) -> Result<Vec<TagCount>, String> {
+    let mut counts: HashMap<String, usize> = state.tags.lock().await.list().iter().map(|t| (t.clone(), 0)).collect();
+    let handles: Vec<_> = state.conversations.lock().await.open.values().cloned().collect();
+    for handle in handles {
+        for tag in &handle.lock().await.tags {
+            *counts.entry(tag.clone()).or_default() += 1;
+        }
+    }
+    let mut tags: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, conversations)| TagCount { tag, conversations })
+        .collect();
+    tags.sort_by(|a, b| a.tag.cmp(&b.tag));
+    Ok(tags)
+}
+
+// Assigning a tag that does not exist yet creates it
+#[tauri::command]
+async fn assign_tag(
+    conversation_id: Option<String>,
+    tag: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let tag = state.tags.lock().await.create(&tag).map_err(|e| e.to_string())?;
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let mut conversation = handle.lock().await;
+    tagging::merge_tags(&mut conversation.tags, [tag]);
+    Ok(conversation.tags.clone())
+}
+
+#[tauri::command]
+async fn unassign_tag(
+    conversation_id: Option<String>,
+    tag: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let mut conversation = handle.lock().await;
+    conversation.tags.retain(|t| *t != tag);
+    Ok(conversation.tags.clone())
+}
+
+async fn run_slash_command(
+    sink: &EventSink,
+    state: &AppState,
+    conversation_id: &str,
+    command: SlashCommand,
+) -> Result<(), String> {
+    let (name, message) = match command {
+        SlashCommand::Search(query) => {
+            let results = run_search(&sink.app, state, Some(conversation_id), query.clone()).await?;
+            ("search", format!("Found {} results for \"{}\"", results.len(), query))
+        }
+        SlashCommand::Clear => {
+            reset_conversation(state, conversation_id).await?;
+            ("clear", "Conversation cleared".to_string())
+        }
+        SlashCommand::Model(None) => {
+            let workspace = conversation_workspace(state, Some(conversation_id)).await;
+            ("model", format!("Current model: {}", chat_model(state, workspace.as_ref()).await))
+        }
+        SlashCommand::Model(Some(model)) => {
+            let model = select_model(state, &model).await?;
+            ("model", format!("Switched to {}", model))
+        }
+        SlashCommand::Persona(None) => {
+            let custom = conversation(state, Some(conversation_id)).await?.lock().await.system_prompt.clone();
+            let message = match custom {
+                Some(prompt) => format!("Custom system prompt: {}", prompt),
+                None => "Using the default system prompt".to_string(),
+            };
+            ("persona", message)
+        }
+        SlashCommand::Persona(Some(prompt)) => {
+            let handle = conversation(state, Some(conversation_id)).await?;
+            if prompt.eq_ignore_ascii_case("default") {
+                handle.lock().await.system_prompt = None;
+                ("persona", "Switched back to the default system prompt".to_string())
+            } else {
+                handle.lock().await.system_prompt = Some(prompt);
+                ("persona", "System prompt replaced for this conversation".to_string())
+            }
+        }
+        SlashCommand::Ingest(target) => {
+            if !target.starts_with("http://") && !target.starts_with("https://") {
+                return Err("Only web pages can be ingested for now".to_string());
+            }
+            let snapshot = snapshot_page(state, Some(conversation_id), &target).await?;
+            ("ingest", format!("Saved \"{}\" to the knowledge base", snapshot.title))
+        }
+    };
+
+    let result = SlashCommandResult {
+        command: name.to_string(),
+        message,
+    };
+    sink.emit("slash-command-result", &result)
+}
+
+#[tauri::command]
+async fn list_snippets(state: State<'_, AppState>) -> Result<Vec<Snippet>, String> {
+    Ok(state.snippets.lock().await.list())
+}
+
+#[tauri::command]
+async fn save_snippet(
+    trigger: String,
+    expansion: String,
+    state: State<'_, AppState>,
+) -> Result<Snippet, String> {
+    state
+        .snippets
+        .lock()
+        .await
+        .upsert(&trigger, expansion)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_snippet(trigger: String, state: State<'_, AppState>) -> Result<(), String> {
+    match state.snippets.lock().await.delete(&trigger) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("No snippet: {}", trigger)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+async fn save_conversation_template(
+    name: String,
+    system_prompt: Option<String>,
+    first_message: Option<String>,
+    model: Option<String>,
+    options: Option<GenerationOptions>,
+    state: State<'_, AppState>,
+) -> Result<ConversationTemplate, String> {
+    state
+        .templates
+        .lock()
+        .await
+        .add(&name, system_prompt, first_message, model, options.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_conversation_templates(state: State<'_, AppState>) -> Result<Vec<ConversationTemplate>, String> {
+    Ok(state.templates.lock().await.list())
+}
+
+#[tauri::command]
+async fn delete_conversation_template(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    match state.templates.lock().await.delete(&id) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("No template: {}", id)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct TemplateStart {
+    conversation: ConversationInfo,
+    // Set when the template's first message is being answered
+    stream_id: Option<String>,
+}
+
+// Creates a conversation from the template and sends its first message, if it has one;
+// returns without waiting for the answer
+#[tauri::command]
+async fn start_from_template(
+    window: tauri::Window,
+    template_id: String,
+    state: State<'_, AppState>,
+) -> Result<TemplateStart, String> {
+    let template = state
+        .templates
+        .lock()
+        .await
+        .get(&template_id)
+        .ok_or_else(|| format!("No template: {}", template_id))?;
+    let id = add_conversation(&state, template.name.clone()).await?;
+    let handle = conversation(&state, Some(&id)).await?;
+    let info = {
+        let mut conversation = handle.lock().await;
+        conversation.system_prompt = template.system_prompt;
+        conversation.model = template.model;
+        conversation.options = template.options;
+        conversation.info(false)
+    };
+
+    let Some(message) = template.first_message else {
+        return Ok(TemplateStart {
+            conversation: info,
+            stream_id: None,
+        });
+    };
+    let stream_id = Uuid::new_v4().to_string();
+    let sink = EventSink::window(&window).with_stream(stream_id.clone());
+    tauri::async_runtime::spawn(async move {
+        let state = sink.app.state::<AppState>();
+        if let Err(e) = run_chat(&sink, &state, &id, message, None).await {
+            eprintln!("Template first message failed for {}: {}", id, e);
+        }
+    });
+    Ok(TemplateStart {
+        conversation: info,
+        stream_id: Some(stream_id),
+    })
+}
+
+// Exactly what the next chat request would carry ahead of the new message
+#[tauri::command]
+async fn get_context_preview(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ContextPreview, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let conversation = handle.lock().await;
+    let workspace = state.workspaces.lock().await.for_conversation(&conversation.id).cloned();
+    let variables = prompt_variables(&state, workspace.as_ref()).await;
+    // The language detected from the next message is unknown yet; only an override shows
+    let system = SystemPrompt {
+        custom: conversation.system_prompt.as_deref(),
+        persona: workspace.as_ref().and_then(|w| w.persona.as_deref()),
+        variables: &variables,
+        reply_language: conversation.reply_language.as_deref(),
+        summary: conversation.summary.as_deref(),
+        // Matched against the next message, which is not known yet
+        facts: None,
+    };
+    let context_tokens = conversation.options.num_ctx.unwrap_or(DEFAULT_CONTEXT_TOKENS);
+    let mut entries = context::preview_entries(&conversation.messages, &system, context_tokens);
+
+    // Mirror the redaction applied when talking to a remote provider, on a scratch map
+    let is_local = state.backend.lock().await.is_local();
+    let redaction = state.settings.lock().await.redaction.clone();
+    let redacted = redaction.enabled && !is_local;
+    if redacted {
+        let redactor = Redactor::new(&redaction).map_err(|e| e.to_string())?;
+        let mut redactions = conversation.redactions.clone();
+        for entry in entries.iter_mut().filter(|e| e.role != "system") {
+            entry.content = redactor.redact(&entry.content, &mut redactions);
+            entry.tokens = context::estimate_tokens(&entry.content);
+        }
+    }
+
+    Ok(ContextPreview {
+        conversation_id: conversation.id.clone(),
+        model: chat_model(&state, workspace.as_ref()).await,
+        redacted,
+        context_tokens,
+        total_tokens: entries.iter().filter(|e| e.included).map(|e| e.tokens).sum(),
+        entries,
+    })
+}
+
+// Reads an image the user picked so it can be sent with the next chat_stream call
+#[tauri::command]
+async fn attach_image(path: String) -> Result<ImageAttachment, String> {
+    attachments::load_image(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+// One-off completion outside any conversation: no history, no default system prompt
+#[tauri::command]
+async fn complete_text(
+    prompt: String,
+    model: Option<String>,
+    system: Option<String>,
+    options: Option<GenerationOptions>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    if let Some(options) = &options {
+        options.validate().map_err(|e| e.to_string())?;
+    }
+    let model = match model {
+        Some(model) => model,
+        None => state.model.lock().await.clone(),
+    };
+    // Through the chat backend, since the selected model may not be an Ollama one
+    let mut messages = Vec::new();
+    if let Some(system) = system.filter(|s| !s.trim().is_empty()) {
+        let mut message = OllamaClient::create_system_message();
+        message.content = system;
+        messages.push(message);
+    }
+    messages.push(OllamaClient::create_user_message(prompt));
+    let client = state.backend.lock().await.clone();
+    let request = ChatRequest {
+        model,
+        messages,
+        stream: false,
+        keep_alive: None,
+        options: options.and_then(|o| o.for_request()),
+        tools: None,
+        logprobs: None,
+        top_logprobs: None,
+    };
+    let reply = client.chat(request).await.map_err(|e| e.to_string())?;
+    Ok(reply.content)
+}
+
+// Infill between the code before and after the cursor, for models such as codellama:code
+// or qwen2.5-coder; returns only the inserted text
+#[tauri::command]
+async fn complete_code(
+    prefix: String,
+    suffix: String,
+    model: Option<String>,
+    options: Option<GenerationOptions>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    if state.settings.lock().await.backend.kind != BackendKind::Ollama {
+        return Err("Code completion is only available with Ollama".to_string());
+    }
+    if let Some(options) = &options {
+        options.validate().map_err(|e| e.to_string())?;
+    }
+    let model = match model {
+        Some(model) => model,
+        None => state.model.lock().await.clone(),
+    };
+    let client = state.ollama.lock().await.clone();
+    let request = GenerateRequest {
+        model,
+        prompt: prefix,
+        suffix: Some(suffix),
+        system: None,
+        stream: false,
+        options: options.and_then(|o| o.for_request()),
+        keep_alive: None,
+        raw: None,
+        logprobs: None,
+        top_logprobs: None,
+    };
+    client.generate(request).await.map_err(|e| e.to_string())
+}
+
+// Developer mode: the prompt goes to the model untemplated and the reply comes back with
+// its token probabilities
+#[tauri::command]
+async fn complete_raw(
+    prompt: String,
+    model: Option<String>,
+    options: Option<GenerationOptions>,
+    state: State<'_, AppState>,
+) -> Result<Completion, String> {
+    let (kind, developer) = {
+        let settings = state.settings.lock().await;
+        (settings.backend.kind, settings.developer.clone())
+    };
+    if !developer.enabled {
+        return Err("Raw completions need developer mode".to_string());
+    }
+    if kind != BackendKind::Ollama {
+        return Err("Raw completions are only available with Ollama".to_string());
+    }
+    if let Some(options) = &options {
+        options.validate().map_err(|e| e.to_string())?;
+    }
+    let model = match model {
+        Some(model) => model,
+        None => state.model.lock().await.clone(),
+    };
+    let (logprobs, top_logprobs) = developer.logprobs();
+    let client = state.ollama.lock().await.clone();
+    let request = GenerateRequest {
+        model,
+        prompt,
+        suffix: None,
+        system: None,
+        stream: false,
+        options: options.and_then(|o| o.for_request()),
+        keep_alive: None,
+        raw: Some(true),
+        logprobs,
+        top_logprobs,
+    };
+    client.complete(request).await.map_err(|e| e.to_string())
+}
+
+// Loads the conversation's model so the first message does not wait for it; returns its name
+async fn preload(state: &AppState, conversation_id: Option<&str>) -> Result<String, String> {
+    if state.settings.lock().await.backend.kind != BackendKind::Ollama {
+        return Err("Preloading is only available with Ollama".to_string());
+    }
+    let workspace = conversation_workspace(state, conversation_id).await;
+    let model = chat_model(state, workspace.as_ref()).await;
+    let keep_alive = if is_low_power(state).await {
+        power::KEEP_ALIVE
+    } else {
+        PRELOAD_KEEP_ALIVE
+    };
+    let client = state.ollama.lock().await.clone();
+    client.preload(&model, keep_alive).await.map_err(|e| e.to_string())?;
+    Ok(model)
+}
+
+#[tauri::command]
+async fn preload_model(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    preload(&state, conversation_id.as_deref()).await
+}
+
+// Warms up the selected model at startup; skipped in low-power mode and when Ollama is not up yet
+async fn run_startup_preload(app: AppHandle) {
+    let state = app.state::<AppState>();
+    if is_low_power(&state).await {
+        return;
+    }
+    if let Ok(model) = preload(&state, None).await {
+        let _ = app.emit("model-preloaded", model);
+    }
+}
+
+#[tauri::command]
+async fn diff_messages(message_a: String, message_b: String) -> Result<MessageDiff, String> {
+    Ok(diff::diff_words(&message_a, &message_b))
+}
+
+#[tauri::command]
+async fn export_conversation(
+    path: String,
+    bundle_assets: bool,
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let conversation_id = conversation_id.as_deref();
+    let (name, messages) = {
+        let handle = conversation(&state, conversation_id).await?;
+        let conversation = handle.lock().await;
+        (conversation.name.clone(), conversation.messages.clone())
+    };
+    let mut markdown = export::render_markdown(&messages, &name);
+
+    if bundle_assets {
+        ensure_web_allowed(&state, conversation_id).await?;
+        let client = state.search.lock().await.client.clone();
+        markdown = export::bundle_assets(&markdown, Path::new(&path), &client)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    std::fs::write(&path, markdown).map_err(|e| e.to_string())
+}
+
+// Round-trippable backup; import_conversation loads it into a new conversation
+#[tauri::command]
+async fn export_conversation_json(
+    path: String,
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let conversation = handle.lock().await;
+    let mut export = ConversationExport::new(
+        conversation.name.clone(),
+        conversation.created_at,
+        conversation.messages.clone(),
+    );
+    export.system_prompt = conversation.system_prompt.clone();
+    export.summary = conversation.summary.clone();
+    export.options = conversation.options.clone();
+    export.tags = conversation.tags.clone();
+    export.no_web = conversation.no_web;
+    export.reply_language = conversation.reply_language.clone();
+    drop(conversation);
+    export.save(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn import_conversation(path: String, state: State<'_, AppState>) -> Result<ConversationInfo, String> {
+    let export = ConversationExport::load(Path::new(&path)).map_err(|e| e.to_string())?;
+    load_export(&state, export).await
+}
+
+// conversations.json from a ChatGPT data export; each thread becomes a new conversation
+#[tauri::command]
+async fn import_chatgpt(path: String, state: State<'_, AppState>) -> Result<Vec<ConversationInfo>, String> {
+    let exports = chatgpt::load(Path::new(&path)).map_err(|e| e.to_string())?;
+    let mut imported = Vec::new();
+    for export in exports {
+        imported.push(load_export(&state, export).await?);
+    }
+    Ok(imported)
+}
+
+async fn load_export(state: &AppState, export: ConversationExport) -> Result<ConversationInfo, String> {
+    let id = add_conversation(state, export.name.trim().to_string()).await?;
+    let handle = conversation(state, Some(&id)).await?;
+    let mut conversation = handle.lock().await;
+    conversation.exchanges = export.messages.iter().filter(|m| m.role == "assistant").count();
+    conversation.created_at = export.created_at;
+    conversation.messages = export.messages;
+    conversation.system_prompt = export.system_prompt;
+    conversation.summary = export.summary;
+    conversation.options = export.options;
+    conversation.tags = export.tags;
+    conversation.no_web = export.no_web;
+    conversation.reply_language = export.reply_language;
+    Ok(conversation.info(false))
+}
+
+// Renders the conversation to HTML in a separate webview and opens the system
+// print dialog there, which offers "Save as PDF" on every platform
+#[tauri::command]
+async fn export_conversation_pdf(
+    app: AppHandle,
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let (name, messages) = {
+        let handle = conversation(&state, conversation_id.as_deref()).await?;
+        let conversation = handle.lock().await;
+        (conversation.name.clone(), conversation.messages.clone())
+    };
+    let exported_at = Local::now().format("%Y-%m-%d %H:%M").to_string();
+    let html = export::render_html(&messages, &name, &exported_at);
+    let html = serde_json::to_string(&html).map_err(|e| e.to_string())?;
+
+    if let Some(existing) = app.get_webview_window("print-export") {
+        let _ = existing.close();
+    }
+
+    // document.write can fire another load event, so only render once
+    let rendered = AtomicBool::new(false);
+    let blank = "about:blank".parse().map_err(|e: url::ParseError| e.to_string())?;
+    WebviewWindowBuilder::new(&app, "print-export", WebviewUrl::External(blank))
+        .title("Export conversation")
+        .on_page_load(move |window, payload| {
+            if payload.event() == PageLoadEvent::Finished && !rendered.swap(true, Ordering::SeqCst) {
+                let script = format!(
+                    "document.open(); document.write({}); document.close(); setTimeout(() => window.print(), 100);",
+                    html
+                );
+                if let Err(e) = window.eval(&script) {
+                    eprintln!("Failed to render print export: {:?}", e);
+                }
+            }
+        })
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+async fn snapshot_page(state: &AppState, conversation_id: Option<&str>, url: &str) -> Result<Snapshot, String> {
+    ensure_web_allowed(state, conversation_id).await?;
+    let client = state.search.lock().await.client.clone();
+    let page = client
+        .fetch_text(url)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Could not fetch a text page from {}", url))?;
+
+    let snapshot = state
+        .knowledge
+        .lock()
+        .await
+        .add_snapshot(url, &page)
+        .map_err(|e| e.to_string())?;
+    state
+        .workspaces
+        .lock()
+        .await
+        .add_to_active(|workspace| workspace.snapshot_ids.push(snapshot.id.clone()))
+        .map_err(|e| e.to_string())?;
+
+    track_search(state, |history| history.mark_used(url)).await;
+    Ok(snapshot)
+}
+
+#[tauri::command]
+async fn save_page_snapshot(url: String, state: State<'_, AppState>) -> Result<Snapshot, String> {
+    snapshot_page(&state, None, &url).await
+}
+
+#[tauri::command]
+// Scoped to the active workspace's snapshots when one is active
+async fn list_snapshots(state: State<'_, AppState>) -> Result<Vec<Snapshot>, String> {
+    let snapshots = state.knowledge.lock().await.list();
+    Ok(match state.workspaces.lock().await.active() {
+        Some(workspace) => snapshots
+            .into_iter()
+            .filter(|s| workspace.snapshot_ids.contains(&s.id))
+            .collect(),
+        None => snapshots,
+    })
+}
+
+#[tauri::command]
+async fn get_snapshot(id: String, state: State<'_, AppState>) -> Result<SnapshotContent, String> {
+    state.knowledge.lock().await.get(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_snapshot(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    match state.knowledge.lock().await.delete(&id) {
+        Ok(true) => state.workspaces.lock().await.forget(&id).map_err(|e| e.to_string()),
+        Ok(false) => Err(format!("No snapshot: {}", id)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn emit_job(app: &AppHandle, job: &Job) {
+    let _ = app.emit("job-progress", job);
+}
+
+// Drives a job from its last completed step until it finishes, fails or is cancelled
+async fn run_job(app: AppHandle, mut worker: JobWorker) {
+    let state = app.state::<AppState>();
+    let Some(job) = state.jobs.lock().await.get(&worker.id) else {
+        return;
+    };
+
+    let result = match &job.kind {
+        JobKind::Snapshot { urls } => {
+            run_snapshot_job(&app, &state, &mut worker, urls, job.completed_steps).await
+        }
+        JobKind::ModelPull { model } => run_model_pull_job(&app, &state, &mut worker, model).await,
+    };
+
+    let finished = state.jobs.lock().await.finish(&worker.id, result.err());
+    match finished {
+        Ok(job) => emit_job(&app, &job),
+        Err(e) => eprintln!("Failed to update job {}: {:?}", worker.id, e),
+    }
+}
+
+// Pages that fail are reported in the progress message and skipped
+async fn run_snapshot_job(
+    app: &AppHandle,
+    state: &AppState,
+    worker: &mut JobWorker,
+    urls: &[String],
+    start: usize,
+) -> Result<(), String> {
+    for (index, url) in urls.iter().enumerate().skip(start) {
+        if !worker.checkpoint().await {
+            return Ok(());
+        }
+        let message = match snapshot_page(state, None, url).await {
+            Ok(snapshot) => format!("Saved \"{}\"", snapshot.title),
+            Err(e) => format!("Skipped {}: {}", url, e),
+        };
+        let job = state
+            .jobs
+            .lock()
+            .await
+            .set_progress(&worker.id, index + 1, Some(message))
+            .map_err(|e| e.to_string())?;
+        emit_job(app, &job);
+    }
+    Ok(())
+}
+
+// Download detail for install UIs, alongside the job's percentage
+#[derive(Serialize, Clone)]
+struct ModelPullEvent<'a> {
+    job_id: &'a str,
+    model: &'a str,
+    #[serde(flatten)]
+    progress: &'a PullProgress,
+}
+
+async fn run_model_pull_job(
+    app: &AppHandle,
+    state: &AppState,
+    worker: &mut JobWorker,
+    model: &str,
+) -> Result<(), String> {
+    let ollama = state.ollama.lock().await.clone();
+    let mut updates = ollama.pull_model(model).await.map_err(|e| e.to_string())?;
+
+    // Ollama reports many times a second; only persist and emit visible changes
+    let mut last = (0, String::new());
+    while let Some(update) = updates.recv().await {
+        if !worker.checkpoint().await {
+            return Ok(());
+        }
+        if let Some(error) = update.error {
+            return Err(error);
+        }
+
+        let percent = match (update.completed, update.total) {
+            (Some(completed), Some(total)) if total > 0 => (completed * 100 / total) as usize,
+            _ if update.status == "success" => 100,
+            _ => last.0,
+        };
+        if (percent, update.status.as_str()) == (last.0, last.1.as_str()) {
+            continue;
+        }
+        let job = state
+            .jobs
+            .lock()
+            .await
+            .set_progress(&worker.id, percent, Some(update.status.clone()))
+            .map_err(|e| e.to_string())?;
+        emit_job(app, &job);
+        let event = ModelPullEvent {
+            job_id: &worker.id,
+            model,
+            progress: &update,
+        };
+        let _ = app.emit("model-pull-progress", &event);
+        last = (percent, update.status);
+    }
+
+    if last.1 != "success" {
+        return Err(format!("Pull of {} ended before it finished", model));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn start_snapshot_job(
+    app: AppHandle,
+    urls: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Job, String> {
+    if urls.is_empty() {
+        return Err("No URLs to snapshot".to_string());
+    }
+    ensure_web_allowed(&state, None).await?;
+    start_job(&app, &state, JobKind::Snapshot { urls }).await
+}
+
+// Jobs started in low-power mode begin paused and run once it ends
+async fn start_job(app: &AppHandle, state: &AppState, kind: JobKind) -> Result<Job, String> {
+    let (mut job, worker) = state
+        .jobs
+        .lock()
+        .await
+        .create(kind)
+        .map_err(|e| e.to_string())?;
+
+    let mut power = state.power.lock().await;
+    if power.low_power {
+        job = state.jobs.lock().await.pause(&job.id).map_err(|e| e.to_string())?;
+        power.paused_jobs.push(job.id.clone());
+    }
+    drop(power);
+
+    emit_job(app, &job);
+    tauri::async_runtime::spawn(run_job(app.clone(), worker));
+    Ok(job)
+}
+
+// Compares installed models with the registry and announces updates not seen before
+async fn find_model_updates(app: &AppHandle, state: &AppState) -> Result<Vec<ModelUpdate>, String> {
+    let ollama = state.ollama.lock().await.clone();
+    let tls = state.settings.lock().await.tls.clone();
+    let updates = model_updates::check(&ollama, &tls)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut announced = state.announced_updates.lock().await;
+    for update in &updates {
+        if announced.get(&update.model) != Some(&update.remote_digest) {
+            let _ = app.emit("model-update-available", update);
+            announced.insert(update.model.clone(), update.remote_digest.clone());
+        }
+    }
+    Ok(updates)
+}
+
+async fn run_model_update_checks(app: AppHandle) {
+    tokio::time::sleep(model_updates::FIRST_CHECK_DELAY).await;
+    loop {
+        let state = app.state::<AppState>();
+        if is_low_power(&state).await {
+            tokio::time::sleep(model_updates::CHECK_INTERVAL).await;
+            continue;
+        }
+        if let Err(e) = find_model_updates(&app, &state).await {
+            eprintln!("Model update check failed: {}", e);
+        }
+        tokio::time::sleep(model_updates::CHECK_INTERVAL).await;
+    }
+}
+
+// Models the chat backend offers, so the UI never offers one that is missing
+#[tauri::command]
+async fn list_models(state: State<'_, AppState>) -> Result<Vec<LocalModel>, String> {
+    let client = state.backend.lock().await.clone();
+    client.list_models().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn running_models(state: State<'_, AppState>) -> Result<Vec<RunningModel>, String> {
+    let client = state.ollama.lock().await.clone();
+    client.running_models().await.map_err(|e| e.to_string())
+}
+
+// Defaults to the selected model
+#[tauri::command]
+async fn show_model(model: Option<String>, state: State<'_, AppState>) -> Result<ModelInfo, String> {
+    let model = match model {
+        Some(model) => model,
+        None => state.model.lock().await.clone(),
+    };
+    let client = state.ollama.lock().await.clone();
+    client.show_model(&model).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_model_disk_usage(state: State<'_, AppState>) -> Result<ModelDiskUsage, String> {
+    let client = state.ollama.lock().await.clone();
+    client.disk_usage().await.map_err(|e| e.to_string())
+}
+
+// Bakes a persona into a named model so it no longer has to be sent with every turn
+#[tauri::command]
+async fn create_model(
+    name: String,
+    base_model: String,
+    system: Option<String>,
+    parameters: Option<GenerationOptions>,
+    state: State<'_, AppState>,
+) -> Result<ModelInfo, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Model name is empty".to_string());
+    }
+    if let Some(parameters) = &parameters {
+        parameters.validate().map_err(|e| e.to_string())?;
+    }
+    let client = state.ollama.lock().await.clone();
+    let request = CreateModelRequest {
+        model: name.clone(),
+        from: base_model,
+        system: system.filter(|s| !s.trim().is_empty()),
+        parameters: parameters.and_then(|p| p.for_request()),
+        stream: false,
+    };
+    client.create_model(request).await.map_err(|e| e.to_string())?;
+    client.show_model(&name).await.map_err(|e| e.to_string())
+}
+
+// Deleting the selected model falls back to the default one
+#[tauri::command]
+async fn delete_model(model: String, state: State<'_, AppState>) -> Result<(), String> {
+    let client = state.ollama.lock().await.clone();
+    client.delete_model(&model).await.map_err(|e| e.to_string())?;
+    let mut selected = state.model.lock().await;
+    if *selected == model {
+        *selected = DEFAULT_MODEL.to_string();
+    }
+    state.announced_updates.lock().await.remove(&model);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_model(state: State<'_, AppState>) -> Result<String, String> {
+    Ok(state.model.lock().await.clone())
+}
+
+#[tauri::command]
+async fn set_model(model: String, state: State<'_, AppState>) -> Result<String, String> {
+    select_model(&state, &model).await
+}
+
+#[tauri::command]
+async fn check_model_updates(app: AppHandle, state: State<'_, AppState>) -> Result<Vec<ModelUpdate>, String> {
+    find_model_updates(&app, &state).await
+}
+
+// Installs a model from the Ollama registry as a background job
+#[tauri::command]
+async fn pull_model(app: AppHandle, model: String, state: State<'_, AppState>) -> Result<Job, String> {
+    let model = model.trim().to_string();
+    if model.is_empty() {
+        return Err("Model name is empty".to_string());
+    }
+    start_job(&app, &state, JobKind::ModelPull { model }).await
+}
+
+// Pulls the newer version of an installed model as a background job
+#[tauri::command]
+async fn update_model(app: AppHandle, model: String, state: State<'_, AppState>) -> Result<Job, String> {
+    start_job(&app, &state, JobKind::ModelPull { model }).await
+}
+
+async fn is_low_power(state: &AppState) -> bool {
+    state.power.lock().await.low_power
+}
+
+// Re-evaluates low-power mode from the settings and battery state, pausing running
+// jobs on the way in and resuming the ones it paused on the way out
+async fn apply_power_state(app: &AppHandle, state: &AppState) -> PowerStatus {
+    let settings = state.settings.lock().await.power.clone();
+    let status = tauri::async_runtime::spawn_blocking(move || settings.status())
+        .await
+        .unwrap_or_else(|_| PowerSettings::default().status());
+
+    let mut power = state.power.lock().await;
+    if power.low_power == status.low_power {
+        return status;
+    }
+    power.low_power = status.low_power;
+
+    let mut jobs = state.jobs.lock().await;
+    if status.low_power {
+        let running: Vec<String> = jobs
+            .list()
+            .into_iter()
+            .filter(|job| job.status == JobStatus::Running)
+            .map(|job| job.id)
+            .collect();
+        for id in running {
+            match jobs.pause(&id) {
+                Ok(job) => {
+                    emit_job(app, &job);
+                    power.paused_jobs.push(id);
+                }
+                Err(e) => eprintln!("Could not pause job {}: {:?}", id, e),
+            }
+        }
+    } else {
+        // Jobs cancelled or resumed by hand in the meantime are simply skipped
+        for id in std::mem::take(&mut power.paused_jobs) {
+            if let Ok((job, worker)) = jobs.resume(&id) {
+                emit_job(app, &job);
+                if let Some(worker) = worker {
+                    tauri::async_runtime::spawn(run_job(app.clone(), worker));
+                }
+            }
+        }
+    }
+    drop(jobs);
+    drop(power);
+
+    let _ = app.emit("power-mode-changed", &status);
+    status
+}
+
+async fn run_power_monitor(app: AppHandle) {
+    loop {
+        let state = app.state::<AppState>();
+        apply_power_state(&app, &state).await;
+        tokio::time::sleep(power::POLL_INTERVAL).await;
+    }
+}
+
+#[tauri::command]
+async fn get_power_status(app: AppHandle, state: State<'_, AppState>) -> Result<PowerStatus, String> {
+    Ok(apply_power_state(&app, &state).await)
+}
+
+#[tauri::command]
+async fn list_jobs(state: State<'_, AppState>) -> Result<Vec<Job>, String> {
+    Ok(state.jobs.lock().await.list())
+}
+
+#[tauri::command]
+async fn pause_job(app: AppHandle, id: String, state: State<'_, AppState>) -> Result<Job, String> {
+    let job = state.jobs.lock().await.pause(&id).map_err(|e| e.to_string())?;
+    emit_job(&app, &job);
+    Ok(job)
+}
+
+#[tauri::command]
+async fn resume_job(app: AppHandle, id: String, state: State<'_, AppState>) -> Result<Job, String> {
+    let (job, worker) = state.jobs.lock().await.resume(&id).map_err(|e| e.to_string())?;
+    emit_job(&app, &job);
+    if let Some(worker) = worker {
+        tauri::async_runtime::spawn(run_job(app.clone(), worker));
+    }
+    Ok(job)
+}
+
+#[tauri::command]
+async fn cancel_job(app: AppHandle, id: String, state: State<'_, AppState>) -> Result<Job, String> {
+    let job = state.jobs.lock().await.cancel(&id).map_err(|e| e.to_string())?;
+    emit_job(&app, &job);
+    Ok(job)
+}
+
+#[tauri::command]
+async fn clear_finished_jobs(state: State<'_, AppState>) -> Result<(), String> {
+    state.jobs.lock().await.clear_finished().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn add_fact(
+    text: String,
+    topic: Option<String>,
+    source: Option<String>,
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Fact, String> {
+    state
+        .facts
+        .lock()
+        .await
+        .add(&text, topic.as_deref(), source, conversation_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_facts(state: State<'_, AppState>) -> Result<Vec<Fact>, String> {
+    Ok(state.facts.lock().await.list())
+}
+
+#[tauri::command]
+async fn delete_fact(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    match state.facts.lock().await.delete(&id) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("No fact: {}", id)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Writes per-topic Markdown notes into the chosen folder; returns the files written
+#[tauri::command]
+async fn export_facts_markdown(folder: String, state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let written = state
+        .facts
+        .lock()
+        .await
+        .export_markdown(Path::new(&folder))
+        .map_err(|e| e.to_string())?;
+    Ok(written.iter().map(|path| path.display().to_string()).collect())
+}
+
+#[tauri::command]
+async fn bookmark_result(
+    result: SearchResult,
+    tags: Vec<String>,
+    snapshot: bool,
+    state: State<'_, AppState>,
+) -> Result<Bookmark, String> {
+    let snapshot_id = if snapshot {
+        Some(snapshot_page(&state, None, &result.url).await?.id)
+    } else {
+        None
+    };
+    track_search(&state, |history| history.mark_used(&result.url)).await;
+
+    state
+        .bookmarks
+        .lock()
+        .await
+        .add(result, tags, snapshot_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_bookmarks(
+    tag: Option<String>,
+    query: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Bookmark>, String> {
+    Ok(state
+        .bookmarks
+        .lock()
+        .await
+        .search(tag.as_deref(), query.as_deref()))
+}
+
+#[tauri::command]
+async fn list_bookmark_tags(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    Ok(state.bookmarks.lock().await.tags())
+}
+
+#[tauri::command]
+async fn set_bookmark_tags(
+    id: String,
+    tags: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Bookmark, String> {
+    state
+        .bookmarks
+        .lock()
+        .await
+        .set_tags(&id, tags)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No bookmark: {}", id))
+}
+
+#[tauri::command]
+async fn delete_bookmark(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    match state.bookmarks.lock().await.delete(&id) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("No bookmark: {}", id)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+async fn list_slash_commands() -> Result<Vec<SlashCommandInfo>, String> {
+    Ok(slash::list())
+}
+
+#[tauri::command]
+async fn clear_conversation(conversation_id: Option<String>, state: State<'_, AppState>) -> Result<(), String> {
+    let conversation_id = match conversation_id {
+        Some(id) => id,
+        None => state.conversations.lock().await.default_id.clone(),
+    };
+    reset_conversation(&state, &conversation_id).await
+}
+
+// The history as the UI shows it, with timestamps and the model behind each answer
+#[tauri::command]
+async fn get_messages(conversation_id: Option<String>, state: State<'_, AppState>) -> Result<Vec<ChatMessage>, String> {
+    Ok(conversation(&state, conversation_id.as_deref()).await?.lock().await.messages.clone())
+}
+
+// Thumbs up or down on an answer. With `save_learning`, a thumbs-up also files the
+// answer's LEARNING section as a fact.
+#[tauri::command]
+async fn rate_message(
+    conversation_id: Option<String>,
+    message_index: usize,
+    rating: Rating,
+    comment: Option<String>,
+    save_learning: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Feedback, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let (id, message, prompt) = {
+        let conversation = handle.lock().await;
+        if conversation.ephemeral {
+            return Err("Answers in incognito conversations can't be rated".to_string());
+        }
+        let message = match conversation.messages.get(message_index) {
+            Some(message) if message.role == "assistant" => message.clone(),
+            _ => return Err(format!("Message {} is not an answer", message_index)),
+        };
+        let prompt = conversation.messages[..message_index]
+            .iter()
+            .rev()
+            .find(|m| m.role == "user")
+            .map(|m| m.content.clone());
+        (conversation.id.clone(), message, prompt)
+    };
+
+    let learning = message.metadata.as_ref().and_then(|m| m.learning.clone());
+    let feedback = Feedback {
+        conversation_id: id.clone(),
+        message_index,
+        message_created_at: message.created_at,
+        rating,
+        comment: comment.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()),
+        model: message.model,
+        prompt,
+        response: message.content,
+        created_at: Utc::now(),
+    };
+    let feedback = state.feedback.lock().await.rate(feedback).map_err(|e| e.to_string())?;
+
+    if let (Rating::Up, Some(true), Some(learning)) = (rating, save_learning, learning) {
+        state
+            .facts
+            .lock()
+            .await
+            .add(&learning, None, Some("feedback".to_string()), Some(id))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(feedback)
+}
+
+#[tauri::command]
+async fn list_feedback(
+    rating: Option<Rating>,
+    model: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Feedback>, String> {
+    Ok(state.feedback.lock().await.list(rating, model.as_deref()))
+}
+
+#[derive(Serialize, Clone)]
+struct ConversationStats {
+    conversation_id: String,
+    messages: usize,
+    user_messages: usize,
+    assistant_messages: usize,
+    // Includes messages since folded into the summary
+    exchanges: usize,
+    // The history and summary as they would be sent now
+    estimated_tokens: u64,
+    // Over answers that recorded their generation time
+    average_response_ms: Option<u64>,
+    searches: usize,
+}
+
+#[tauri::command]
+async fn conversation_stats(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ConversationStats, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let conversation = handle.lock().await;
+    let count = |role: &str| conversation.messages.iter().filter(|m| m.role == role).count();
+    let durations: Vec<u64> = conversation.messages.iter().filter_map(|m| m.duration_ms).collect();
+    let summary_tokens = conversation.summary.as_deref().map_or(0, context::estimate_tokens);
+    Ok(ConversationStats {
+        conversation_id: conversation.id.clone(),
+        messages: conversation.messages.len(),
+        user_messages: count("user"),
+        assistant_messages: count("assistant"),
+        exchanges: conversation.exchanges,
+        estimated_tokens: conversation.messages.iter().map(context::message_tokens).sum::<u64>() + summary_tokens,
+        average_response_ms: (!durations.is_empty()).then(|| durations.iter().sum::<u64>() / durations.len() as u64),
+        searches: conversation.searches,
+    })
+}
+
+// Takes one message out of the history, and so out of everything sent to the model later;
+// returns it so the UI can offer an undo
+#[tauri::command]
+async fn delete_message(
+    conversation_id: Option<String>,
+    message_index: usize,
+    state: State<'_, AppState>,
+) -> Result<ChatMessage, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let mut conversation = handle.lock().await;
+    if message_index >= conversation.messages.len() {
+        return Err(format!("No message at index {}", message_index));
+    }
+    Ok(conversation.messages.remove(message_index))
+}
+
+// A pinned message is sent with every request however long the conversation gets, e.g.
+// standing instructions or reference data; rolling summaries leave it in place
+#[tauri::command]
+async fn pin_message(
+    conversation_id: Option<String>,
+    message_index: usize,
+    pinned: bool,
+    state: State<'_, AppState>,
+) -> Result<ChatMessage, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let mut conversation = handle.lock().await;
+    let message = conversation
+        .messages
+        .get_mut(message_index)
+        .ok_or_else(|| format!("No message at index {}", message_index))?;
+    message.pinned = pinned;
+    Ok(message.clone())
+}
+
+// The cleared history goes to the trash, where restore_conversation can bring it back
+async fn reset_conversation(state: &AppState, conversation_id: &str) -> Result<(), String> {
+    let handle = conversation(state, Some(conversation_id)).await?;
+    let mut conversation = handle.lock().await;
+    let cleared = ClearedHistory {
+        messages: std::mem::take(&mut conversation.messages),
+        redactions: std::mem::take(&mut conversation.redactions),
+        tags: std::mem::take(&mut conversation.tags),
+        exchanges: std::mem::take(&mut conversation.exchanges),
+        summary: conversation.summary.take(),
+        digest: conversation.digest.take(),
+        cleared_at: Utc::now(),
+    };
+    conversation.search_results.clear();
+    let ephemeral = conversation.ephemeral;
+    drop(conversation);
+    // Cleared incognito history is gone for good
+    if !ephemeral {
+        let settings = state.settings.lock().await.trash.clone();
+        state
+            .trash
+            .lock()
+            .await
+            .put(conversation_id, cleared, &settings)
+            .map_err(|e| e.to_string())?;
+    }
+    // A cleared conversation starts over, so messages scheduled for it no longer apply
+    state.scheduler.lock().await.cancel_conversation(conversation_id);
+    Ok(())
+}
+
+// Undoes the last clear; messages written since then are kept after the restored ones
+#[tauri::command]
+async fn restore_conversation(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let id = handle.lock().await.id.clone();
+    let settings = state.settings.lock().await.trash.clone();
+    let Some(cleared) = state.trash.lock().await.take(&id, &settings).map_err(|e| e.to_string())? else {
+        return Err("Nothing to restore for this conversation".to_string());
+    };
+    let current = state.conversations.lock().await.default_id.clone();
+    let mut conversation = handle.lock().await;
+    let newer = std::mem::replace(&mut conversation.messages, cleared.messages);
+    if newer.is_empty() {
+        conversation.redactions = cleared.redactions;
+    }
+    conversation.messages.extend(newer);
+    let newer_tags = std::mem::replace(&mut conversation.tags, cleared.tags);
+    tagging::merge_tags(&mut conversation.tags, newer_tags);
+    conversation.exchanges += cleared.exchanges;
+    conversation.summary = cleared.summary;
+    conversation.digest = cleared.digest;
+    Ok(conversation.info(conversation.id == current))
+}
+
+#[tauri::command]
+async fn list_trash(state: State<'_, AppState>) -> Result<Vec<TrashEntry>, String> {
+    let settings = state.settings.lock().await.trash.clone();
+    state.trash.lock().await.list(&settings).map_err(|e| e.to_string())
+}
+
+// The conversation used when a command is called without a conversation_id
+#[tauri::command]
+async fn get_conversation_id(state: State<'_, AppState>) -> Result<String, String> {
+    Ok(state.conversations.lock().await.default_id.clone())
+}
+
+// A separate conversation with its own history and stream, e.g. for a second window
+#[tauri::command]
+// New conversations join the active workspace
+async fn open_conversation(state: State<'_, AppState>) -> Result<String, String> {
+    add_conversation(&state, DEFAULT_CONVERSATION_NAME.to_string()).await
+}
+
+async fn add_conversation(state: &AppState, name: String) -> Result<String, String> {
+    let id = state.conversations.lock().await.open_new(name);
+    state
+        .workspaces
+        .lock()
+        .await
+        .add_to_active(|workspace| workspace.conversation_ids.push(id.clone()))
+        .map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+fn conversation_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Conversation name is empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+// An `ephemeral` (incognito) conversation stays out of workspaces, usage data, search
+// history, facts, feedback and the trash
+#[tauri::command]
+async fn create_conversation(
+    name: Option<String>,
+    ephemeral: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    let name = match name {
+        Some(name) => conversation_name(&name)?,
+        None => DEFAULT_CONVERSATION_NAME.to_string(),
+    };
+    if ephemeral == Some(true) {
+        let mut conversation = ConversationState::new(name);
+        conversation.ephemeral = true;
+        let info = conversation.info(false);
+        state
+            .conversations
+            .lock()
+            .await
+            .open
+            .insert(info.id.clone(), Arc::new(Mutex::new(conversation)));
+        return Ok(info);
+    }
+    let id = add_conversation(&state, name).await?;
+    let handle = conversation(&state, Some(&id)).await?;
+    let info = handle.lock().await.info(false);
+    Ok(info)
+}
+
+// Pinned first, then favorites, each group oldest first; `tag` keeps only conversations carrying it
+#[tauri::command]
+async fn list_conversations(
+    tag: Option<String>,
+    include_archived: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ConversationInfo>, String> {
+    let include_archived = include_archived.unwrap_or(false);
+    let tag = tag.as_deref().and_then(tagging::normalize_tag);
+    let (current, handles): (String, Vec<_>) = {
+        let conversations = state.conversations.lock().await;
+        (conversations.default_id.clone(), conversations.open.values().cloned().collect())
+    };
+    let mut list = Vec::new();
+    for handle in handles {
+        let conversation = handle.lock().await;
+        if tag.as_ref().is_some_and(|tag| !conversation.tags.contains(tag)) {
+            continue;
+        }
+        if conversation.archived && !include_archived {
+            continue;
+        }
+        list.push(conversation.info(conversation.id == current));
+    }
+    list.sort_by_key(|c| (!c.pinned, !c.favorite, c.created_at));
+    Ok(list)
+}
+
+#[tauri::command]
+async fn pin_conversation(
+    conversation_id: String,
+    pinned: bool,
+    state: State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    update_conversation(&state, &conversation_id, |conversation| conversation.pinned = pinned).await
+}
+
+#[tauri::command]
+async fn favorite_conversation(
+    conversation_id: String,
+    favorite: bool,
+    state: State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    update_conversation(&state, &conversation_id, |conversation| conversation.favorite = favorite).await
+}
+
+#[tauri::command]
+async fn archive_conversation(
+    conversation_id: String,
+    archived: bool,
+    state: State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    update_conversation(&state, &conversation_id, |conversation| conversation.archived = archived).await
+}
+
+// Archives every unpinned conversation without activity in the last `days` days; returns their ids
+#[tauri::command]
+async fn archive_conversations_older_than(days: u32, state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let cutoff = Utc::now() - chrono::Duration::days(days.into());
+    let (current, handles): (String, Vec<_>) = {
+        let conversations = state.conversations.lock().await;
+        (conversations.default_id.clone(), conversations.open.values().cloned().collect())
+    };
+    let mut archived = Vec::new();
+    for handle in handles {
+        let mut conversation = handle.lock().await;
+        // The current conversation is the one in use, whatever its age
+        if conversation.archived || conversation.pinned || conversation.id == current {
+            continue;
+        }
+        if conversation.last_activity() < cutoff {
+            conversation.archived = true;
+            archived.push(conversation.id.clone());
+        }
+    }
+    Ok(archived)
+}
+
+// Deletes the stored conversations the retention setting no longer keeps, with the ratings
+// of their answers, or with `dry_run` only reports them. Pinned conversations and the
+// current one are kept, as when archiving. Skipped while the storage is locked, since the
+// ratings cannot be read then.
+async fn apply_retention(state: &AppState, dry_run: bool) -> Result<RetentionReport, String> {
+    let settings = state.settings.lock().await.retention.clone();
+    let Some(cutoff) = settings.cutoff(Utc::now()) else {
+        return Ok(RetentionReport::default());
+    };
+    if encryption::status().locked {
+        return Ok(RetentionReport::default());
+    }
+    // Pins and activity since the last flush count too
+    flush_conversations(state, false).await;
+    let rows = state
+        .conversation_store
+        .lock()
+        .await
+        .rows()
+        .map_err(|e| e.to_string())?;
+
+    let current = state.conversations.lock().await.default_id.clone();
+    let mut expired = Vec::new();
+    for row in rows {
+        if row.pinned || row.id == current || row.last_activity >= cutoff {
+            continue;
+        }
+        let name = match conversation(state, Some(&row.id)).await {
+            Ok(handle) => Some(handle.lock().await.name.clone()),
+            Err(_) => None,
+        };
+        expired.push(ExpiredConversation {
+            conversation_id: row.id,
+            name,
+            last_activity: row.last_activity,
+        });
+    }
+
+    let ids: HashSet<&str> = expired.iter().map(|c| c.conversation_id.as_str()).collect();
+    let feedback = if dry_run {
+        state.feedback.lock().await.count_for(&ids)
+    } else {
+        for conversation in &expired {
+            // Not open if it failed to load; its row still goes
+            let _ = state.conversations.lock().await.delete(&conversation.conversation_id);
+            forget_conversation(state, &conversation.conversation_id).await?;
+        }
+        state
+            .feedback
+            .lock()
+            .await
+            .delete_for(&ids)
+            .map_err(|e| e.to_string())?
+    };
+    Ok(RetentionReport {
+        cutoff: Some(cutoff),
+        conversations: expired,
+        feedback,
+    })
+}
+
+// What the retention setting would delete right now, without deleting it
+#[tauri::command]
+async fn preview_retention(state: State<'_, AppState>) -> Result<RetentionReport, String> {
+    apply_retention(&state, true).await
+}
+
+async fn run_retention(app: AppHandle) {
+    loop {
+        let state = app.state::<AppState>();
+        match apply_retention(&state, false).await {
+            Ok(report) if !report.conversations.is_empty() || report.feedback > 0 => {
+                let _ = app.emit("retention-applied", &report);
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Retention pass failed: {}", e),
+        }
+        tokio::time::sleep(retention::CHECK_INTERVAL).await;
+    }
+}
+
+async fn update_conversation(
+    state: &AppState,
+    conversation_id: &str,
+    update: impl FnOnce(&mut ConversationState),
+) -> Result<ConversationInfo, String> {
+    let current = state.conversations.lock().await.default_id.clone();
+    let handle = conversation(state, Some(conversation_id)).await?;
+    let mut conversation = handle.lock().await;
+    update(&mut conversation);
+    Ok(conversation.info(conversation.id == current))
+}
+
+// Best matches first, then the newest
+#[tauri::command]
+async fn search_conversations(
+    query: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<MessageMatch>, String> {
+    flush_conversations(&state, true).await;
+    state
+        .conversation_store
+        .lock()
+        .await
+        .search(&query, limit.unwrap_or(history_search::DEFAULT_LIMIT))
+        .map_err(|e| e.to_string())
+}
+
+// Writes and reindexes the conversation if it changed; incognito ones are only indexed.
+// Nothing is written while the storage is locked.
+async fn flush_conversation(state: &AppState, conversation: &ConversationState) {
+    if encryption::status().locked {
+        return;
+    }
+    let result = state
+        .conversation_store
+        .lock()
+        .await
+        .sync(&conversation.stored(), !conversation.ephemeral);
+    if let Err(e) = result {
+        eprintln!("Failed to save conversation {}: {:?}", conversation.id, e);
+    }
+}
+
+// With `wait` false, conversations busy with a stream are left for the next pass
+async fn flush_conversations(state: &AppState, wait: bool) {
+    let handles: Vec<_> = state.conversations.lock().await.open.values().cloned().collect();
+    for handle in handles {
+        let conversation = if wait {
+            handle.lock().await
+        } else {
+            match handle.try_lock() {
+                Ok(conversation) => conversation,
+                Err(_) => continue,
+            }
+        };
+        flush_conversation(state, &conversation).await;
+    }
+}
+
+async fn run_conversation_flush(app: AppHandle) {
+    loop {
+        tokio::time::sleep(conversation_store::FLUSH_INTERVAL).await;
+        flush_conversations(&app.state::<AppState>(), false).await;
+    }
+}
+
+// Makes the conversation the one used when commands get no conversation_id
+#[tauri::command]
+async fn switch_conversation(conversation_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state.conversations.lock().await.switch(&conversation_id)
+}
+
+#[tauri::command]
+async fn rename_conversation(
+    conversation_id: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    let name = conversation_name(&name)?;
+    update_conversation(&state, &conversation_id, |conversation| conversation.name = name).await
+}
+
+// A new conversation holding a copy of the history up to and including the message, with
+// the same settings; the original thread is left as it is
+#[tauri::command]
+async fn fork_conversation(
+    conversation_id: String,
+    message_index: usize,
+    state: State<'_, AppState>,
+) -> Result<ConversationInfo, String> {
+    let source = conversation(&state, Some(&conversation_id)).await?;
+    let source = source.lock().await;
+    if message_index >= source.messages.len() {
+        return Err(format!("No message at index {}", message_index));
+    }
+    let mut fork = copy_settings(&source, format!("{} (fork)", source.name));
+    fork.forked_from = Some(conversation_id.clone());
+    fork.messages = source.messages[..=message_index].to_vec();
+    fork.redactions = source.redactions.clone();
+    // The summary stands in for messages before the shared prefix
+    fork.summary = source.summary.clone();
+    drop(source);
+    add_copy(&state, &conversation_id, fork).await
+}
+
+// A full copy to experiment on; only the id, the name and the creation time differ
+#[tauri::command]
+async fn duplicate_conversation(conversation_id: String, state: State<'_, AppState>) -> Result<ConversationInfo, String> {
+    let source = conversation(&state, Some(&conversation_id)).await?;
+    let source = source.lock().await;
+    let mut copy = copy_settings(&source, format!("{} (copy)", source.name));
+    copy.forked_from = source.forked_from.clone();
+    copy.messages = source.messages.clone();
+    copy.redactions = source.redactions.clone();
+    copy.summary = source.summary.clone();
+    copy.digest = source.digest.clone();
+    copy.exchanges = source.exchanges;
+    copy.searches = source.searches;
+    drop(source);
+    add_copy(&state, &conversation_id, copy).await
+}
+
+// One new conversation holding the messages of both, in the order they were written;
+// `a` is kept and provides the settings. The privacy flags of either carry over.
+#[tauri::command]
+async fn merge_conversations(a: String, b: String, state: State<'_, AppState>) -> Result<ConversationInfo, String> {
+    if a == b {
+        return Err("Cannot merge a conversation with itself".to_string());
+    }
+    let first = conversation(&state, Some(&a)).await?;
+    let second = conversation(&state, Some(&b)).await?;
+    // One lock at a time, so this cannot deadlock with a merge the other way round
+    let (mut merged, first_messages, first_start) = {
+        let first = first.lock().await;
+        let mut merged = copy_settings(&first, first.name.clone());
+        merged.summary = first.summary.clone();
+        merged.exchanges = first.exchanges;
+        merged.searches = first.searches;
+        (merged, first.messages.clone(), first.created_at)
+    };
+    let second = second.lock().await;
+    merged.name = format!("{} + {}", merged.name, second.name);
+    merged.no_web |= second.no_web;
+    merged.ephemeral |= second.ephemeral;
+    tagging::merge_tags(&mut merged.tags, second.tags.clone());
+    merged.summary = match (merged.summary.take(), second.summary.clone()) {
+        (Some(a), Some(b)) => Some(format!("{}\n\n{}", a, b)),
+        (a, b) => a.or(b),
+    };
+    merged.exchanges += second.exchanges;
+    merged.searches += second.searches;
+    merged.messages = interleave(first_messages, first_start, second.messages.clone(), second.created_at);
+    drop(second);
+    add_copy(&state, &a, merged).await
+}
+
+// Merges two histories by time without reordering either. Messages without a
+// timestamp count as written with the one before them.
+fn interleave(
+    first: Vec<ChatMessage>,
+    first_start: DateTime<Utc>,
+    second: Vec<ChatMessage>,
+    second_start: DateTime<Utc>,
+) -> Vec<ChatMessage> {
+    let times = |messages: &[ChatMessage], start: DateTime<Utc>| -> Vec<DateTime<Utc>> {
+        let mut last = start;
+        messages
+            .iter()
+            .map(|m| {
+                last = m.created_at.unwrap_or(last);
+                last
+            })
+            .collect()
+    };
+    let first_times = times(&first, first_start);
+    let second_times = times(&second, second_start);
+    let mut first = first.into_iter().zip(first_times).peekable();
+    let mut second = second.into_iter().zip(second_times).peekable();
+    let mut merged = Vec::new();
+    loop {
+        let next = match (first.peek(), second.peek()) {
+            (Some((_, a)), Some((_, b))) if b < a => second.next(),
+            (Some(_), _) => first.next(),
+            (None, _) => second.next(),
+        };
+        match next {
+            Some((message, _)) => merged.push(message),
+            None => return merged,
+        }
+    }
+}
+
+// A new conversation with the source's settings and tags but no history
+fn copy_settings(source: &ConversationState, name: String) -> ConversationState {
+    let mut copy = ConversationState::new(name);
+    copy.no_web = source.no_web;
+    copy.ephemeral = source.ephemeral;
+    copy.reply_language = source.reply_language.clone();
+    copy.system_prompt = source.system_prompt.clone();
+    copy.model = source.model.clone();
+    copy.options = source.options.clone();
+    copy.tags = source.tags.clone();
+    copy
+}
+
+// Copies go into the source's workspace, unless they are incognito
+async fn add_copy(state: &AppState, source_id: &str, copy: ConversationState) -> Result<ConversationInfo, String> {
+    let ephemeral = copy.ephemeral;
+    let info = copy.info(false);
+    state
+        .conversations
+        .lock()
+        .await
+        .open
+        .insert(info.id.clone(), Arc::new(Mutex::new(copy)));
+    if ephemeral {
+        return Ok(info);
+    }
+    let mut workspaces = state.workspaces.lock().await;
+    if let Some(workspace) = workspaces.for_conversation(source_id).map(|w| w.id.clone()) {
+        workspaces
+            .add_conversation(&workspace, &info.id)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(info)
+}
+
+// Returns the current conversation, which is a new one if the deleted one was current
+#[tauri::command]
+async fn delete_conversation(conversation_id: String, state: State<'_, AppState>) -> Result<String, String> {
+    let current = {
+        let mut conversations = state.conversations.lock().await;
+        conversations.delete(&conversation_id)?;
+        conversations.default_id.clone()
+    };
+    forget_conversation(&state, &conversation_id).await?;
+    Ok(current)
+}
+
+#[tauri::command]
+async fn close_conversation(conversation_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state.conversations.lock().await.close(&conversation_id)?;
+    forget_conversation(&state, &conversation_id).await
+}
+
+// Stops everything still running for a conversation that has been removed
+async fn forget_conversation(state: &AppState, conversation_id: &str) -> Result<(), String> {
+    state.streams.lock().await.retain(|_, stream| {
+        let closing = stream.conversation_id == conversation_id;
+        if closing {
+            stream.cancel.cancel();
+        }
+        !closing
+    });
+    state.scheduler.lock().await.cancel_conversation(conversation_id);
+    state.trash.lock().await.forget(conversation_id).map_err(|e| e.to_string())?;
+    state
+        .conversation_store
+        .lock()
+        .await
+        .delete(conversation_id)
+        .map_err(|e| e.to_string())?;
+    state
+        .workspaces
+        .lock()
+        .await
+        .forget(conversation_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_workspaces(
+    include_archived: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Workspace>, String> {
+    Ok(state.workspaces.lock().await.list(include_archived.unwrap_or(false)))
+}
+
+#[tauri::command]
+async fn get_active_workspace(state: State<'_, AppState>) -> Result<Option<Workspace>, String> {
+    Ok(state.workspaces.lock().await.active().cloned())
+}
+
+#[tauri::command]
+async fn create_workspace(
+    name: String,
+    config: WorkspaceConfig,
+    state: State<'_, AppState>,
+) -> Result<Workspace, String> {
+    state
+        .workspaces
+        .lock()
+        .await
+        .create(&name, config)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_workspace(
+    id: String,
+    config: WorkspaceConfig,
+    state: State<'_, AppState>,
+) -> Result<Workspace, String> {
+    state
+        .workspaces
+        .lock()
+        .await
+        .update(&id, config)
+        .map_err(|e| e.to_string())
+}
+
+// None leaves the current workspace
+#[tauri::command]
+async fn switch_workspace(id: Option<String>, state: State<'_, AppState>) -> Result<Option<Workspace>, String> {
+    state
+        .workspaces
+        .lock()
+        .await
+        .switch(id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn archive_workspace(id: String, archived: bool, state: State<'_, AppState>) -> Result<Workspace, String> {
+    state
+        .workspaces
+        .lock()
+        .await
+        .set_archived(&id, archived)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn move_conversation_to_workspace(
+    workspace_id: String,
+    conversation_id: String,
+    state: State<'_, AppState>,
+) -> Result<Workspace, String> {
+    if conversation(&state, Some(&conversation_id)).await?.lock().await.ephemeral {
+        return Err("Incognito conversations can't be added to a workspace".to_string());
+    }
+    state
+        .workspaces
+        .lock()
+        .await
+        .add_conversation(&workspace_id, &conversation_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn schedule_message(
+    conversation_id: String,
+    text: String,
+    at: DateTime<Utc>,
+    state: State<'_, AppState>,
+) -> Result<ScheduledMessage, String> {
+    conversation(&state, Some(&conversation_id)).await?;
+    Ok(state.scheduler.lock().await.add(conversation_id, text, at))
+}
+
+#[tauri::command]
+async fn list_scheduled_messages(state: State<'_, AppState>) -> Result<Vec<ScheduledMessage>, String> {
+    Ok(state.scheduler.lock().await.list())
+}
+
+#[tauri::command]
+async fn cancel_scheduled_message(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    if state.scheduler.lock().await.cancel(&id) {
+        Ok(())
+    } else {
+        Err(format!("No scheduled message: {}", id))
+    }
+}
+
+#[tauri::command]
+async fn create_reminder(text: String, state: State<'_, AppState>) -> Result<Reminder, String> {
+    let (due, what) = reminders::parse_reminder(&text, Local::now())
+        .ok_or_else(|| "Could not understand when to remind you".to_string())?;
+    state
+        .reminders
+        .lock()
+        .await
+        .add(what, due)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_reminders(state: State<'_, AppState>) -> Result<Vec<Reminder>, String> {
+    Ok(state.reminders.lock().await.list())
+}
+
+#[tauri::command]
+async fn delete_reminder(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    match state.reminders.lock().await.delete(&id) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("No reminder: {}", id)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn notify_reminder(app: &AppHandle, reminder: &Reminder) {
+    let shown = app
+        .notification()
+        .builder()
+        .title("Reminder")
+        .body(&reminder.text)
+        .show();
+    if let Err(e) = shown {
+        eprintln!("Failed to show reminder notification: {:?}", e);
+    }
+    let _ = app.emit("reminder-due", reminder);
+}
+
+// Background loop that fires scheduled messages and reminders once they are due
+async fn run_scheduler(app: AppHandle) {
+    loop {
+        tokio::time::sleep(scheduler::TICK_INTERVAL).await;
+
+        let state = app.state::<AppState>();
+
+        match state.reminders.lock().await.take_due(Utc::now()) {
+            Ok(reminders) => reminders.iter().for_each(|r| notify_reminder(&app, r)),
+            Err(e) => eprintln!("Failed to update reminders: {:?}", e),
+        }
+
+        let due = state.scheduler.lock().await.take_due(Utc::now());
+        for message in due {
+            if conversation(&state, Some(&message.conversation_id)).await.is_err() {
+                eprintln!("Dropping scheduled message for closed conversation {}", message.id);
+                continue;
+            }
+
+            let _ = app.emit("scheduled-message-sent", &message);
+            let sink = EventSink::broadcast(&app);
+            if let Err(e) = run_chat(&sink, &state, &message.conversation_id, message.text.clone(), None).await {
+                eprintln!("Scheduled message {} failed: {}", message.id, e);
+            }
+        }
+    }
+}
+
+#[tauri::command]
+async fn set_no_web(
+    enabled: bool,
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    conversation(&state, conversation_id.as_deref()).await?.lock().await.no_web = enabled;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_no_web(conversation_id: Option<String>, state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(conversation(&state, conversation_id.as_deref()).await?.lock().await.no_web)
+}
+
+#[tauri::command]
+async fn set_generation_options(
+    options: GenerationOptions,
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    options.validate().map_err(|e| e.to_string())?;
+    conversation(&state, conversation_id.as_deref()).await?.lock().await.options = options;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_generation_options(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<GenerationOptions, String> {
+    Ok(conversation(&state, conversation_id.as_deref())
+        .await?
+        .lock()
+        .await
+        .options
+        .clone())
+}
+
+// None goes back to replying in whatever language each message is written in
+#[tauri::command]
+async fn set_reply_language(
+    language: Option<String>,
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let language = language.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    conversation(&state, conversation_id.as_deref()).await?.lock().await.reply_language = language;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_reply_language(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    Ok(conversation(&state, conversation_id.as_deref())
+        .await?
+        .lock()
+        .await
+        .reply_language
+        .clone())
+}
+
+#[derive(Serialize, Clone)]
+struct ConversationSystemPrompt {
+    prompt: String,
+    // False while the default prompt is in use
+    custom: bool,
+}
+
+#[tauri::command]
+async fn get_system_prompt(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ConversationSystemPrompt, String> {
+    let handle = conversation(&state, conversation_id.as_deref()).await?;
+    let custom = handle.lock().await.system_prompt.clone();
+    Ok(ConversationSystemPrompt {
+        custom: custom.is_some(),
+        prompt: custom.unwrap_or_else(|| SYSTEM_PROMPT.to_string()),
+    })
+}
+
+#[tauri::command]
+async fn set_system_prompt(
+    prompt: String,
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        return Err("System prompt is empty; reset it to use the default".to_string());
+    }
+    conversation(&state, conversation_id.as_deref()).await?.lock().await.system_prompt = Some(prompt.to_string());
+    Ok(())
+}
+
+#[tauri::command]
+async fn reset_system_prompt(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    conversation(&state, conversation_id.as_deref()).await?.lock().await.system_prompt = None;
+    Ok(())
+}
+
+// Encrypted stores load empty while locked, so they are read again on unlock and
+// dropped from memory on lock
+async fn reload_stores(state: &AppState) {
+    let data_dir = &state.data_dir;
+    *state.analytics.lock().await = Analytics::load(data_dir);
+    *state.trash.lock().await = Trash::load(data_dir);
+    *state.reminders.lock().await = ReminderStore::load(data_dir);
+    *state.snippets.lock().await = SnippetStore::load(data_dir);
+    *state.templates.lock().await = TemplateStore::load(data_dir);
+    *state.knowledge.lock().await = KnowledgeBase::load(data_dir);
+    *state.provider_keys.lock().await = ProviderKeys::load(data_dir);
+    *state.bookmarks.lock().await = BookmarkStore::load(data_dir);
+    *state.tags.lock().await = TagStore::load(data_dir);
+    *state.search_history.lock().await = SearchHistory::load(data_dir);
+    *state.jobs.lock().await = JobManager::load(data_dir);
+    *state.facts.lock().await = FactStore::load(data_dir);
+    *state.feedback.lock().await = FeedbackStore::load(data_dir);
+    *state.workspaces.lock().await = WorkspaceStore::load(data_dir);
+    state.search.lock().await.bangs = Bangs::load(data_dir);
+}
+
+#[tauri::command]
+async fn storage_status() -> Result<StorageStatus, String> {
+    Ok(encryption::status())
+}
+
+// Without a passphrase the key is generated and kept in the OS keychain
+#[tauri::command]
+async fn enable_storage_encryption(
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<StorageStatus, String> {
+    encryption::enable(&state.data_dir, passphrase.as_deref()).map_err(|e| e.to_string())?;
+    state
+        .conversation_store
+        .lock()
+        .await
+        .encrypt_all()
+        .map_err(|e| e.to_string())?;
+    Ok(encryption::status())
+}
+
+#[tauri::command]
+async fn unlock_storage(passphrase: Option<String>, state: State<'_, AppState>) -> Result<StorageStatus, String> {
+    encryption::unlock(&state.data_dir, passphrase.as_deref()).map_err(|e| e.to_string())?;
+    reload_stores(&state).await;
+    let stored = {
+        let mut store = state.conversation_store.lock().await;
+        store.encrypt_all().map_err(|e| e.to_string())?;
+        store.load_all().map_err(|e| e.to_string())?
+    };
+    state.conversations.lock().await.restore(stored);
+    Ok(encryption::status())
+}
+
+// Conversations are saved and then closed, streams included, so nothing decrypted stays in memory
+#[tauri::command]
+async fn lock_storage(state: State<'_, AppState>) -> Result<StorageStatus, String> {
+    if !encryption::status().enabled {
+        return Err("Storage encryption is not enabled".to_string());
+    }
+    for (_, stream) in state.streams.lock().await.drain() {
+        stream.cancel.cancel();
+    }
+    flush_conversations(&state, true).await;
+    encryption::lock().map_err(|e| e.to_string())?;
+    *state.conversations.lock().await = Conversations::new();
+    state
+        .conversation_store
+        .lock()
+        .await
+        .clear_index()
+        .map_err(|e| e.to_string())?;
+    reload_stores(&state).await;
+    Ok(encryption::status())
+}
+
+#[tauri::command]
+async fn set_secret(name: String, value: String) -> Result<(), String> {
+    secrets::check_name(&name).map_err(|e| e.to_string())?;
+    secrets::set_secret(&name, &value).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn has_secret(name: String) -> Result<bool, String> {
+    secrets::check_name(&name).map_err(|e| e.to_string())?;
+    secrets::has_secret(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_masked_secret(name: String) -> Result<Option<String>, String> {
+    secrets::check_name(&name).map_err(|e| e.to_string())?;
+    let secret = secrets::get_secret(&name).map_err(|e| e.to_string())?;
+    Ok(secret.map(|s| secrets::mask(&s)))
+}
+
+#[tauri::command]
+async fn delete_secret(name: String) -> Result<(), String> {
+    secrets::check_name(&name).map_err(|e| e.to_string())?;
+    secrets::delete_secret(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_provider_keys(state: State<'_, AppState>) -> Result<Vec<ProviderKeyStatus>, String> {
+    state.provider_keys.lock().await.list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_provider_key(
+    provider: Provider,
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<ProviderKeyStatus, String> {
+    let settings = state.settings.lock().await.clone();
+    let status = state
+        .provider_keys
+        .lock()
+        .await
+        .set(&settings.tls, &settings.backend.openai.base_url, provider, &key)
+        .await
+        .map_err(|e| e.to_string())?;
+    if provider == Provider::OpenAi {
+        reload_backend(&state).await?;
+    }
+    Ok(status)
+}
+
+#[tauri::command]
+async fn validate_provider_key(
+    provider: Provider,
+    state: State<'_, AppState>,
+) -> Result<ValidationResult, String> {
+    let settings = state.settings.lock().await.clone();
+    state
+        .provider_keys
+        .lock()
+        .await
+        .validate(&settings.tls, &settings.backend.openai.base_url, provider)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn remove_provider_key(provider: Provider, state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .provider_keys
+        .lock()
+        .await
+        .remove(provider)
+        .map_err(|e| e.to_string())?;
+    if provider == Provider::OpenAi {
+        reload_backend(&state).await?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_redactions(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RedactionMap, String> {
+    Ok(conversation(&state, conversation_id.as_deref())
+        .await?
+        .lock()
+        .await
+        .redactions
+        .clone())
+}
+
+#[tauri::command]
+async fn respond_fetch_consent(
+    request_id: String,
+    approved_urls: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if state.consent.lock().await.respond(&request_id, approved_urls) {
+        Ok(())
+    } else {
+        Err(format!("No pending consent request: {}", request_id))
+    }
+}
+
+#[tauri::command]
+async fn get_settings(state: State<'_, AppState>) -> Result<Settings, String> {
+    Ok(state.settings.lock().await.clone())
+}
+
+#[tauri::command]
+async fn check_ollama_status(app: AppHandle, state: State<'_, AppState>) -> Result<OllamaStatus, String> {
+    let client = state.ollama.lock().await.clone();
+    let status = client.status().await;
+    let _ = app.emit("ollama-status", &status);
+    Ok(status)
+}
+
+async fn run_ollama_status_checks(app: AppHandle) {
+    loop {
+        let client = app.state::<AppState>().ollama.lock().await.clone();
+        let _ = app.emit("ollama-status", &client.status().await);
+        tokio::time::sleep(OLLAMA_STATUS_INTERVAL).await;
+    }
+}
+
+// Starts a local Ollama that is not running, if allowed, and waits until it answers.
+// True when the caller can try again.
+async fn start_ollama(sink: &EventSink, state: &AppState) -> bool {
+    let client = state.ollama.lock().await.clone();
+    let (autostart, backend) = {
+        let settings = state.settings.lock().await;
+        (settings.autostart.clone(), settings.backend.kind)
+    };
+    if !autostart.enabled || backend != BackendKind::Ollama || !client.is_local() {
+        return false;
+    }
+    let Some(host) = launcher::serve_host(client.base_url()) else {
+        return false;
+    };
+
+    if let Err(e) = state.launcher.lock().await.start(&autostart, &host) {
+        let _ = sink.emit("ollama-start-failed", e.to_string());
+        return false;
+    }
+    let _ = sink.emit("ollama-starting", &host);
+
+    let deadline = Instant::now() + launcher::READY_TIMEOUT;
+    while Instant::now() < deadline {
+        if client.version().await.is_ok() {
+            let _ = sink.app.emit("ollama-status", &client.status().await);
+            return true;
+        }
+        if let Err(e) = state.launcher.lock().await.check_exited() {
+            let _ = sink.emit("ollama-start-failed", e.to_string());
+            return false;
+        }
+        tokio::time::sleep(launcher::READY_POLL_INTERVAL).await;
+    }
+    let _ = sink.emit("ollama-start-failed", "Ollama did not become ready in time");
+    false
+}
+
+fn ollama_client(settings: &Settings) -> anyhow::Result<OllamaClient> {
+    Ok(OllamaClient::with_tls(&settings.ollama_url, &settings.tls, &settings.http)?.with_retry(settings.retry.clone()))
+}
+
+fn llm_backend(settings: &Settings) -> anyhow::Result<Box<dyn LlmBackend>> {
+    match settings.backend.kind {
+        BackendKind::Ollama => Ok(Box::new(ollama_client(settings)?)),
+        BackendKind::OpenAi => {
+            let api_key = Provider::OpenAi.api_key().unwrap_or_else(|e| {
+                eprintln!("Could not read the OpenAI key: {:?}", e);
+                None
+            });
+            let client = OpenAiClient::new(
+                &settings.backend.openai,
+                &settings.tls,
+                &settings.http,
+                settings.retry.clone(),
+                api_key,
+            )?;
+            Ok(Box::new(client))
+        }
+        kind @ (BackendKind::LlamaCpp | BackendKind::LmStudio) => {
+            let local_url = settings.backend.local_url.trim();
+            let server = OpenAiSettings {
+                base_url: match local_url {
+                    "" => kind.default_local_url().unwrap_or_default().to_string(),
+                    url => url.to_string(),
+                },
+                default_model: String::new(),
+                embed_model: DEFAULT_EMBED_MODEL.to_string(),
+            };
+            Ok(Box::new(OpenAiClient::new(&server, &settings.tls, &settings.http, settings.retry.clone(), None)?))
+        }
+    }
+}
+
+// llama.cpp and LM Studio servers answering on their usual ports
+#[tauri::command]
+async fn detect_local_servers() -> Result<Vec<LocalServer>, String> {
+    local_servers::detect().await.map_err(|e| e.to_string())
+}
+
+// Picks up a changed OpenAI key without restarting
+async fn reload_backend(state: &AppState) -> Result<(), String> {
+    let settings = state.settings.lock().await.clone();
+    *state.backend.lock().await = llm_backend(&settings).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_ollama_url(state: State<'_, AppState>) -> Result<String, String> {
+    Ok(state.ollama.lock().await.base_url().to_string())
+}
+
+// Only switches once the server answers, and returns the version it runs
+#[tauri::command]
+async fn set_ollama_url(url: String, state: State<'_, AppState>) -> Result<String, String> {
+    let mut settings = state.settings.lock().await.clone();
+    settings.ollama_url = ollama::normalize_base_url(&url).map_err(|e| e.to_string())?;
+    let ollama = ollama_client(&settings).map_err(|e| e.to_string())?;
+    let version = ollama.version().await.map_err(|e| e.to_string())?;
+    let backend = llm_backend(&settings).map_err(|e| e.to_string())?;
+
+    settings.save(&state.data_dir).map_err(|e| e.to_string())?;
+    *state.ollama.lock().await = ollama;
+    *state.backend.lock().await = backend;
+    *state.settings.lock().await = settings;
+    Ok(version)
+}
+
+#[tauri::command]
+async fn update_settings(
+    app: AppHandle,
+    settings: Settings,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    // Reject invalid custom patterns now rather than on the next chat request
+    Redactor::new(&settings.redaction).map_err(|e| e.to_string())?;
+    settings.developer.validate().map_err(|e| e.to_string())?;
+    settings.retention.validate().map_err(|e| e.to_string())?;
+    settings.trash.validate().map_err(|e| e.to_string())?;
+    let ollama = ollama_client(&settings).map_err(|e| e.to_string())?;
+    let backend = llm_backend(&settings).map_err(|e| e.to_string())?;
+    settings.save(&state.data_dir).map_err(|e| e.to_string())?;
+    *state.ollama.lock().await = ollama;
+    // Model names differ between servers, so switching backends selects the new one's default
+    let previous = state.settings.lock().await.backend.kind;
+    if previous != settings.backend.kind {
+        *state.model.lock().await = backend.default_model();
+    }
+    *state.backend.lock().await = backend;
+    let mut search = state.search.lock().await;
+    search.client = SearchClient::new(&settings, &search.selectors);
+    drop(search);
+    *state.settings.lock().await = settings;
+    apply_power_state(&app, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_extraction_selectors(state: State<'_, AppState>) -> Result<ExtractionSelectors, String> {
+    Ok(state.search.lock().await.selectors.clone())
+}
+
+#[tauri::command]
+async fn add_extraction_selector(
+    kind: SelectorKind,
+    selector: String,
+    state: State<'_, AppState>,
+) -> Result<ExtractionSelectors, String> {
+    update_selectors(&state, |selectors| selectors.add(kind, &selector)).await
+}
+
+#[tauri::command]
+async fn remove_extraction_selector(
+    kind: SelectorKind,
+    selector: String,
+    state: State<'_, AppState>,
+) -> Result<ExtractionSelectors, String> {
+    update_selectors(&state, |selectors| {
+        if selectors.remove(kind, &selector) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("No such selector: {}", selector))
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+async fn reset_extraction_selectors(state: State<'_, AppState>) -> Result<ExtractionSelectors, String> {
+    update_selectors(&state, |selectors| {
+        *selectors = ExtractionSelectors::default();
+        Ok(())
+    })
+    .await
+}
+
+async fn update_selectors(
+    state: &AppState,
+    change: impl FnOnce(&mut ExtractionSelectors) -> anyhow::Result<()>,
+) -> Result<ExtractionSelectors, String> {
+    let mut search = state.search.lock().await;
+    let mut selectors = search.selectors.clone();
+    change(&mut selectors).map_err(|e| e.to_string())?;
+    selectors.save(&state.data_dir).map_err(|e| e.to_string())?;
+
+    search.client.set_selectors(&selectors);
+    search.selectors = selectors.clone();
+    Ok(selectors)
+}
+
+#[tauri::command]
+async fn get_bangs(state: State<'_, AppState>) -> Result<Bangs, String> {
+    Ok(state.search.lock().await.bangs.clone())
+}
+
+#[tauri::command]
+async fn set_bang(name: String, target: BangTarget, state: State<'_, AppState>) -> Result<Bangs, String> {
+    update_bangs(&state, |bangs| bangs.set(&name, target)).await
+}
+
+#[tauri::command]
+async fn remove_bang(name: String, state: State<'_, AppState>) -> Result<Bangs, String> {
+    update_bangs(&state, |bangs| {
+        if !bangs.remove(&name)? {
+            anyhow::bail!("No bang: {}", name);
+        }
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn reset_bangs(state: State<'_, AppState>) -> Result<Bangs, String> {
+    update_bangs(&state, |bangs| {
+        *bangs = Bangs::default();
+        Ok(())
+    })
+    .await
+}
+
+async fn update_bangs(
+    state: &AppState,
+    change: impl FnOnce(&mut Bangs) -> anyhow::Result<()>,
+) -> Result<Bangs, String> {
+    let mut search = state.search.lock().await;
+    let mut bangs = search.bangs.clone();
+    change(&mut bangs).map_err(|e| e.to_string())?;
+    bangs.save(&state.data_dir).map_err(|e| e.to_string())?;
+    search.bangs = bangs.clone();
+    Ok(bangs)
+}
+
+#[tauri::command]
+async fn get_usage_report(state: State<'_, AppState>) -> Result<UsageReport, String> {
+    let enabled = state.settings.lock().await.analytics_enabled;
+    Ok(state.analytics.lock().await.report(enabled))
+}
+
+#[tauri::command]
+async fn get_dashboard_data(period: String, state: State<'_, AppState>) -> Result<DashboardData, String> {
+    let enabled = state.settings.lock().await.analytics_enabled;
+    state
+        .analytics
+        .lock()
+        .await
+        .dashboard(&period, enabled)
+        .map_err(|e| e.to_string())
+}
+
+// Called by the frontend when a result is opened
+#[tauri::command]
+async fn record_result_opened(url: String, state: State<'_, AppState>) -> Result<(), String> {
+    track_search(&state, |history| history.mark_used(&url)).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_search_analytics(period: String, state: State<'_, AppState>) -> Result<SearchAnalytics, String> {
+    let enabled = state.settings.lock().await.analytics_enabled;
+    let (mut report, queries) = state
+        .search_history
+        .lock()
+        .await
+        .report(&period, enabled)
+        .map_err(|e| e.to_string())?;
+    if queries.is_empty() {
+        return Ok(report);
+    }
+
+    let backend = state.backend.lock().await.clone();
+    let texts = queries.iter().map(|q| q.name.clone()).collect();
+    let model = backend.embed_model();
+    match backend.embed(texts, model).await {
+        Ok(embeddings) => report.topics = search_history::cluster_topics(&queries, &embeddings),
+        Err(e) => report.topics_error = Some(format!("Topic detection unavailable: {}", e)),
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+async fn clear_search_history(state: State<'_, AppState>) -> Result<(), String> {
+    state.search_history.lock().await.clear().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn clear_usage_data(state: State<'_, AppState>) -> Result<(), String> {
+    state.analytics.lock().await.clear().map_err(|e| e.to_string())
+}
+
+fn main() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            if let Err(e) = encryption::init(&data_dir) {
+                eprintln!("Storage stays locked: {:?}", e);
+            }
+
+            let settings = Settings::load(&data_dir);
+            let selectors = ExtractionSelectors::load(&data_dir);
+            let ollama = ollama_client(&settings).unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid Ollama connection settings: {:?}", e);
+                OllamaClient::new()
+            });
+            let backend = llm_backend(&settings).unwrap_or_else(|_| Box::new(ollama.clone()));
+            let model = backend.default_model();
+
+            let mut conversation_store = ConversationStore::open(&data_dir)?;
+            let mut conversations = Conversations::new();
+            match conversation_store.load_all() {
+                Ok(stored) => conversations.restore(stored),
+                // Loaded by unlock_storage instead
+                Err(e) if e.is::<encryption::Locked>() => {}
+                Err(e) => eprintln!("Failed to load conversations: {:?}", e),
+            }
+
+            let app_state = AppState {
+                ollama: Mutex::new(ollama),
+                backend: Mutex::new(backend),
+                model: Mutex::new(model),
+                conversations: Mutex::new(conversations),
+                conversation_store: Mutex::new(conversation_store),
+                trash: Mutex::new(Trash::load(&data_dir)),
+                streams: Mutex::new(HashMap::new()),
+                search: Mutex::new(SearchState {
+                    client: SearchClient::new(&settings, &selectors),
+                    selectors,
+                    bangs: Bangs::load(&data_dir),
+                }),
+                settings: Mutex::new(settings),
+                analytics: Mutex::new(Analytics::load(&data_dir)),
+                consent: Mutex::new(ConsentBroker::default()),
+                scheduler: Mutex::new(Scheduler::default()),
+                reminders: Mutex::new(ReminderStore::load(&data_dir)),
+                snippets: Mutex::new(SnippetStore::load(&data_dir)),
+                templates: Mutex::new(TemplateStore::load(&data_dir)),
+                knowledge: Mutex::new(KnowledgeBase::load(&data_dir)),
+                provider_keys: Mutex::new(ProviderKeys::load(&data_dir)),
+                bookmarks: Mutex::new(BookmarkStore::load(&data_dir)),
+                tags: Mutex::new(TagStore::load(&data_dir)),
+                search_history: Mutex::new(SearchHistory::load(&data_dir)),
+                jobs: Mutex::new(JobManager::load(&data_dir)),
+                announced_updates: Mutex::new(HashMap::new()),
+                facts: Mutex::new(FactStore::load(&data_dir)),
+                feedback: Mutex::new(FeedbackStore::load(&data_dir)),
+                power: Mutex::new(PowerState::default()),
+                launcher: Mutex::new(OllamaLauncher::default()),
+                workspaces: Mutex::new(WorkspaceStore::load(&data_dir)),
+                data_dir,
+            };
+
+            app.manage(app_state);
+            tauri::async_runtime::spawn(run_scheduler(app.handle().clone()));
+            tauri::async_runtime::spawn(run_model_update_checks(app.handle().clone()));
+            tauri::async_runtime::spawn(run_power_monitor(app.handle().clone()));
+            tauri::async_runtime::spawn(run_ollama_status_checks(app.handle().clone()));
+            tauri::async_runtime::spawn(run_startup_preload(app.handle().clone()));
+            tauri::async_runtime::spawn(run_retention(app.handle().clone()));
+            tauri::async_runtime::spawn(run_conversation_flush(app.handle().clone()));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             chat_stream,
+            regenerate_response,
+            replay_conversation,
+            edit_message,
+            cancel_chat_stream,
+            stop_generation,
+            cancel_stream,
+            list_streams,
+            attach_image,
+            complete_text,
+            complete_code,
+            complete_raw,
+            preload_model,
             clear_conversation,
-            perform_search
+            get_messages,
+            conversation_stats,
+            rate_message,
+            list_feedback,
+            delete_message,
+            pin_message,
+            restore_conversation,
+            list_trash,
+            open_conversation,
+            close_conversation,
+            create_conversation,
+            list_conversations,
+            pin_conversation,
+            favorite_conversation,
+            archive_conversation,
+            archive_conversations_older_than,
+            preview_retention,
+            search_conversations,
+            switch_conversation,
+            rename_conversation,
+            delete_conversation,
+            fork_conversation,
+            duplicate_conversation,
+            merge_conversations,
+            summarize_conversation,
+            get_conversation_digest,
+            list_workspaces,
+            get_active_workspace,
+            create_workspace,
+            update_workspace,
+            switch_workspace,
+            archive_workspace,
+            move_conversation_to_workspace,
+            get_conversation_tags,
+            set_conversation_tags,
+            create_tag,
+            delete_tag,
+            list_tags,
+            assign_tag,
+            unassign_tag,
+            perform_search,
+            get_settings,
+            update_settings,
+            detect_local_servers,
+            get_ollama_url,
+            set_ollama_url,
+            check_ollama_status,
+            get_usage_report,
+            get_extraction_selectors,
+            add_extraction_selector,
+            remove_extraction_selector,
+            reset_extraction_selectors,
+            get_bangs,
+            set_bang,
+            remove_bang,
+            reset_bangs,
+            clear_usage_data,
+            get_dashboard_data,
+            record_result_opened,
+            get_search_analytics,
+            clear_search_history,
+            get_redactions,
+            set_no_web,
+            get_no_web,
+            set_generation_options,
+            get_generation_options,
+            set_reply_language,
+            get_reply_language,
+            get_system_prompt,
+            set_system_prompt,
+            reset_system_prompt,
+            storage_status,
+            enable_storage_encryption,
+            unlock_storage,
+            lock_storage,
+            set_secret,
+            has_secret,
+            get_masked_secret,
+            delete_secret,
+            list_provider_keys,
+            set_provider_key,
+            validate_provider_key,
+            remove_provider_key,
+            respond_fetch_consent,
+            get_conversation_id,
+            schedule_message,
+            list_scheduled_messages,
+            cancel_scheduled_message,
+            create_reminder,
+            list_reminders,
+            delete_reminder,
+            list_slash_commands,
+            list_snippets,
+            save_snippet,
+            delete_snippet,
+            save_conversation_template,
+            list_conversation_templates,
+            delete_conversation_template,
+            start_from_template,
+            get_context_preview,
+            diff_messages,
+            export_conversation,
+            export_conversation_pdf,
+            export_conversation_json,
+            import_conversation,
+            import_chatgpt,
+            save_page_snapshot,
+            list_snapshots,
+            get_snapshot,
+            delete_snapshot,
+            start_snapshot_job,
+            list_jobs,
+            get_power_status,
+            list_models,
+            running_models,
+            show_model,
+            get_model_disk_usage,
+            create_model,
+            delete_model,
+            get_model,
+            set_model,
+            check_model_updates,
+            pull_model,
+            update_model,
+            pause_job,
+            resume_job,
+            cancel_job,
+            clear_finished_jobs,
+            add_fact,
+            list_facts,
+            delete_fact,
+            export_facts_markdown,
+            bookmark_result,
+            list_bookmarks,
+            list_bookmark_tags,
+            set_bookmark_tags,
+            delete_bookmark
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Whatever changed since the last periodic flush
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(flush_conversations(&app.state::<AppState>(), false));
+            }
+        });
 }
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::ollama::OllamaClient;
+use crate::tls::TlsSettings;
+
+// How often installed models are compared with the registry, and how long after
+// startup the first comparison runs
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+pub const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+
+const REGISTRY_URL: &str = "https://registry.ollama.ai";
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelUpdate {
+    pub model: String,
+    pub local_digest: String,
+    pub remote_digest: String,
+}
+
+// "llama3", "llama3:8b" or "user/model:tag" -> the registry manifest URL.
+// Models pulled from other registries ("host.example/ns/model") are not checked.
+fn manifest_url(name: &str) -> Option<String> {
+    let (path, tag) = match name.rsplit_once(':') {
+        Some((path, tag)) if !tag.contains('/') => (path, tag),
+        _ => (name, "latest"),
+    };
+    let (namespace, model) = match path.split_once('/') {
+        Some((first, _)) if first.contains('.') => return None,
+        Some((namespace, model)) if !model.contains('/') => (namespace, model),
+        Some(_) => return None,
+        None => ("library", path),
+    };
+    Some(format!("{}/v2/{}/{}/manifests/{}", REGISTRY_URL, namespace, model, tag))
+}
+
+// Ollama identifies an installed model by the sha256 of its manifest
+async fn registry_digest(client: &Client, url: &str) -> Result<String> {
+    let manifest = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, MANIFEST_MEDIA_TYPE)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(format!("{:x}", Sha256::digest(&manifest)))
+}
+
+pub async fn check(ollama: &OllamaClient, tls: &TlsSettings) -> Result<Vec<ModelUpdate>> {
+    let client = tls
+        .apply(Client::builder().timeout(Duration::from_secs(30)), REGISTRY_URL)?
+        .build()?;
+
+    let mut updates = Vec::new();
+    for model in ollama.list_models().await? {
+        let Some(url) = manifest_url(&model.name) else {
+            continue;
+        };
+        let remote_digest = match registry_digest(&client, &url).await {
+            Ok(digest) => digest,
+            Err(e) => {
+                eprintln!("Could not check {} for updates: {:?}", model.name, e);
+                continue;
+            }
+        };
+        let local_digest = model.digest.trim_start_matches("sha256:").to_string();
+        if local_digest != remote_digest {
+            updates.push(ModelUpdate {
+                model: model.name,
+                local_digest,
+                remote_digest,
+            });
+        }
+    }
+    Ok(updates)
+}
@@ -1,8 +1,19 @@
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use tokio::sync::mpsc;
 use tauri::async_runtime::Receiver;
 use futures_util::StreamExt;
+use crate::tls::TlsSettings;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+pub const DEFAULT_MODEL: &str = "granite3-moe";
+pub const DEFAULT_EMBED_MODEL: &str = "nomic-embed-text";
+// Context windows below this are flagged as too short for the structured prompt plus history
+pub const SHORT_CONTEXT_TOKENS: u64 = 4096;
+// How long a preloaded model stays in memory without being used
+pub const PRELOAD_KEEP_ALIVE: &str = "30m";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchResult {
@@ -29,13 +40,352 @@ pub struct ChatMessage {
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<MessageMetadata>,
+    // Base64-encoded images for vision models such as llava
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<String>>,
+    // Set on assistant messages that ask for tools to be run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    // When the message entered the history; unset on system and tool messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    // Assistant messages only: which model answered and how long generating took
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    // Always sent to the model, however far back in the history it is
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// A function the model may call, described with a JSON schema
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolDefinition {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: FunctionDefinition,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCall {
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
     pub stream: bool,
+    // How long Ollama keeps the model loaded afterwards; None uses the server default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<GenerationOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ToolDefinition>>,
+    // Token log probabilities, for developer mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u32>,
+}
+
+// A named model derived from an installed one, with its own system prompt and parameters
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateModelRequest {
+    pub model: String,
+    pub from: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<GenerationOptions>,
+    pub stream: bool,
+}
+
+// A plain completion without chat history or the structured system prompt
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerateRequest {
+    pub model: String,
+    pub prompt: String,
+    // Text after the cursor for fill-in-the-middle; needs a model trained for infill
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<GenerationOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+    // Sends the prompt as is, without the model's template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GenerateResponse {
+    response: String,
+    #[serde(default)]
+    logprobs: Option<Vec<TokenLogprob>>,
+}
+
+// Generated text with the probability of each token, when they were requested
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub logprobs: Vec<TokenLogprob>,
+}
+
+// Log probability of one generated token, with the likeliest alternatives if asked for
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_logprobs: Vec<TokenLogprob>,
+}
+
+impl TokenLogprob {
+    // Probability between 0 and 1
+    pub fn confidence(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+// Sampling and context overrides; unset fields keep the model's own defaults
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct GenerationOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    // Fixed seed for reproducible answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    // Generation ends as soon as the model writes one of these
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+impl GenerationOptions {
+    pub fn validate(&self) -> Result<()> {
+        if self.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            bail!("Temperature must be between 0 and 2");
+        }
+        if self.top_p.is_some_and(|p| !(0.0..=1.0).contains(&p)) {
+            bail!("top_p must be between 0 and 1");
+        }
+        if self.num_ctx == Some(0) {
+            bail!("num_ctx must be greater than 0");
+        }
+        if self.stop.iter().any(|s| s.is_empty()) {
+            bail!("Stop sequences cannot be empty");
+        }
+        Ok(())
+    }
+
+    // None when nothing is overridden, so the request carries no options at all
+    pub fn for_request(&self) -> Option<Self> {
+        (*self != Self::default()).then(|| self.clone())
+    }
+}
+
+// An installed model as reported by /api/tags
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalModel {
+    pub name: String,
+    pub digest: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: String,
+    #[serde(default)]
+    pub details: ModelDetails,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ModelDetails {
+    pub format: String,
+    pub family: String,
+    pub parameter_size: String,
+    pub quantization_level: String,
+}
+
+// Installed models by size, largest first. Models sharing layers count them once
+// each, so the total can exceed what is actually used on disk.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelDiskUsage {
+    pub total_bytes: u64,
+    pub models: Vec<LocalModel>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ShowResponse {
+    #[serde(default)]
+    parameters: String,
+    #[serde(default)]
+    template: String,
+    #[serde(default)]
+    details: ModelDetails,
+    #[serde(default)]
+    model_info: serde_json::Map<String, serde_json::Value>,
+}
+
+// What /api/show reports about a model, reduced to what the UI needs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelInfo {
+    pub name: String,
+    // Modelfile PARAMETER lines, e.g. "temperature 0.7"
+    pub parameters: Vec<String>,
+    pub template: String,
+    pub details: ModelDetails,
+    // num_ctx from the parameters if set, otherwise the model's trained context length
+    pub context_length: Option<u64>,
+    pub short_context: bool,
+}
+
+impl ShowResponse {
+    fn into_info(self, name: &str) -> ModelInfo {
+        let parameters: Vec<String> = self
+            .parameters
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect();
+        let num_ctx = parameters
+            .iter()
+            .find_map(|line| line.strip_prefix("num_ctx "))
+            .and_then(|value| value.parse().ok());
+        // Keyed by architecture, e.g. "llama.context_length"
+        let trained = self
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64());
+        let context_length = num_ctx.or(trained);
+        ModelInfo {
+            name: name.to_string(),
+            parameters,
+            template: self.template,
+            details: self.details,
+            context_length,
+            short_context: context_length.is_some_and(|length| length < SHORT_CONTEXT_TOKENS),
+        }
+    }
+}
+
+// A model currently loaded in memory, from /api/ps
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RunningModel {
+    pub name: String,
+    // Bytes in memory in total, and the part of that held in VRAM
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub size_vram: u64,
+    // When Ollama unloads it unless it is used again
+    #[serde(default)]
+    pub expires_at: String,
+    #[serde(default)]
+    pub details: ModelDetails,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PsResponse {
+    models: Vec<RunningModel>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TagsResponse {
+    models: Vec<LocalModel>,
+}
+
+// One line of /api/pull's progress stream
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PullProgress {
+    pub status: String,
+    pub total: Option<u64>,
+    pub completed: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EmbedRequest {
+    model: String,
+    input: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+// The single-prompt /api/embeddings endpoint, the only one older Ollama versions have
+#[derive(Debug, Serialize, Deserialize)]
+struct EmbeddingsRequest {
+    model: String,
+    prompt: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EmbeddingsResponse {
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StreamError {
+    error: String,
+}
+
+// Reassembles newline-delimited JSON from network chunks, which can split an
+// object or carry several at once
+#[derive(Default)]
+pub(crate) struct LineBuffer {
+    buffer: Vec<u8>,
+}
+
+impl LineBuffer {
+    // Complete lines so far; a trailing partial line waits for the next chunk
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            if line.iter().any(|b| !b.is_ascii_whitespace()) {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    pub(crate) fn finish(&mut self) -> Option<Vec<u8>> {
+        let rest = std::mem::take(&mut self.buffer);
+        rest.iter().any(|b| !b.is_ascii_whitespace()).then_some(rest)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,9 +393,89 @@ pub struct ChatResponse {
     pub model: String,
     pub message: ChatMessage,
     pub done: bool,
+    // Timings in nanoseconds, only present on the final response
+    #[serde(default)]
+    pub total_duration: Option<u64>,
+    #[serde(default)]
+    pub load_duration: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_duration: Option<u64>,
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    #[serde(default)]
+    pub eval_duration: Option<u64>,
+    #[serde(default)]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+}
+
+// Final numbers for one generated message, as reported by Ollama
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatStats {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_duration_ms: u64,
+    pub load_duration_ms: u64,
+    pub prompt_eval_ms: u64,
+    pub eval_ms: u64,
+    // Generation speed alone, excluding model load and prompt processing
+    pub tokens_per_sec: f64,
+}
+
+impl ChatResponse {
+    fn stats(&self) -> Option<ChatStats> {
+        if !self.done {
+            return None;
+        }
+        let ms = |ns: Option<u64>| ns.unwrap_or(0) / 1_000_000;
+        let completion_tokens = self.eval_count.unwrap_or(0);
+        let eval_secs = self.eval_duration.unwrap_or(0) as f64 / 1e9;
+        Some(ChatStats {
+            prompt_tokens: self.prompt_eval_count.unwrap_or(0),
+            completion_tokens,
+            total_duration_ms: ms(self.total_duration),
+            load_duration_ms: ms(self.load_duration),
+            prompt_eval_ms: ms(self.prompt_eval_duration),
+            eval_ms: ms(self.eval_duration),
+            tokens_per_sec: if eval_secs > 0.0 { completion_tokens as f64 / eval_secs } else { 0.0 },
+        })
+    }
+}
+
+// One piece of a streamed reply; the last one carries the stats
+#[derive(Debug, Clone)]
+pub struct ChatChunk {
+    pub content: String,
+    pub stats: Option<ChatStats>,
+    pub tool_calls: Vec<ToolCall>,
+    // Empty unless the request asked for them
+    pub logprobs: Vec<TokenLogprob>,
+}
+
+// Live generation progress; Ollama streams roughly one token per chunk
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatProgress {
+    pub elapsed_ms: u64,
+    pub tokens: u64,
+    pub tokens_per_sec: f64,
+    pub done: bool,
+}
+
+impl ChatProgress {
+    pub fn new(elapsed: std::time::Duration, tokens: u64, done: bool) -> Self {
+        let secs = elapsed.as_secs_f64();
+        Self {
+            elapsed_ms: elapsed.as_millis() as u64,
+            tokens,
+            tokens_per_sec: if secs > 0.0 { tokens as f64 / secs } else { 0.0 },
+            done,
+        }
+    }
 }
 
 pub const SYSTEM_PROMPT: &str = r#"You are an AI assistant that follows a strict, structured thinking process on every response. Never deviate from this process.
+Today is {{date}}.
 
 PRIMARY DIRECTIVES:
 1. Always analyze context before facts
@@ -84,53 +514,586 @@ LEARNING:
 [What context was most useful]
 [What searches were most helpful]"#;
 
+// The parts of the structured format SYSTEM_PROMPT asks for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    ContextCheck,
+    FactsCheck,
+    SearchCheck,
+    Reasoning,
+    Response,
+    Learning,
+}
+
+impl Section {
+    const ALL: [Section; 6] = [
+        Section::ContextCheck,
+        Section::FactsCheck,
+        Section::SearchCheck,
+        Section::Reasoning,
+        Section::Response,
+        Section::Learning,
+    ];
+
+    fn heading(self) -> &'static str {
+        match self {
+            Section::ContextCheck => "CONTEXT_CHECK:",
+            Section::FactsCheck => "FACTS_CHECK:",
+            Section::SearchCheck => "SEARCH_CHECK:",
+            Section::Reasoning => "REASONING:",
+            Section::Response => "RESPONSE:",
+            Section::Learning => "LEARNING:",
+        }
+    }
+
+    // "REASONING: text" -> (Reasoning, "text"); models often wrap headings in ** or #
+    fn parse_heading(line: &str) -> Option<(Section, &str)> {
+        let line = line.trim_start_matches(|c: char| c == '#' || c == '*' || c.is_whitespace());
+        Section::ALL.into_iter().find_map(|section| {
+            let rest = line.strip_prefix(section.heading())?;
+            Some((section, rest.trim_start_matches('*').trim_start_matches(' ')))
+        })
+    }
+}
+
+// Routes a streamed reply into its sections a line at a time, since headings
+// only count at the start of a line. Text before the first heading is taken
+// as the response, for models that ignore the format.
+#[derive(Debug, Default)]
+pub struct SectionParser {
+    current: Option<Section>,
+    partial: String,
+    texts: [String; Section::ALL.len()],
+}
+
+impl SectionParser {
+    // Completed lines since the last call, with the section each belongs to
+    pub fn push(&mut self, chunk: &str) -> Vec<(Section, String)> {
+        self.partial.push_str(chunk);
+        let mut updates = Vec::new();
+        while let Some(newline) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=newline).collect();
+            updates.extend(self.line(&line));
+        }
+        updates
+    }
+
+    pub fn finish(&mut self) -> Option<(Section, String)> {
+        let line = std::mem::take(&mut self.partial);
+        self.line(&line)
+    }
+
+    fn line(&mut self, line: &str) -> Option<(Section, String)> {
+        let text = match Section::parse_heading(line) {
+            Some((section, rest)) => {
+                self.current = Some(section);
+                rest
+            }
+            None => line,
+        };
+        let section = self.current.unwrap_or(Section::Response);
+        let content = &mut self.texts[section as usize];
+        // Blank lines between a heading and its text carry nothing
+        if text.trim().is_empty() && content.is_empty() {
+            return None;
+        }
+        content.push_str(text);
+        Some((section, text.to_string()))
+    }
+
+    fn text(&self, section: Section) -> Option<String> {
+        let text = self.texts[section as usize].trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    pub fn metadata(&self) -> MessageMetadata {
+        MessageMetadata {
+            context_check: self.text(Section::ContextCheck),
+            facts_check: self.text(Section::FactsCheck),
+            search_check: self.text(Section::SearchCheck),
+            reasoning: self.text(Section::Reasoning),
+            learning: self.text(Section::Learning),
+            search_results: None,
+        }
+    }
+}
+
+impl MessageMetadata {
+    // Sections of a complete reply
+    pub fn parse(text: &str) -> Self {
+        let mut parser = SectionParser::default();
+        parser.push(text);
+        parser.finish();
+        parser.metadata()
+    }
+}
+
+// Failures the UI can act on, e.g. offering to pull a missing model.
+// Also used by the other chat backends, so the wording names no server.
+#[derive(Debug, thiserror::Error)]
+pub enum OllamaError {
+    #[error("The model server is not running or cannot be reached at {0}")]
+    ConnectionRefused(String),
+    #[error("Model {0} is not available")]
+    ModelNotFound(String),
+    #[error("The model server did not respond in time")]
+    Timeout,
+    #[error("Unexpected response from the model server: {0}")]
+    BadResponse(String),
+}
+
+impl OllamaError {
+    pub(crate) fn from_reqwest(error: reqwest::Error, base_url: &str, model: &str) -> Self {
+        if error.is_timeout() {
+            OllamaError::Timeout
+        } else if error.is_connect() {
+            OllamaError::ConnectionRefused(base_url.to_string())
+        } else if error.status() == Some(reqwest::StatusCode::NOT_FOUND) {
+            OllamaError::ModelNotFound(model.to_string())
+        } else {
+            OllamaError::BadResponse(error.to_string())
+        }
+    }
+
+    // Stable identifier for the frontend
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OllamaError::ConnectionRefused(_) => "connection_refused",
+            OllamaError::ModelNotFound(_) => "model_not_found",
+            OllamaError::Timeout => "timeout",
+            OllamaError::BadResponse(_) => "bad_response",
+        }
+    }
+}
+
+// Retries for when Ollama is briefly unreachable, e.g. while it restarts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetrySettings {
+    // Including the first attempt
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 8000,
+        }
+    }
+}
+
+impl RetrySettings {
+    // Doubles after every failed attempt, up to the cap
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self.initial_backoff_ms.saturating_mul(1 << attempt.min(16));
+        Duration::from_millis(delay.min(self.max_backoff_ms))
+    }
+}
+
+// Connection tuning for the HTTP clients talking to model servers
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpSettings {
+    pub connect_timeout_secs: u64,
+    // Longest silence between two reads; 0 waits forever, which a model that is
+    // still loading may need
+    pub read_timeout_secs: u64,
+    // Keeps idle connections from being dropped by proxies mid-generation; 0 turns it off
+    pub tcp_keepalive_secs: u64,
+    // Whole non-streaming requests such as summaries; streams are never cut off. 0 means no limit.
+    pub request_timeout_secs: u64,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 0,
+            tcp_keepalive_secs: 30,
+            request_timeout_secs: 300,
+        }
+    }
+}
+
+impl HttpSettings {
+    pub fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        let secs = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        let mut builder = builder.tcp_keepalive(secs(self.tcp_keepalive_secs));
+        if let Some(timeout) = secs(self.connect_timeout_secs) {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = secs(self.read_timeout_secs) {
+            builder = builder.read_timeout(timeout);
+        }
+        builder
+    }
+
+    pub fn request_timeout(&self) -> Option<Duration> {
+        (self.request_timeout_secs > 0).then(|| Duration::from_secs(self.request_timeout_secs))
+    }
+}
+
+// Limits a non-streaming request to the configured total time
+pub(crate) fn with_request_timeout(
+    request: reqwest::RequestBuilder,
+    timeout: Option<Duration>,
+) -> reqwest::RequestBuilder {
+    match timeout {
+        Some(timeout) => request.timeout(timeout),
+        None => request,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionResponse {
+    version: String,
+}
+
+// Connection indicator state
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OllamaStatus {
+    pub base_url: String,
+    pub reachable: bool,
+    pub version: Option<String>,
+    // OllamaError::kind and message when unreachable
+    pub error_kind: Option<String>,
+    pub error: Option<String>,
+}
+
+// "homelab:11434" -> "http://homelab:11434"; empty means the local default
+pub fn normalize_base_url(url: &str) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    if url.is_empty() {
+        return Ok(DEFAULT_BASE_URL.to_string());
+    }
+    let with_scheme = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    };
+    let parsed = url::Url::parse(&with_scheme)?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!("The Ollama URL must use http or https");
+    }
+    if parsed.host_str().is_none() {
+        bail!("The Ollama URL needs a host");
+    }
+    if parsed.path() != "/" || parsed.query().is_some() {
+        bail!("The Ollama URL should be just a host and port, e.g. http://homelab:11434");
+    }
+    Ok(with_scheme)
+}
+
+// Anything other than a loopback host counts as a remote provider
+pub fn is_loopback(base_url: &str) -> bool {
+    url::Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| {
+            matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+        }))
+        .unwrap_or(false)
+}
+
+// Connection failures, timeouts and 5xx responses are retried with backoff;
+// anything else is returned straight away
+pub(crate) async fn send_with_retry(
+    request: reqwest::RequestBuilder,
+    retry: &RetrySettings,
+) -> reqwest::Result<reqwest::Response> {
+    let mut attempt = 0;
+    loop {
+        let Some(this_attempt) = request.try_clone() else {
+            return request.send().await?.error_for_status();
+        };
+        let last_attempt = attempt + 1 >= retry.max_attempts;
+        match this_attempt.send().await {
+            Ok(response) if last_attempt || !response.status().is_server_error() => {
+                return response.error_for_status();
+            }
+            Err(e) if last_attempt || !(e.is_connect() || e.is_timeout()) => return Err(e),
+            _ => {}
+        }
+        tokio::time::sleep(retry.backoff(attempt)).await;
+        attempt += 1;
+    }
+}
+
 #[derive(Clone)]
 pub struct OllamaClient {
     client: reqwest::Client,
     base_url: String,
+    retry: RetrySettings,
+    request_timeout: Option<Duration>,
 }
 
 impl OllamaClient {
     pub fn new() -> Self {
+        let http = HttpSettings::default();
         Self {
-            client: reqwest::Client::new(),
-            base_url: "http://localhost:11434".to_string(),
+            client: http.apply(reqwest::Client::builder()).build().unwrap_or_default(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            retry: RetrySettings::default(),
+            request_timeout: http.request_timeout(),
         }
     }
 
-    pub async fn chat_stream(&self, request: ChatRequest) -> Result<Receiver<String>> {
-        let (tx, rx) = mpsc::channel(100);
-        let client = self.client.clone();
+    pub fn with_tls(base_url: &str, tls: &TlsSettings, http: &HttpSettings) -> Result<Self> {
+        let base_url = normalize_base_url(base_url)?;
+        let client = tls.apply(http.apply(reqwest::Client::builder()), &base_url)?.build()?;
+        Ok(Self {
+            client,
+            base_url,
+            retry: RetrySettings::default(),
+            request_timeout: http.request_timeout(),
+        })
+    }
+
+    // For the non-streaming endpoints; a long generation there is cut off at the limit
+    fn post_timed(&self, url: &str) -> reqwest::RequestBuilder {
+        with_request_timeout(self.client.post(url), self.request_timeout)
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub async fn status(&self) -> OllamaStatus {
+        let (version, error) = match self.version().await {
+            Ok(version) => (Some(version), None),
+            Err(e) => (None, Some(e)),
+        };
+        OllamaStatus {
+            base_url: self.base_url.clone(),
+            reachable: version.is_some(),
+            version,
+            error_kind: error.as_ref().map(|e| e.kind().to_string()),
+            error: error.map(|e| e.to_string()),
+        }
+    }
+
+    // Doubles as a connectivity test
+    pub async fn version(&self) -> Result<String, OllamaError> {
+        let url = format!("{}/api/version", self.base_url);
+        let response: VersionResponse = self
+            .client
+            .get(&url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| OllamaError::from_reqwest(e, &self.base_url, ""))?
+            .json()
+            .await
+            .map_err(|e| OllamaError::BadResponse(e.to_string()))?;
+        Ok(response.version)
+    }
+
+    pub fn with_retry(mut self, retry: RetrySettings) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    async fn send_with_retry(&self, request: reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+        send_with_retry(request, &self.retry).await
+    }
+
+    pub fn is_local(&self) -> bool {
+        is_loopback(&self.base_url)
+    }
+
+    // Fails if Ollama cannot be reached after retrying; errors once streaming has
+    // started arrive on the channel and end it
+    pub async fn chat_stream(
+        &self,
+        request: ChatRequest,
+    ) -> Result<Receiver<Result<ChatChunk, OllamaError>>, OllamaError> {
         let url = format!("{}/api/chat", self.base_url);
+        let response = self
+            .send_with_retry(self.client.post(&url).json(&request))
+            .await
+            .map_err(|e| OllamaError::from_reqwest(e, &self.base_url, &request.model))?;
 
+        let (tx, rx) = mpsc::channel(100);
+        let base_url = self.base_url.clone();
+        let model = request.model;
         tauri::async_runtime::spawn(async move {
-            let response = client
-                .post(&url)
-                .json(&request)
-                .send()
-                .await
-                .unwrap();
-
             let mut stream = response.bytes_stream();
+            let mut lines = LineBuffer::default();
             let mut response_buffer = String::new();
 
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(chunk) => {
-                        if let Ok(text) = String::from_utf8(chunk.to_vec()) {
-                            if let Ok(response) = serde_json::from_str::<ChatResponse>(&text) {
-                                if response.done {
-                                    response_buffer.push_str(&response.message.content);
-                                    let _ = tx.send(response_buffer.clone()).await;
-                                    response_buffer.clear();
-                                } else {
-                                    let _ = tx.send(response.message.content).await;
-                                }
+            'read: loop {
+                let (chunk_lines, ended) = match stream.next().await {
+                    Some(Ok(chunk)) => (lines.push(&chunk), false),
+                    Some(Err(e)) => {
+                        let _ = tx.send(Err(OllamaError::from_reqwest(e, &base_url, &model))).await;
+                        break;
+                    }
+                    // A final object without a trailing newline still counts
+                    None => (lines.finish().into_iter().collect(), true),
+                };
+
+                for line in chunk_lines {
+                    let chunk = match serde_json::from_slice::<ChatResponse>(&line) {
+                        Ok(response) if response.done => {
+                            response_buffer.push_str(&response.message.content);
+                            ChatChunk {
+                                stats: response.stats(),
+                                content: std::mem::take(&mut response_buffer),
+                                tool_calls: response.message.tool_calls.unwrap_or_default(),
+                                logprobs: response.logprobs.unwrap_or_default(),
+                            }
+                        }
+                        Ok(response) => ChatChunk {
+                            content: response.message.content,
+                            stats: None,
+                            tool_calls: response.message.tool_calls.unwrap_or_default(),
+                            logprobs: response.logprobs.unwrap_or_default(),
+                        },
+                        Err(_) => {
+                            // Ollama reports failures mid-stream as {"error": "..."}
+                            if let Ok(error) = serde_json::from_slice::<StreamError>(&line) {
+                                let _ = tx.send(Err(OllamaError::BadResponse(error.error))).await;
+                                break 'read;
                             }
+                            continue;
                         }
+                    };
+                    // The receiver is gone when the stream was cancelled;
+                    // dropping the response stops generation
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        break 'read;
                     }
+                }
+                if ended {
+                    break;
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    pub async fn list_models(&self) -> Result<Vec<LocalModel>> {
+        let url = format!("{}/api/tags", self.base_url);
+        let response: TagsResponse = self
+            .client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.models)
+    }
+
+    // A model missing here has to be loaded first, which delays its first token
+    pub async fn running_models(&self) -> Result<Vec<RunningModel>> {
+        let url = format!("{}/api/ps", self.base_url);
+        let response: PsResponse = self
+            .client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.models)
+    }
+
+    pub async fn disk_usage(&self) -> Result<ModelDiskUsage> {
+        let mut models = self.list_models().await?;
+        models.sort_by_key(|m| std::cmp::Reverse(m.size));
+        Ok(ModelDiskUsage {
+            total_bytes: models.iter().map(|m| m.size).sum(),
+            models,
+        })
+    }
+
+    pub async fn show_model(&self, name: &str) -> Result<ModelInfo> {
+        let url = format!("{}/api/show", self.base_url);
+        let response: ShowResponse = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "model": name }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.into_info(name))
+    }
+
+    pub async fn create_model(&self, request: CreateModelRequest) -> Result<(), OllamaError> {
+        let url = format!("{}/api/create", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| OllamaError::from_reqwest(e, &self.base_url, &request.from))?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        // The body explains what was wrong, e.g. an unknown base model
+        let status = response.status();
+        let message = response
+            .json::<StreamError>()
+            .await
+            .map(|e| e.error)
+            .unwrap_or_else(|_| status.to_string());
+        Err(OllamaError::BadResponse(message))
+    }
+
+    pub async fn delete_model(&self, name: &str) -> Result<()> {
+        let url = format!("{}/api/delete", self.base_url);
+        self.client
+            .delete(&url)
+            .json(&serde_json::json!({ "model": name }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    // Streams pull progress until the download finishes; stops early if the receiver is dropped
+    pub async fn pull_model(&self, name: &str) -> Result<Receiver<PullProgress>> {
+        let url = format!("{}/api/pull", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "model": name, "stream": true }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let (tx, rx) = mpsc::channel(100);
+        tauri::async_runtime::spawn(async move {
+            let mut stream = response.bytes_stream();
+            let mut lines = LineBuffer::default();
+            while let Some(item) = stream.next().await {
+                let chunk = match item {
+                    Ok(chunk) => chunk,
                     Err(e) => {
-                        eprintln!("Error reading chunk: {:?}", e);
+                        let error = PullProgress {
+                            error: Some(e.to_string()),
+                            ..Default::default()
+                        };
+                        let _ = tx.send(error).await;
+                        break;
+                    }
+                };
+                for line in lines.push(&chunk) {
+                    let Ok(progress) = serde_json::from_slice::<PullProgress>(&line) else {
+                        continue;
+                    };
+                    if tx.send(progress).await.is_err() {
+                        return;
                     }
                 }
             }
@@ -139,11 +1102,104 @@ impl OllamaClient {
         Ok(rx)
     }
 
+    // Non-streaming request for short background tasks such as classification
+    pub async fn chat(&self, mut request: ChatRequest) -> Result<ChatMessage> {
+        request.stream = false;
+        let url = format!("{}/api/chat", self.base_url);
+        let response: ChatResponse = self
+            .send_with_retry(self.post_timed(&url).json(&request))
+            .await
+            .map_err(|e| OllamaError::from_reqwest(e, &self.base_url, &request.model))?
+            .json()
+            .await
+            .map_err(|e| OllamaError::BadResponse(e.to_string()))?;
+        Ok(response.message)
+    }
+
+    // Non-streaming; for titles, summaries and classification
+    pub async fn generate(&self, request: GenerateRequest) -> Result<String, OllamaError> {
+        self.complete(request).await.map(|completion| completion.text)
+    }
+
+    // Like generate, keeping the token log probabilities
+    pub async fn complete(&self, mut request: GenerateRequest) -> Result<Completion, OllamaError> {
+        request.stream = false;
+        let url = format!("{}/api/generate", self.base_url);
+        let response: GenerateResponse = self
+            .send_with_retry(self.post_timed(&url).json(&request))
+            .await
+            .map_err(|e| OllamaError::from_reqwest(e, &self.base_url, &request.model))?
+            .json()
+            .await
+            .map_err(|e| OllamaError::BadResponse(e.to_string()))?;
+        Ok(Completion {
+            text: response.response,
+            logprobs: response.logprobs.unwrap_or_default(),
+        })
+    }
+
+    // Loads the model into memory without generating anything, keeping it there for `keep_alive`
+    pub async fn preload(&self, model: &str, keep_alive: &str) -> Result<(), OllamaError> {
+        let request = GenerateRequest {
+            model: model.to_string(),
+            prompt: String::new(),
+            suffix: None,
+            system: None,
+            stream: false,
+            options: None,
+            keep_alive: Some(keep_alive.to_string()),
+            raw: None,
+            logprobs: None,
+            top_logprobs: None,
+        };
+        self.generate(request).await.map(|_| ())
+    }
+
+    // One vector per input text, in the same order. Batches through /api/embed and falls
+    // back to one /api/embeddings call per text on servers without it.
+    pub async fn embed(&self, texts: Vec<String>, model: String) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embed", self.base_url);
+        let request = EmbedRequest {
+            model: model.clone(),
+            input: texts,
+        };
+        let response = self.post_timed(&url).json(&request).send().await?;
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            let response: EmbedResponse = response.error_for_status()?.json().await?;
+            return Ok(response.embeddings);
+        }
+
+        let url = format!("{}/api/embeddings", self.base_url);
+        let mut embeddings = Vec::with_capacity(request.input.len());
+        for prompt in request.input {
+            let request = EmbeddingsRequest {
+                model: model.clone(),
+                prompt,
+            };
+            let response: EmbeddingsResponse = self
+                .post_timed(&url)
+                .json(&request)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            embeddings.push(response.embedding);
+        }
+        Ok(embeddings)
+    }
+
     pub fn create_system_message() -> ChatMessage {
         ChatMessage {
             role: "system".to_string(),
             content: SYSTEM_PROMPT.to_string(),
             metadata: None,
+            images: None,
+            tool_calls: None,
+            created_at: None,
+            model: None,
+            duration_ms: None,
+            pinned: false,
         }
     }
 
@@ -152,21 +1208,130 @@ impl OllamaClient {
             role: "user".to_string(),
             content,
             metadata: None,
+            images: None,
+            tool_calls: None,
+            created_at: Some(Utc::now()),
+            model: None,
+            duration_ms: None,
+            pinned: false,
         }
     }
 
     pub fn create_assistant_message(content: String) -> ChatMessage {
         ChatMessage {
             role: "assistant".to_string(),
+            metadata: Some(MessageMetadata::parse(&content)),
             content,
-            metadata: Some(MessageMetadata {
-                context_check: None,
-                facts_check: None,
-                search_check: None,
-                reasoning: None,
-                learning: None,
-                search_results: None,
-            }),
+            images: None,
+            tool_calls: None,
+            created_at: Some(Utc::now()),
+            model: None,
+            duration_ms: None,
+            pinned: false,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_split_across_pushes_comes_out_whole() {
+        let mut lines = LineBuffer::default();
+        assert!(lines.push(b"{\"message\":{\"con").is_empty());
+        assert!(lines.push(b"tent\":\"hi\"}").is_empty());
+        assert_eq!(lines.push(b",\"done\":false}\n"), vec![b"{\"message\":{\"content\":\"hi\"},\"done\":false}\n".to_vec()]);
+        assert_eq!(lines.finish(), None);
+    }
+
+    #[test]
+    fn several_objects_in_one_push_are_separate_lines() {
+        let mut lines = LineBuffer::default();
+        let pushed = lines.push(b"{\"a\":1}\n\n{\"b\":2}\n{\"c\":");
+        assert_eq!(pushed, vec![b"{\"a\":1}\n".to_vec(), b"{\"b\":2}\n".to_vec()]);
+        assert_eq!(lines.push(b"3}\n"), vec![b"{\"c\":3}\n".to_vec()]);
+    }
+
+    #[test]
+    fn utf8_sequence_split_across_chunks_is_rejoined() {
+        let line = "{\"content\":\"café ✓\"}\n".as_bytes();
+        // Inside the two-byte é, then inside the three-byte ✓
+        let (first, rest) = line.split_at(16);
+        let (second, third) = rest.split_at(4);
+        let mut lines = LineBuffer::default();
+        assert!(lines.push(first).is_empty());
+        assert!(lines.push(second).is_empty());
+        let pushed = lines.push(third);
+        assert_eq!(pushed.len(), 1);
+        assert_eq!(String::from_utf8(pushed[0].clone()).unwrap(), "{\"content\":\"café ✓\"}\n");
+    }
+
+    #[test]
+    fn trailing_line_without_newline_is_returned_by_finish() {
+        let mut lines = LineBuffer::default();
+        assert_eq!(lines.push(b"{\"a\":1}\n{\"done\":true}"), vec![b"{\"a\":1}\n".to_vec()]);
+        assert_eq!(lines.finish(), Some(b"{\"done\":true}".to_vec()));
+        assert_eq!(lines.finish(), None);
+    }
+
+    const STRUCTURED_ANSWER: &str = "**CONTEXT_CHECK:** Earlier turns mention a Postgres 16 cluster.\n\
+        ## FACTS_CHECK:\n\
+        The team prefers logical replication.\n\
+        SEARCH_CHECK: No search needed.\n\
+        # REASONING:\n\
+        Logical replication keeps writes online — no downtime.\n\
+        \n\
+        **RESPONSE:**\n\
+        Use pg_upgrade with --link.\n\
+        ### **LEARNING:**\n\
+        - New information: the cluster is 2 TB";
+
+    fn assert_structured_metadata(metadata: &MessageMetadata) {
+        assert_eq!(metadata.context_check.as_deref(), Some("Earlier turns mention a Postgres 16 cluster."));
+        assert_eq!(metadata.facts_check.as_deref(), Some("The team prefers logical replication."));
+        assert_eq!(metadata.search_check.as_deref(), Some("No search needed."));
+        assert_eq!(metadata.reasoning.as_deref(), Some("Logical replication keeps writes online — no downtime."));
+        assert_eq!(metadata.learning.as_deref(), Some("- New information: the cluster is 2 TB"));
+        assert!(metadata.search_results.is_none());
+    }
+
+    #[test]
+    fn metadata_parse_reads_every_section() {
+        assert_structured_metadata(&MessageMetadata::parse(STRUCTURED_ANSWER));
+    }
+
+    #[test]
+    fn section_parser_handles_any_chunking() {
+        for size in 1..=STRUCTURED_ANSWER.len() {
+            let mut parser = SectionParser::default();
+            let mut response = String::new();
+            let mut start = 0;
+            while start < STRUCTURED_ANSWER.len() {
+                // Chunks are &str, so a cut can only fall on a character boundary
+                let mut end = (start + size).min(STRUCTURED_ANSWER.len());
+                while !STRUCTURED_ANSWER.is_char_boundary(end) {
+                    end += 1;
+                }
+                for (section, text) in parser.push(&STRUCTURED_ANSWER[start..end]) {
+                    if section == Section::Response {
+                        response.push_str(&text);
+                    }
+                }
+                start = end;
+            }
+            if let Some((Section::Response, text)) = parser.finish() {
+                response.push_str(&text);
+            }
+            assert_structured_metadata(&parser.metadata());
+            assert_eq!(response.trim(), "Use pg_upgrade with --link.", "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn text_before_the_first_heading_is_the_response() {
+        let mut parser = SectionParser::default();
+        let updates = parser.push("Plain answer with no format.\nSecond line\n");
+        assert!(updates.iter().all(|(section, _)| *section == Section::Response));
+        assert!(parser.metadata().reasoning.is_none());
+    }
+}
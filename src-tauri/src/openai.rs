@@ -0,0 +1,490 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, Receiver};
+
+use crate::backend::LlmBackend;
+use crate::ollama::{
+    self, ChatChunk, ChatMessage, ChatRequest, ChatStats, FunctionCall, LineBuffer, LocalModel, OllamaError,
+    HttpSettings, RetrySettings, TokenLogprob, ToolCall,
+};
+use crate::tls::TlsSettings;
+
+// Any server speaking the OpenAI chat completions API: OpenAI, Groq, OpenRouter, vLLM...
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OpenAiSettings {
+    // Up to and including the version segment, e.g. https://api.groq.com/openai/v1
+    pub base_url: String,
+    // Selected when switching to this backend
+    pub default_model: String,
+    pub embed_model: String,
+}
+
+impl Default for OpenAiSettings {
+    fn default() -> Self {
+        Self {
+            base_url: "https://api.openai.com/v1".to_string(),
+            default_model: "gpt-4o-mini".to_string(),
+            embed_model: "text-embedding-3-small".to_string(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OpenAiClient {
+    client: reqwest::Client,
+    base_url: String,
+    // Local servers such as vLLM usually run without one
+    api_key: Option<String>,
+    retry: RetrySettings,
+    request_timeout: Option<std::time::Duration>,
+    default_model: String,
+    embed_model: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionResponse {
+    choices: Vec<CompletionChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChoice {
+    message: CompletionMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+// One `data:` event of a streamed completion
+#[derive(Debug, Deserialize)]
+struct StreamEvent {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: Delta,
+    #[serde(default)]
+    logprobs: Option<ChoiceLogprobs>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceLogprobs {
+    #[serde(default)]
+    content: Option<Vec<TokenLogprob>>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct Delta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+// Tool calls arrive in pieces: the name first, then the arguments a fragment at a time
+#[derive(Debug, Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingEntry {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+// Vision input goes in as a data URL, so the image type has to be guessed from its first bytes
+fn image_url(data: &str) -> String {
+    let mime_type = match data {
+        d if d.starts_with("/9j/") => "image/jpeg",
+        d if d.starts_with("R0lG") => "image/gif",
+        d if d.starts_with("UklG") => "image/webp",
+        d if d.starts_with("Qk") => "image/bmp",
+        _ => "image/png",
+    };
+    format!("data:{};base64,{}", mime_type, data)
+}
+
+// Our history has no tool call ids, so calls are numbered and the tool results that
+// follow are matched to them in order
+fn wire_messages(messages: &[ChatMessage]) -> Vec<Value> {
+    let mut pending_ids = VecDeque::new();
+    let mut next_id = 0;
+    messages
+        .iter()
+        .map(|message| {
+            let mut value = match message.images.as_deref() {
+                Some(images) if !images.is_empty() => {
+                    let mut parts = vec![json!({ "type": "text", "text": message.content })];
+                    parts.extend(images.iter().map(|data| {
+                        json!({ "type": "image_url", "image_url": { "url": image_url(data) } })
+                    }));
+                    json!({ "role": message.role, "content": parts })
+                }
+                _ => json!({ "role": message.role, "content": message.content }),
+            };
+            if let Some(calls) = &message.tool_calls {
+                let calls: Vec<Value> = calls
+                    .iter()
+                    .map(|call| {
+                        let id = format!("call_{}", next_id);
+                        next_id += 1;
+                        pending_ids.push_back(id.clone());
+                        json!({
+                            "id": id,
+                            "type": "function",
+                            "function": {
+                                "name": call.function.name,
+                                "arguments": call.function.arguments.to_string(),
+                            }
+                        })
+                    })
+                    .collect();
+                value["tool_calls"] = Value::Array(calls);
+            }
+            if message.role == "tool" {
+                if let Some(id) = pending_ids.pop_front() {
+                    value["tool_call_id"] = json!(id);
+                }
+            }
+            value
+        })
+        .collect()
+}
+
+fn request_body(request: &ChatRequest, stream: bool) -> Value {
+    let mut body = json!({
+        "messages": wire_messages(&request.messages),
+        "stream": stream,
+    });
+    // Without a model, local servers answer with whichever one they have loaded
+    if !request.model.is_empty() {
+        body["model"] = json!(request.model);
+    }
+    if stream {
+        // Token counts arrive in a final event that has no choices
+        body["stream_options"] = json!({ "include_usage": true });
+    }
+    // num_ctx has no equivalent; the server decides the context size
+    if let Some(options) = &request.options {
+        if let Some(temperature) = options.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = options.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(seed) = options.seed {
+            body["seed"] = json!(seed);
+        }
+        if !options.stop.is_empty() {
+            body["stop"] = json!(options.stop);
+        }
+    }
+    if let Some(tools) = &request.tools {
+        body["tools"] = json!(tools);
+    }
+    if let Some(logprobs) = request.logprobs {
+        body["logprobs"] = json!(logprobs);
+    }
+    if let Some(top_logprobs) = request.top_logprobs {
+        body["top_logprobs"] = json!(top_logprobs);
+    }
+    body
+}
+
+// Only token counts are reported, so timings are measured here: prompt processing
+// until the first token, generation from there to the end
+fn stream_stats(usage: &Usage, started: Instant, first_token: Option<Instant>) -> ChatStats {
+    let ms = |d: std::time::Duration| d.as_millis() as u64;
+    let first_token = first_token.unwrap_or(started);
+    let generation = first_token.elapsed();
+    let secs = generation.as_secs_f64();
+    ChatStats {
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        total_duration_ms: ms(started.elapsed()),
+        load_duration_ms: 0,
+        prompt_eval_ms: ms(first_token - started),
+        eval_ms: ms(generation),
+        tokens_per_sec: if secs > 0.0 { usage.completion_tokens as f64 / secs } else { 0.0 },
+    }
+}
+
+fn tool_calls(partial: Vec<(String, String)>) -> Vec<ToolCall> {
+    partial
+        .into_iter()
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, arguments)| ToolCall {
+            function: FunctionCall {
+                name,
+                arguments: serde_json::from_str(&arguments).unwrap_or_else(|_| json!({})),
+            },
+        })
+        .collect()
+}
+
+impl OpenAiClient {
+    pub fn new(
+        settings: &OpenAiSettings,
+        tls: &TlsSettings,
+        http: &HttpSettings,
+        retry: RetrySettings,
+        api_key: Option<String>,
+    ) -> Result<Self> {
+        let base_url = settings.base_url.trim().trim_end_matches('/').to_string();
+        url::Url::parse(&base_url).with_context(|| format!("Not a valid URL: {}", base_url))?;
+        let client = tls.apply(http.apply(reqwest::Client::builder()), &base_url)?.build()?;
+        Ok(Self {
+            client,
+            base_url,
+            api_key: api_key.filter(|key| !key.is_empty()),
+            retry,
+            request_timeout: http.request_timeout(),
+            default_model: settings.default_model.clone(),
+            embed_model: settings.embed_model.clone(),
+        })
+    }
+
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.authorize(self.client.post(format!("{}{}", self.base_url, path)))
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenAiClient {
+    fn is_local(&self) -> bool {
+        ollama::is_loopback(&self.base_url)
+    }
+
+    fn default_model(&self) -> String {
+        self.default_model.clone()
+    }
+
+    fn embed_model(&self) -> String {
+        self.embed_model.clone()
+    }
+
+    async fn chat_stream(
+        &self,
+        request: ChatRequest,
+    ) -> Result<Receiver<Result<ChatChunk, OllamaError>>, OllamaError> {
+        let started = Instant::now();
+        let response = ollama::send_with_retry(
+            self.post("/chat/completions").json(&request_body(&request, true)),
+            &self.retry,
+        )
+        .await
+        .map_err(|e| OllamaError::from_reqwest(e, &self.base_url, &request.model))?;
+
+        let (tx, rx) = mpsc::channel(100);
+        let base_url = self.base_url.clone();
+        let model = request.model;
+        tauri::async_runtime::spawn(async move {
+            let mut stream = response.bytes_stream();
+            let mut lines = LineBuffer::default();
+            let mut first_token = None;
+            let mut usage = None;
+            // (name, arguments so far) per tool call index
+            let mut partial_calls: Vec<(String, String)> = Vec::new();
+
+            'read: loop {
+                let (event_lines, ended) = match stream.next().await {
+                    Some(Ok(chunk)) => (lines.push(&chunk), false),
+                    Some(Err(e)) => {
+                        let _ = tx.send(Err(OllamaError::from_reqwest(e, &base_url, &model))).await;
+                        return;
+                    }
+                    None => (lines.finish().into_iter().collect(), true),
+                };
+
+                for line in event_lines {
+                    let line = String::from_utf8_lossy(&line);
+                    // Server-sent events; comments and other fields are keep-alives
+                    let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                        continue;
+                    };
+                    if data == "[DONE]" {
+                        break 'read;
+                    }
+                    let Ok(event) = serde_json::from_str::<StreamEvent>(data) else {
+                        continue;
+                    };
+                    if let Some(error) = event.error {
+                        let message = error["message"].as_str().map(str::to_string).unwrap_or_else(|| error.to_string());
+                        let _ = tx.send(Err(OllamaError::BadResponse(message))).await;
+                        return;
+                    }
+                    if event.usage.is_some() {
+                        usage = event.usage;
+                    }
+                    for choice in event.choices {
+                        for call in choice.delta.tool_calls {
+                            if partial_calls.len() <= call.index {
+                                partial_calls.resize(call.index + 1, Default::default());
+                            }
+                            if let Some(function) = call.function {
+                                let (name, arguments) = &mut partial_calls[call.index];
+                                name.push_str(function.name.as_deref().unwrap_or_default());
+                                arguments.push_str(function.arguments.as_deref().unwrap_or_default());
+                            }
+                        }
+                        let Some(content) = choice.delta.content.filter(|c| !c.is_empty()) else {
+                            continue;
+                        };
+                        first_token.get_or_insert_with(Instant::now);
+                        let chunk = ChatChunk {
+                            content,
+                            stats: None,
+                            tool_calls: Vec::new(),
+                            logprobs: choice.logprobs.and_then(|l| l.content).unwrap_or_default(),
+                        };
+                        // The receiver is gone when the stream was cancelled
+                        if tx.send(Ok(chunk)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                if ended {
+                    break;
+                }
+            }
+
+            let chunk = ChatChunk {
+                content: String::new(),
+                stats: usage.map(|usage| stream_stats(&usage, started, first_token)),
+                tool_calls: tool_calls(partial_calls),
+                logprobs: Vec::new(),
+            };
+            let _ = tx.send(Ok(chunk)).await;
+        });
+
+        Ok(rx)
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatMessage> {
+        let response: CompletionResponse = ollama::send_with_retry(
+            ollama::with_request_timeout(self.post("/chat/completions"), self.request_timeout)
+                .json(&request_body(&request, false)),
+            &self.retry,
+        )
+        .await
+        .map_err(|e| OllamaError::from_reqwest(e, &self.base_url, &request.model))?
+        .json()
+        .await?;
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+        Ok(ChatMessage {
+            role: "assistant".to_string(),
+            content,
+            metadata: None,
+            images: None,
+            tool_calls: None,
+            created_at: Some(Utc::now()),
+            model: None,
+            duration_ms: None,
+            pinned: false,
+        })
+    }
+
+    // Only ids are listed; sizes and details are not part of the API
+    async fn list_models(&self) -> Result<Vec<LocalModel>> {
+        let url = format!("{}/models", self.base_url);
+        let response: ModelsResponse = self
+            .authorize(self.client.get(&url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let mut models: Vec<LocalModel> = response
+            .data
+            .into_iter()
+            .map(|model| LocalModel {
+                name: model.id,
+                digest: String::new(),
+                size: 0,
+                modified_at: String::new(),
+                details: Default::default(),
+            })
+            .collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(models)
+    }
+
+    async fn embed(&self, texts: Vec<String>, model: String) -> Result<Vec<Vec<f32>>> {
+        let response: EmbeddingsResponse = ollama::send_with_retry(
+            ollama::with_request_timeout(self.post("/embeddings"), self.request_timeout)
+                .json(&json!({ "model": model, "input": texts })),
+            &self.retry,
+        )
+        .await?
+        .json()
+        .await?;
+        let mut data = response.data;
+        data.sort_by_key(|entry| entry.index);
+        Ok(data.into_iter().map(|entry| entry.embedding).collect())
+    }
+
+    fn boxed_clone(&self) -> Box<dyn LlmBackend> {
+        Box::new(self.clone())
+    }
+}
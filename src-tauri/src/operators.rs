@@ -0,0 +1,153 @@
+use url::Url;
+
+// A search query split into plain terms and the operators people type out of habit:
+// "exact phrase", -excluded, site:example.com and filetype:pdf
+#[derive(Debug, Clone, Default)]
+pub struct ParsedQuery {
+    pub terms: Vec<String>,
+    pub phrases: Vec<String>,
+    pub excluded: Vec<String>,
+    pub sites: Vec<String>,
+    pub excluded_sites: Vec<String>,
+    pub filetype: Option<String>,
+}
+
+// Splits on whitespace, keeping double-quoted runs (and a leading minus) together
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                current.push(c);
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn unquote(token: &str) -> Option<&str> {
+    token
+        .strip_prefix('"')
+        .map(|rest| rest.strip_suffix('"').unwrap_or(rest))
+        .map(str::trim)
+}
+
+fn normalize_site(site: &str) -> String {
+    let site = site.trim().trim_end_matches('/').to_lowercase();
+    let site = site
+        .strip_prefix("https://")
+        .or_else(|| site.strip_prefix("http://"))
+        .unwrap_or(&site);
+    site.strip_prefix("www.").unwrap_or(site).to_string()
+}
+
+fn host_matches(host: &str, site: &str) -> bool {
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    host == site || host.ends_with(&format!(".{}", site))
+}
+
+impl ParsedQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        for token in tokenize(query) {
+            let (negated, body) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token.as_str()),
+            };
+
+            let lower = body.to_lowercase();
+            if let Some(site) = lower.strip_prefix("site:").filter(|s| !s.is_empty()) {
+                let site = normalize_site(site);
+                if negated {
+                    parsed.excluded_sites.push(site);
+                } else {
+                    parsed.sites.push(site);
+                }
+            } else if let Some(ext) = lower
+                .strip_prefix("filetype:")
+                .or_else(|| lower.strip_prefix("ext:"))
+                .filter(|s| !s.is_empty() && !negated)
+            {
+                parsed.filetype = Some(ext.trim_start_matches('.').to_string());
+            } else if let Some(phrase) = unquote(body).filter(|p| !p.is_empty()) {
+                if negated {
+                    parsed.excluded.push(phrase.to_string());
+                } else {
+                    parsed.phrases.push(phrase.to_string());
+                }
+            } else if negated {
+                parsed.excluded.push(body.to_string());
+            } else {
+                parsed.terms.push(token);
+            }
+        }
+        parsed
+    }
+
+    // DuckDuckGo understands all of these natively; several site: operators are OR-ed
+    pub fn to_duckduckgo(&self) -> String {
+        let mut parts: Vec<String> = self.terms.clone();
+        parts.extend(self.phrases.iter().map(|p| format!("\"{}\"", p)));
+        parts.extend(self.excluded.iter().map(|term| {
+            if term.contains(' ') {
+                format!("-\"{}\"", term)
+            } else {
+                format!("-{}", term)
+            }
+        }));
+        match self.sites.as_slice() {
+            [] => {}
+            [site] => parts.push(format!("site:{}", site)),
+            sites => parts.push(format!(
+                "({})",
+                sites.iter().map(|s| format!("site:{}", s)).collect::<Vec<_>>().join(" OR ")
+            )),
+        }
+        parts.extend(self.excluded_sites.iter().map(|s| format!("-site:{}", s)));
+        if let Some(ext) = &self.filetype {
+            parts.push(format!("filetype:{}", ext));
+        }
+        parts.join(" ")
+    }
+
+    // Post-filter for when the provider ignores an operator. Phrases are left to the
+    // provider since the snippet at this point is only the title.
+    pub fn matches(&self, url: &str, title: &str) -> bool {
+        let Ok(parsed) = Url::parse(url) else {
+            return true;
+        };
+        let host = parsed.host_str().unwrap_or_default().to_lowercase();
+
+        if !self.sites.is_empty() && !self.sites.iter().any(|site| host_matches(&host, site)) {
+            return false;
+        }
+        if self.excluded_sites.iter().any(|site| host_matches(&host, site)) {
+            return false;
+        }
+
+        if let Some(ext) = &self.filetype {
+            let path = parsed.path().to_lowercase();
+            if !path.ends_with(&format!(".{}", ext)) {
+                return false;
+            }
+        }
+
+        let haystack = format!("{} {}", url, title).to_lowercase();
+        !self
+            .excluded
+            .iter()
+            .any(|term| haystack.contains(&term.to_lowercase()))
+    }
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// How often the battery state is re-read in automatic mode
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+// While low-power mode is active
+pub const ENRICH_CONCURRENCY: usize = 1;
+pub const KEEP_ALIVE: &str = "1m";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerMode {
+    #[default]
+    Off,
+    On,
+    // Active whenever the machine runs on battery
+    Auto,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PowerSettings {
+    pub mode: PowerMode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PowerStatus {
+    pub mode: PowerMode,
+    // None when the battery state cannot be read on this platform
+    pub on_battery: Option<bool>,
+    pub low_power: bool,
+}
+
+impl PowerSettings {
+    pub fn status(&self) -> PowerStatus {
+        let on_battery = on_battery();
+        let low_power = match self.mode {
+            PowerMode::Off => false,
+            PowerMode::On => true,
+            PowerMode::Auto => on_battery.unwrap_or(false),
+        };
+        PowerStatus {
+            mode: self.mode,
+            on_battery,
+            low_power,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn on_battery() -> Option<bool> {
+    let supplies = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let mut found_battery = false;
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        let kind = std::fs::read_to_string(path.join("type")).unwrap_or_default();
+        if kind.trim() != "Battery" {
+            continue;
+        }
+        found_battery = true;
+        let status = std::fs::read_to_string(path.join("status")).unwrap_or_default();
+        if status.trim() == "Discharging" {
+            return Some(true);
+        }
+    }
+    found_battery.then_some(false)
+}
+
+#[cfg(target_os = "macos")]
+fn on_battery() -> Option<bool> {
+    let output = std::process::Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    if text.contains("'Battery Power'") {
+        Some(true)
+    } else if text.contains("'AC Power'") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn on_battery() -> Option<bool> {
+    None
+}
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::secrets;
+use crate::storage;
+use crate::tls::TlsSettings;
+
+const VALIDATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Brave,
+    OpenAi,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Search,
+    Llm,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 2] = [Provider::Brave, Provider::OpenAi];
+
+    pub fn kind(self) -> ProviderKind {
+        match self {
+            Provider::Brave => ProviderKind::Search,
+            Provider::OpenAi => ProviderKind::Llm,
+        }
+    }
+
+    // Keychain entry name for the provider's key
+    fn secret_name(self) -> &'static str {
+        match self {
+            Provider::Brave => "brave_api_key",
+            Provider::OpenAi => "openai_api_key",
+        }
+    }
+
+    fn key_name(self) -> &'static str {
+        match self {
+            Provider::Brave => "brave",
+            Provider::OpenAi => "openai",
+        }
+    }
+
+    pub fn api_key(self) -> Result<Option<String>> {
+        secrets::get_secret(self.secret_name())
+    }
+
+    // The cheapest authenticated request each provider offers. The OpenAI key is
+    // checked against whichever compatible server the chat backend points at.
+    fn validation_url(self, openai_base_url: &str) -> String {
+        match self {
+            Provider::Brave => "https://api.search.brave.com/res/v1/web/search?q=test&count=1".to_string(),
+            Provider::OpenAi => format!("{}/models", openai_base_url.trim_end_matches('/')),
+        }
+    }
+
+    async fn validate(self, tls: &TlsSettings, openai_base_url: &str, key: &str) -> Result<()> {
+        let url = self.validation_url(openai_base_url);
+        let client = tls
+            .apply(Client::builder().timeout(VALIDATION_TIMEOUT), &url)?
+            .build()?;
+        let request = match self {
+            Provider::Brave => client.get(&url).header("X-Subscription-Token", key),
+            Provider::OpenAi => client.get(&url).bearer_auth(key),
+        };
+
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            anyhow::bail!("Key was rejected ({})", status)
+        } else {
+            anyhow::bail!("Validation request failed ({})", status)
+        }
+    }
+}
+
+// Outcome of the last validation, kept so settings can show it without re-checking
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ValidationResult {
+    pub valid: bool,
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProviderKeyStatus {
+    pub provider: Provider,
+    pub kind: ProviderKind,
+    pub configured: bool,
+    pub masked_key: Option<String>,
+    pub last_validation: Option<ValidationResult>,
+}
+
+// Keys themselves stay in the keychain; only validation results are written to disk
+pub struct ProviderKeys {
+    path: PathBuf,
+    validations: HashMap<String, ValidationResult>,
+}
+
+impl ProviderKeys {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("provider_keys.json");
+        let validations = storage::load_json(&path);
+        Self { path, validations }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.validations)
+    }
+
+    fn record(&mut self, provider: Provider, outcome: &Result<()>) -> Result<ValidationResult> {
+        let result = ValidationResult {
+            valid: outcome.is_ok(),
+            error: outcome.as_ref().err().map(|e| e.to_string()),
+            checked_at: Utc::now(),
+        };
+        self.validations.insert(provider.key_name().to_string(), result.clone());
+        self.save()?;
+        Ok(result)
+    }
+
+    pub fn status(&self, provider: Provider) -> Result<ProviderKeyStatus> {
+        let key = secrets::get_secret(provider.secret_name())?;
+        Ok(ProviderKeyStatus {
+            provider,
+            kind: provider.kind(),
+            configured: key.is_some(),
+            masked_key: key.as_deref().map(secrets::mask),
+            last_validation: self.validations.get(provider.key_name()).cloned(),
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<ProviderKeyStatus>> {
+        Provider::ALL.iter().map(|&p| self.status(p)).collect()
+    }
+
+    // A key is only stored once the provider has accepted it
+    pub async fn set(
+        &mut self,
+        tls: &TlsSettings,
+        openai_base_url: &str,
+        provider: Provider,
+        key: &str,
+    ) -> Result<ProviderKeyStatus> {
+        let key = key.trim();
+        if key.is_empty() {
+            anyhow::bail!("API key is empty");
+        }
+        let outcome = provider.validate(tls, openai_base_url, key).await;
+        if let Err(e) = outcome {
+            anyhow::bail!("Not saving {} key: {}", provider.key_name(), e);
+        }
+        secrets::set_secret(provider.secret_name(), key)?;
+        self.record(provider, &outcome)?;
+        self.status(provider)
+    }
+
+    pub async fn validate(
+        &mut self,
+        tls: &TlsSettings,
+        openai_base_url: &str,
+        provider: Provider,
+    ) -> Result<ValidationResult> {
+        let Some(key) = secrets::get_secret(provider.secret_name())? else {
+            anyhow::bail!("No {} key configured", provider.key_name());
+        };
+        let outcome = provider.validate(tls, openai_base_url, &key).await;
+        self.record(provider, &outcome)
+    }
+
+    pub fn remove(&mut self, provider: Provider) -> Result<()> {
+        secrets::delete_secret(provider.secret_name())?;
+        self.validations.remove(provider.key_name());
+        self.save()
+    }
+}
@@ -0,0 +1,126 @@
+// Separates the <think>…</think> spans reasoning models such as deepseek-r1 write
+// before their answer, so they can be shown apart and kept out of the history
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+#[derive(Debug, Default)]
+pub struct Split {
+    pub visible: String,
+    pub reasoning: String,
+}
+
+impl Split {
+    fn part(&mut self, thinking: bool) -> &mut String {
+        if thinking {
+            &mut self.reasoning
+        } else {
+            &mut self.visible
+        }
+    }
+}
+
+// Streaming splitter; a tag cut in half between chunks is held back until it is complete
+#[derive(Debug, Default)]
+pub struct ThinkFilter {
+    thinking: bool,
+    pending: String,
+    // The answer usually follows the closing tag after a blank line
+    trim_answer: bool,
+}
+
+// Length of the longest end of `text` that could be the start of `tag`
+fn partial_tag_len(text: &str, tag: &str) -> usize {
+    (1..tag.len())
+        .rev()
+        .find(|&n| text.ends_with(&tag[..n]))
+        .unwrap_or(0)
+}
+
+impl ThinkFilter {
+    pub fn push(&mut self, chunk: &str) -> Split {
+        self.pending.push_str(chunk);
+        let mut split = Split::default();
+        loop {
+            let tag = if self.thinking { CLOSE_TAG } else { OPEN_TAG };
+            let Some(position) = self.pending.find(tag) else {
+                let ready = self.pending.len() - partial_tag_len(&self.pending, tag);
+                let text: String = self.pending.drain(..ready).collect();
+                self.append(&mut split, &text);
+                return split;
+            };
+            let text: String = self.pending.drain(..position).collect();
+            self.pending.drain(..tag.len());
+            self.append(&mut split, &text);
+            self.trim_answer = self.thinking;
+            self.thinking = !self.thinking;
+        }
+    }
+
+    // Whatever was held back once the stream has ended
+    pub fn finish(&mut self) -> Split {
+        let mut split = Split::default();
+        let text = std::mem::take(&mut self.pending);
+        self.append(&mut split, &text);
+        split
+    }
+
+    fn append(&mut self, split: &mut Split, text: &str) {
+        let text = if !self.thinking && self.trim_answer {
+            let trimmed = text.trim_start();
+            self.trim_answer = trimmed.is_empty();
+            trimmed
+        } else {
+            text
+        };
+        split.part(self.thinking).push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds the chunks as a stream would and collects both parts
+    fn run(chunks: &[&str]) -> Split {
+        let mut filter = ThinkFilter::default();
+        let mut all = Split::default();
+        for chunk in chunks {
+            let split = filter.push(chunk);
+            all.visible.push_str(&split.visible);
+            all.reasoning.push_str(&split.reasoning);
+        }
+        let rest = filter.finish();
+        all.visible.push_str(&rest.visible);
+        all.reasoning.push_str(&rest.reasoning);
+        all
+    }
+
+    #[test]
+    fn tags_split_across_chunks() {
+        let split = run(&["<thi", "nk>weigh the opt", "ions</th", "ink>\n\nUse a queue."]);
+        assert_eq!(split.reasoning, "weigh the options");
+        assert_eq!(split.visible, "Use a queue.");
+    }
+
+    #[test]
+    fn text_before_and_after_the_span_is_visible() {
+        let split = run(&["Short answer: ", "yes. <think>check", " the docs</think>", "  Details follow."]);
+        assert_eq!(split.reasoning, "check the docs");
+        assert_eq!(split.visible, "Short answer: yes. Details follow.");
+    }
+
+    #[test]
+    fn unterminated_span_is_reasoning_at_end_of_stream() {
+        let split = run(&["Hmm <think>still go", "ing</thi"]);
+        assert_eq!(split.reasoning, "still going</thi");
+        assert_eq!(split.visible, "Hmm ");
+    }
+
+    #[test]
+    fn tag_lookalike_is_released_once_it_stops_matching() {
+        let mut filter = ThinkFilter::default();
+        assert_eq!(filter.push("a <thi").visible, "a ");
+        assert_eq!(filter.push("s").visible, "<this");
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RedactionSettings {
+    pub enabled: bool,
+    pub emails: bool,
+    pub phone_numbers: bool,
+    pub api_keys: bool,
+    pub custom_patterns: Vec<String>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            emails: true,
+            phone_numbers: true,
+            api_keys: true,
+            custom_patterns: Vec::new(),
+        }
+    }
+}
+
+const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+const PHONE_PATTERN: &str = r"(?:\+\d{1,3}[\s.-]?)?\(?\b\d{3}\)?[\s.-]?\d{3}[\s.-]?\d{4}\b";
+const API_KEY_PATTERN: &str =
+    r"\b(?:sk-[A-Za-z0-9_-]{16,}|gh[pousr]_[A-Za-z0-9]{20,}|AKIA[0-9A-Z]{16}|xox[baprs]-[A-Za-z0-9-]{10,})\b";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Redaction {
+    pub placeholder: String,
+    pub original: String,
+}
+
+// Placeholder ↔ original mapping kept locally so the UI can show the real values
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RedactionMap {
+    pub entries: Vec<Redaction>,
+}
+
+impl RedactionMap {
+    fn placeholder_for(&mut self, label: &str, original: &str) -> String {
+        // Reuse placeholders so the model sees the same token for the same value across turns
+        if let Some(existing) = self.entries.iter().find(|r| r.original == original) {
+            return existing.placeholder.clone();
+        }
+
+        let index = self
+            .entries
+            .iter()
+            .filter(|r| r.placeholder.starts_with(&format!("[{}_", label)))
+            .count()
+            + 1;
+        let placeholder = format!("[{}_{}]", label, index);
+        self.entries.push(Redaction {
+            placeholder: placeholder.clone(),
+            original: original.to_string(),
+        });
+        placeholder
+    }
+
+    pub fn restore(&self, text: &str) -> String {
+        self.entries.iter().fold(text.to_string(), |acc, r| {
+            acc.replace(&r.placeholder, &r.original)
+        })
+    }
+}
+
+pub struct Redactor {
+    rules: Vec<(String, Regex)>,
+}
+
+impl Redactor {
+    pub fn new(settings: &RedactionSettings) -> Result<Self> {
+        let mut rules = Vec::new();
+
+        if settings.api_keys {
+            rules.push(("API_KEY".to_string(), Regex::new(API_KEY_PATTERN)?));
+        }
+        if settings.emails {
+            rules.push(("EMAIL".to_string(), Regex::new(EMAIL_PATTERN)?));
+        }
+        if settings.phone_numbers {
+            rules.push(("PHONE".to_string(), Regex::new(PHONE_PATTERN)?));
+        }
+        for pattern in &settings.custom_patterns {
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("Invalid redaction pattern: {}", pattern))?;
+            rules.push(("REDACTED".to_string(), regex));
+        }
+
+        Ok(Self { rules })
+    }
+
+    pub fn redact(&self, text: &str, map: &mut RedactionMap) -> String {
+        let mut output = text.to_string();
+        for (label, regex) in &self.rules {
+            output = regex
+                .replace_all(&output, |caps: &regex::Captures| {
+                    map.placeholder_for(label, &caps[0])
+                })
+                .into_owned();
+        }
+        output
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeDelta, TimeZone, Utc, Weekday};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::storage;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Reminder {
+    pub id: String,
+    pub text: String,
+    pub due: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub fired: bool,
+}
+
+pub struct ReminderStore {
+    path: PathBuf,
+    reminders: Vec<Reminder>,
+}
+
+impl ReminderStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("reminders.json");
+        let reminders = storage::load_json(&path);
+        Self { path, reminders }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.reminders)
+    }
+
+    pub fn add(&mut self, text: String, due: DateTime<Utc>) -> Result<Reminder> {
+        let reminder = Reminder {
+            id: Uuid::new_v4().to_string(),
+            text,
+            due,
+            created_at: Utc::now(),
+            fired: false,
+        };
+        self.reminders.push(reminder.clone());
+        self.save()?;
+        Ok(reminder)
+    }
+
+    pub fn list(&self) -> Vec<Reminder> {
+        let mut reminders = self.reminders.clone();
+        reminders.sort_by_key(|r| (r.fired, r.due));
+        reminders
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<bool> {
+        let before = self.reminders.len();
+        self.reminders.retain(|r| r.id != id);
+        let removed = self.reminders.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    // Marks due reminders as fired and returns them for notification
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Result<Vec<Reminder>> {
+        let mut due = Vec::new();
+        for reminder in self.reminders.iter_mut().filter(|r| !r.fired && r.due <= now) {
+            reminder.fired = true;
+            due.push(reminder.clone());
+        }
+        if !due.is_empty() {
+            self.save()?;
+        }
+        Ok(due)
+    }
+}
+
+// Default time of day when only a day is given ("Thursday", "tomorrow")
+const DEFAULT_HOUR: u32 = 9;
+const TONIGHT_HOUR: u32 = 20;
+
+fn parse_time(text: &str) -> Option<(String, Option<(u32, u32)>)> {
+    let time_re =
+        Regex::new(r"(?:^|\s)(?P<at>at\s+)?(?P<h>\d{1,2})(?::(?P<m>\d{2}))?\s*(?P<ampm>am|pm)?$").ok()?;
+    let Some(caps) = time_re.captures(text) else {
+        return Some((text.to_string(), None));
+    };
+
+    // A bare number is not a time unless it reads like one ("at 9", "9am", "9:30")
+    if caps.name("at").is_none() && caps.name("m").is_none() && caps.name("ampm").is_none() {
+        return Some((text.to_string(), None));
+    }
+
+    let mut hour: u32 = caps["h"].parse().ok()?;
+    let minute: u32 = caps.name("m").map_or(Some(0), |m| m.as_str().parse().ok())?;
+    match caps.name("ampm").map(|m| m.as_str()) {
+        Some("pm") if hour < 12 => hour += 12,
+        Some("am") if hour == 12 => hour = 0,
+        _ => {}
+    }
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let day_part = text[..caps.get(0)?.start()].trim().to_string();
+    Some((day_part, Some((hour, minute))))
+}
+
+fn next_weekday(today: NaiveDate, target: Weekday) -> NaiveDate {
+    let today_idx = today.weekday().num_days_from_monday() as i64;
+    let target_idx = target.num_days_from_monday() as i64;
+    let mut days_ahead = (target_idx - today_idx).rem_euclid(7);
+    if days_ahead == 0 {
+        days_ahead = 7;
+    }
+    today + Duration::days(days_ahead)
+}
+
+// Understands "today", "tonight", "tomorrow", weekdays, ISO dates,
+// "in N minutes/hours/days/weeks" and an optional "at 5pm" style time
+pub fn parse_when(when: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let when = when.trim().to_lowercase();
+    let when = when.strip_prefix("on ").unwrap_or(&when).trim();
+
+    let relative_re = Regex::new(r"^in\s+(\d+)\s+(minute|min|hour|hr|day|week)s?$").ok()?;
+    if let Some(caps) = relative_re.captures(when) {
+        let amount: i64 = caps[1].parse().ok()?;
+        // "in 99999999 weeks" is out of range; the plain constructors and `+` would panic
+        let offset = match &caps[2] {
+            "minute" | "min" => TimeDelta::try_minutes(amount),
+            "hour" | "hr" => TimeDelta::try_hours(amount),
+            "day" => TimeDelta::try_days(amount),
+            _ => TimeDelta::try_weeks(amount),
+        }?;
+        return now.checked_add_signed(offset);
+    }
+
+    let (day_part, time) = parse_time(when)?;
+    let today = now.date_naive();
+    let (date, default_hour) = match day_part.as_str() {
+        "" | "today" => (today, DEFAULT_HOUR),
+        "tonight" => (today, TONIGHT_HOUR),
+        "tomorrow" => (today + Duration::days(1), DEFAULT_HOUR),
+        other => {
+            let other = other.strip_prefix("next ").unwrap_or(other);
+            if let Ok(date) = NaiveDate::parse_from_str(other, "%Y-%m-%d") {
+                (date, DEFAULT_HOUR)
+            } else {
+                (next_weekday(today, other.parse::<Weekday>().ok()?), DEFAULT_HOUR)
+            }
+        }
+    };
+
+    let (hour, minute) = time.unwrap_or((default_hour, 0));
+    let due = Local
+        .from_local_datetime(&date.and_hms_opt(hour, minute, 0)?)
+        .single()?;
+
+    // "remind me at 8am to ..." said in the evening means tomorrow morning
+    if due <= now && day_part.is_empty() {
+        return due.checked_add_signed(TimeDelta::days(1));
+    }
+    Some(due)
+}
+
+// Extracts (due, what) from "remind me <when> to <what>" or "remind me to <what> <when>"
+pub fn parse_reminder(text: &str, now: DateTime<Local>) -> Option<(DateTime<Utc>, String)> {
+    let text = text.trim().trim_end_matches(['.', '!']);
+
+    let when_first = Regex::new(r"(?i)^(?:please\s+)?remind me\s+(.+?)\s+to\s+(.+)$").ok()?;
+    if let Some(caps) = when_first.captures(text) {
+        if let Some(due) = parse_when(&caps[1], now) {
+            return Some((due.with_timezone(&Utc), caps[2].trim().to_string()));
+        }
+    }
+
+    let what_first = Regex::new(
+        r"(?i)^(?:please\s+)?remind me to\s+(.+?)\s+((?:on|at|in|next)\s+.+|today|tonight|tomorrow(?:\s+.+)?|\d{4}-\d{2}-\d{2}(?:\s+.+)?)$",
+    )
+    .ok()?;
+    let caps = what_first.captures(text)?;
+    let due = parse_when(&caps[2], now)?;
+    Some((due.with_timezone(&Utc), caps[1].trim().to_string()))
+}
@@ -0,0 +1,56 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::ollama::ChatMessage;
+
+// Pause before each message at a fixed pace, and for messages without a timestamp
+const DEFAULT_DELAY: Duration = Duration::from_millis(800);
+// Long breaks in the original session are cut to this
+const MAX_DELAY: Duration = Duration::from_secs(10);
+const MAX_SPEED: f32 = 100.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplayMessage {
+    pub conversation_id: String,
+    pub index: usize,
+    pub total: usize,
+    pub message: ChatMessage,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplayFinished {
+    pub conversation_id: String,
+    pub replayed: usize,
+    pub cancelled: bool,
+}
+
+pub fn validate_speed(speed: f32) -> Result<()> {
+    if !speed.is_finite() || speed <= 0.0 || speed > MAX_SPEED {
+        bail!("speed must be above 0 and at most {}", MAX_SPEED);
+    }
+    Ok(())
+}
+
+// How long to wait before emitting each message; the first one goes out at once.
+// `original_pacing` follows the gaps between the messages' timestamps.
+pub fn delays(messages: &[ChatMessage], original_pacing: bool, speed: f32) -> Vec<Duration> {
+    let mut previous: Option<DateTime<Utc>> = None;
+    messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let gap = match (original_pacing, previous, message.created_at) {
+                (true, Some(previous), Some(created_at)) => (created_at - previous).to_std().unwrap_or_default(),
+                _ => DEFAULT_DELAY,
+            };
+            previous = message.created_at.or(previous);
+            if index == 0 {
+                Duration::ZERO
+            } else {
+                gap.min(MAX_DELAY).div_f32(speed)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,53 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+// Retention is enforced at startup and then on this interval while the app runs
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+// A century; anything longer is as good as never
+pub const MAX_DELETE_AFTER_DAYS: u32 = 36_500;
+
+// Off unless a number of days is set
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RetentionSettings {
+    // Conversations without activity for this many days are deleted, along with
+    // ratings of their answers
+    pub delete_after_days: Option<u32>,
+}
+
+impl RetentionSettings {
+    pub fn validate(&self) -> Result<()> {
+        match self.delete_after_days {
+            Some(0) => bail!("delete_after_days must be at least 1"),
+            Some(days) if days > MAX_DELETE_AFTER_DAYS => {
+                bail!("delete_after_days can be at most {}", MAX_DELETE_AFTER_DAYS)
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Anything last active before this goes; None when off, or for a setting so large
+    // that nothing could have expired
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let days = self.delete_after_days?;
+        now.checked_sub_signed(TimeDelta::try_days(days.into())?)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExpiredConversation {
+    pub conversation_id: String,
+    // None for a stored conversation that could not be loaded
+    pub name: Option<String>,
+    pub last_activity: DateTime<Utc>,
+}
+
+// What a retention pass removed, or would remove in a dry run
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RetentionReport {
+    pub cutoff: Option<DateTime<Utc>>,
+    pub conversations: Vec<ExpiredConversation>,
+    pub feedback: usize,
+}
@@ -0,0 +1,237 @@
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::LlmBackend;
+use crate::ollama::{ChatMessage, ChatRequest, OllamaClient};
+use crate::reasoning::Split;
+
+pub const BLOCKED_PLACEHOLDER: &str = "[Response blocked by content filter]";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SafetyAction {
+    Flag,
+    Block,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SafetyCategory {
+    pub name: String,
+    pub keywords: Vec<String>,
+    pub action: SafetyAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SafetySettings {
+    pub enabled: bool,
+    // Ask the model to classify responses that no keyword caught
+    pub use_classifier: bool,
+    pub classifier_model: Option<String>,
+    pub categories: Vec<SafetyCategory>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SafetyVerdict {
+    pub flagged: Vec<String>,
+    pub blocked: bool,
+}
+
+fn keyword_regex(keyword: &str) -> Option<Regex> {
+    RegexBuilder::new(&format!(r"\b{}\b", regex::escape(keyword)))
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+impl SafetySettings {
+    // Whether a reply can end up blocked, and so has to be checked before it is shown
+    pub fn can_block(&self) -> bool {
+        self.enabled && self.categories.iter().any(|c| c.action == SafetyAction::Block)
+    }
+
+    fn keyword_matches(&self, text: &str) -> Vec<String> {
+        self.categories
+            .iter()
+            .filter(|category| {
+                category
+                    .keywords
+                    .iter()
+                    .filter_map(|keyword| keyword_regex(keyword))
+                    .any(|regex| regex.is_match(text))
+            })
+            .map(|category| category.name.clone())
+            .collect()
+    }
+
+    async fn classify(
+        &self,
+        client: &dyn LlmBackend,
+        model: &str,
+        text: &str,
+        candidates: &[&SafetyCategory],
+    ) -> Result<Vec<String>> {
+        let names: Vec<&str> = candidates.iter().map(|c| c.name.as_str()).collect();
+        let prompt = format!(
+            "Classify the following text. Categories: {}.\n\
+             Reply only with a comma-separated list of the categories that apply, or NONE.\n\n\
+             Text:\n{}",
+            names.join(", "),
+            text
+        );
+
+        let request = ChatRequest {
+            model: self.classifier_model.clone().unwrap_or_else(|| model.to_string()),
+            messages: vec![OllamaClient::create_user_message(prompt)],
+            stream: false,
+            keep_alive: None,
+            options: None,
+            tools: None,
+            logprobs: None,
+            top_logprobs: None,
+        };
+        let reply: ChatMessage = client.chat(request).await?;
+        let reply = reply.content.to_lowercase();
+
+        Ok(names
+            .iter()
+            .filter(|name| reply.contains(&name.to_lowercase()))
+            .map(|name| name.to_string())
+            .collect())
+    }
+
+    pub async fn check(&self, client: &dyn LlmBackend, model: &str, text: &str) -> SafetyVerdict {
+        let mut flagged = self.keyword_matches(text);
+
+        if self.use_classifier {
+            let candidates: Vec<&SafetyCategory> = self
+                .categories
+                .iter()
+                .filter(|c| !flagged.contains(&c.name))
+                .collect();
+            if !candidates.is_empty() {
+                match self.classify(client, model, text, &candidates).await {
+                    Ok(matches) => flagged.extend(matches),
+                    Err(e) => eprintln!("Safety classifier failed: {:?}", e),
+                }
+            }
+        }
+
+        let blocked = self
+            .categories
+            .iter()
+            .any(|c| c.action == SafetyAction::Block && flagged.contains(&c.name));
+
+        SafetyVerdict { flagged, blocked }
+    }
+}
+
+// Holds a streaming reply back from the window while it may still be blocked. Checking
+// only after streaming would leave the blocked text on screen already.
+pub struct ReplyGate {
+    holding: bool,
+    held: Split,
+}
+
+impl ReplyGate {
+    pub fn new(settings: &SafetySettings) -> Self {
+        Self {
+            holding: settings.can_block(),
+            held: Split::default(),
+        }
+    }
+
+    pub fn is_holding(&self) -> bool {
+        self.holding
+    }
+
+    // What can be shown now; nothing while holding
+    pub fn pass(&mut self, split: Split) -> Split {
+        if !self.holding {
+            return split;
+        }
+        self.held.visible.push_str(&split.visible);
+        self.held.reasoning.push_str(&split.reasoning);
+        Split::default()
+    }
+
+    // The reply and any reasoning held with it, which is shown too once released
+    pub fn text_to_check(&self, reply: &str) -> String {
+        if self.held.reasoning.is_empty() {
+            return reply.to_string();
+        }
+        format!("{}\n\n{}", self.held.reasoning, reply)
+    }
+
+    // After the check: everything held, or only the placeholder for a blocked reply
+    pub fn release(&mut self, blocked: bool) -> Split {
+        let held = std::mem::take(&mut self.held);
+        if !self.holding {
+            return Split::default();
+        }
+        if blocked {
+            return Split {
+                visible: BLOCKED_PLACEHOLDER.to_string(),
+                reasoning: String::new(),
+            };
+        }
+        held
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocking() -> SafetySettings {
+        SafetySettings {
+            enabled: true,
+            categories: vec![SafetyCategory {
+                name: "violence".to_string(),
+                keywords: vec!["weapon".to_string()],
+                action: SafetyAction::Block,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn split(visible: &str) -> Split {
+        Split {
+            visible: visible.to_string(),
+            reasoning: String::new(),
+        }
+    }
+
+    #[test]
+    fn blocked_reply_is_never_passed_on() {
+        let settings = blocking();
+        let mut gate = ReplyGate::new(&settings);
+        let mut shown = String::new();
+        for chunk in ["How to build ", "a weapon", " at home"] {
+            shown.push_str(&gate.pass(split(chunk)).visible);
+        }
+        assert!(shown.is_empty());
+
+        let blocked = !settings.keyword_matches("How to build a weapon at home").is_empty();
+        assert!(blocked);
+        assert_eq!(gate.release(blocked).visible, BLOCKED_PLACEHOLDER);
+    }
+
+    #[test]
+    fn passing_reply_is_released_whole() {
+        let mut gate = ReplyGate::new(&blocking());
+        assert!(gate.pass(split("Hello ")).visible.is_empty());
+        assert!(gate.pass(split("there")).visible.is_empty());
+        assert_eq!(gate.release(false).visible, "Hello there");
+    }
+
+    #[test]
+    fn flag_only_settings_stream_as_usual() {
+        let mut settings = blocking();
+        settings.categories[0].action = SafetyAction::Flag;
+        let mut gate = ReplyGate::new(&settings);
+        assert_eq!(gate.pass(split("weapon")).visible, "weapon");
+        assert!(gate.release(false).visible.is_empty());
+    }
+}
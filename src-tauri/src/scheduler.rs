@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+// How often the background task checks for due messages
+pub const TICK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScheduledMessage {
+    pub id: String,
+    pub conversation_id: String,
+    pub text: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+pub struct Scheduler {
+    messages: Vec<ScheduledMessage>,
+}
+
+impl Scheduler {
+    pub fn add(&mut self, conversation_id: String, text: String, at: DateTime<Utc>) -> ScheduledMessage {
+        let message = ScheduledMessage {
+            id: Uuid::new_v4().to_string(),
+            conversation_id,
+            text,
+            at,
+        };
+        self.messages.push(message.clone());
+        message
+    }
+
+    pub fn list(&self) -> Vec<ScheduledMessage> {
+        let mut messages = self.messages.clone();
+        messages.sort_by_key(|m| m.at);
+        messages
+    }
+
+    pub fn cancel(&mut self, id: &str) -> bool {
+        let before = self.messages.len();
+        self.messages.retain(|m| m.id != id);
+        self.messages.len() != before
+    }
+
+    pub fn cancel_conversation(&mut self, conversation_id: &str) {
+        self.messages.retain(|m| m.conversation_id != conversation_id);
+    }
+
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<ScheduledMessage> {
+        let (mut due, pending): (Vec<_>, Vec<_>) =
+            self.messages.drain(..).partition(|m| m.at <= now);
+        self.messages = pending;
+        due.sort_by_key(|m| m.at);
+        due
+    }
+}
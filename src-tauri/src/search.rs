@@ -1,11 +1,142 @@
 use anyhow::Result;
+use futures_util::StreamExt;
+use reqwest::redirect::Policy;
 use reqwest::Client;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use url::Url;
 
+use crate::doh::DohResolver;
+use crate::ollama;
+use crate::operators::ParsedQuery;
+use crate::settings::Settings;
+use crate::storage;
+
+// Hard limits applied to every page fetched for extraction
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExtractionLimits {
+    pub max_bytes: usize,
+    pub page_timeout_secs: u64,
+    pub max_redirects: usize,
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 2 * 1024 * 1024,
+            page_timeout_secs: 15,
+            max_redirects: 5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectorKind {
+    Paywall,
+    Content,
+}
+
+// CSS selectors used by extraction, kept in a user-editable file so sites can be fixed
+// without a new release. Content selectors are tried in order.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExtractionSelectors {
+    pub paywall: Vec<String>,
+    pub content: Vec<String>,
+}
+
+impl Default for ExtractionSelectors {
+    fn default() -> Self {
+        let to_strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            paywall: to_strings(&[
+                ".paywall", "#paywall", ".subscribe-wall",
+                ".subscription-required", ".paid-content",
+            ]),
+            content: to_strings(&[
+                "article", ".article-content", ".post-content",
+                "main", "[role='main']", ".content",
+            ]),
+        }
+    }
+}
+
+impl ExtractionSelectors {
+    fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join("extraction_selectors.json")
+    }
+
+    // Writes the shipped defaults on first run so there is a file to edit
+    pub fn load(data_dir: &Path) -> Self {
+        let path = Self::path(data_dir);
+        if !path.exists() {
+            let defaults = Self::default();
+            if let Err(e) = defaults.save(data_dir) {
+                eprintln!("Failed to write default extraction selectors: {:?}", e);
+            }
+            return defaults;
+        }
+        storage::load_plain_json(&path)
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        storage::save_plain_json(&Self::path(data_dir), self)
+    }
+
+    fn list_mut(&mut self, kind: SelectorKind) -> &mut Vec<String> {
+        match kind {
+            SelectorKind::Paywall => &mut self.paywall,
+            SelectorKind::Content => &mut self.content,
+        }
+    }
+
+    pub fn add(&mut self, kind: SelectorKind, selector: &str) -> Result<()> {
+        let selector = selector.trim();
+        if Selector::parse(selector).is_err() {
+            anyhow::bail!("Invalid CSS selector: {}", selector);
+        }
+        let list = self.list_mut(kind);
+        if !list.iter().any(|s| s == selector) {
+            list.push(selector.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, kind: SelectorKind, selector: &str) -> bool {
+        let list = self.list_mut(kind);
+        let before = list.len();
+        list.retain(|s| s != selector.trim());
+        list.len() != before
+    }
+}
+
+fn limit_tripped(url: &str, reason: &str) {
+    eprintln!("Extraction limit tripped for {}: {}", url, reason);
+}
+
+fn is_http(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+fn mime_type(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or_default().trim()
+}
+
+fn is_text_content_type(content_type: &str) -> bool {
+    let mime = mime_type(content_type);
+    mime.starts_with("text/") || mime == "application/xhtml+xml"
+}
+
+fn is_image_content_type(content_type: &str) -> bool {
+    mime_type(content_type).starts_with("image/")
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchResult {
     pub url: String,
@@ -14,116 +145,452 @@ pub struct SearchResult {
     pub reading_time: u32,
     pub favicon_url: Option<String>,
     pub is_paywall: bool,
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+}
+
+// The part kept with an answer as one of its sources
+impl From<SearchResult> for ollama::SearchResult {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            url: result.url,
+            title: result.title,
+            summary: result.summary,
+            reading_time: result.reading_time,
+            favicon_url: result.favicon_url,
+        }
+    }
+}
+
+// What enrichment learns from a fetched page
+struct ExtractedPage {
+    // None when the page is paywalled
+    content: Option<String>,
+    icon_url: Option<String>,
+    thumbnail_url: Option<String>,
+}
+
+// Preview images smaller than this, or more lopsided than the ratio, are usually
+// tracking pixels or banners rather than useful thumbnails
+const MIN_THUMBNAIL_SIZE: u32 = 100;
+const MAX_THUMBNAIL_ASPECT: f64 = 4.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchProvider {
+    #[default]
+    DuckDuckGo,
+    Wikipedia,
+    GitHub,
+    // Needs a Brave Search API key; used instead of DuckDuckGo once one is stored
+    Brave,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SearchRequest {
     pub query: String,
     pub max_results: usize,
+    #[serde(default)]
+    pub provider: SearchProvider,
+    // Filled in from the keychain for providers that need one, never sent by the UI
+    #[serde(skip)]
+    pub api_key: Option<String>,
+}
+
+// Strip the <span class="searchmatch"> and <strong> highlighting from API snippets
+fn strip_tags(html: &str) -> String {
+    Html::parse_fragment(html)
+        .root_element()
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn api_result(url: String, title: String, summary: String) -> SearchResult {
+    SearchResult {
+        url,
+        title,
+        summary,
+        reading_time: 0,
+        favicon_url: None,
+        is_paywall: false,
+        thumbnail_url: None,
+    }
 }
 
 #[derive(Clone)]
 pub struct SearchClient {
     client: Client,
     base_url: String,
+    limits: ExtractionLimits,
+    selectors: Arc<ExtractionSelectors>,
 }
 
 impl SearchClient {
-    pub fn new() -> Self {
+    pub fn new(settings: &Settings, selectors: &ExtractionSelectors) -> Self {
+        let limits = settings.extraction.clone();
+        let max_redirects = limits.max_redirects;
+
+        // Never follow a redirect off http(s), and never follow too many
+        let redirect_policy = Policy::custom(move |attempt| {
+            if !is_http(attempt.url()) {
+                limit_tripped(attempt.url().as_str(), "redirect to non-http(s) scheme");
+                attempt.stop()
+            } else if attempt.previous().len() > max_redirects {
+                limit_tripped(attempt.url().as_str(), "too many redirects");
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        });
+
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .redirect(redirect_policy);
+        if settings.doh.enabled {
+            builder = builder.dns_resolver(Arc::new(DohResolver::new(settings.doh.endpoint())));
+        }
+
         Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .unwrap(),
+            client: builder.build().unwrap(),
             base_url: "https://duckduckgo.com/html".to_string(),
+            limits,
+            selectors: Arc::new(selectors.clone()),
         }
     }
 
-    async fn extract_content(&self, url: &str) -> Result<Option<String>> {
-        let response = self.client.get(url).send().await?;
-        if !response.status().is_success() {
-            return Ok(None);
+    pub fn set_selectors(&mut self, selectors: &ExtractionSelectors) {
+        self.selectors = Arc::new(selectors.clone());
+    }
+
+    // Download a URL, enforcing the scheme, content type, byte and time limits.
+    // Returns the (possibly truncated) body, its content type and whether it was truncated.
+    async fn fetch_limited(
+        &self,
+        url: &str,
+        accept_content_type: fn(&str) -> bool,
+    ) -> Result<Option<(Vec<u8>, String, bool)>> {
+        match Url::parse(url) {
+            Ok(parsed) if is_http(&parsed) => {}
+            _ => {
+                limit_tripped(url, "not an http(s) URL");
+                return Ok(None);
+            }
+        }
+
+        let max_bytes = self.limits.max_bytes;
+        let fetch = async {
+            let response = self.client.get(url).send().await?;
+            if !response.status().is_success() {
+                return Ok(None);
+            }
+
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            if !accept_content_type(&content_type) {
+                limit_tripped(url, &format!("unexpected content type '{}'", content_type));
+                return Ok(None);
+            }
+
+            let mut body = Vec::new();
+            let mut truncated = false;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                let remaining = max_bytes.saturating_sub(body.len());
+                if chunk.len() > remaining {
+                    body.extend_from_slice(&chunk[..remaining]);
+                    limit_tripped(url, &format!("body truncated at {} bytes", max_bytes));
+                    truncated = true;
+                    break;
+                }
+                body.extend_from_slice(&chunk);
+            }
+
+            Ok(Some((body, content_type, truncated)))
+        };
+
+        let timeout = Duration::from_secs(self.limits.page_timeout_secs);
+        match tokio::time::timeout(timeout, fetch).await {
+            Ok(result) => result,
+            Err(_) => {
+                limit_tripped(url, &format!("exceeded {}s page time limit", timeout.as_secs()));
+                Ok(None)
+            }
         }
+    }
+
+    // Pages are only ever processed as text
+    pub async fn fetch_text(&self, url: &str) -> Result<Option<String>> {
+        let page = self.fetch_limited(url, is_text_content_type).await?;
+        Ok(page.map(|(body, _, _)| String::from_utf8_lossy(&body).into_owned()))
+    }
+
+    // Images for offline exports; a truncated image is useless, so those are dropped
+    pub async fn fetch_image(&self, url: &str) -> Result<Option<(Vec<u8>, String)>> {
+        let image = self.fetch_limited(url, is_image_content_type).await?;
+        Ok(image.and_then(|(body, content_type, truncated)| {
+            (!truncated).then_some((body, content_type))
+        }))
+    }
 
-        let text = response.text().await?;
+    async fn extract_content(&self, url: &str) -> Result<Option<ExtractedPage>> {
+        let Some(text) = self.fetch_text(url).await? else {
+            return Ok(None);
+        };
+        let page_url = Url::parse(url).ok();
+        let selectors = self.selectors.clone();
         
         // Move HTML parsing to a blocking task to avoid Send issues
-        let content = tokio::task::spawn_blocking(move || {
+        let page = tokio::task::spawn_blocking(move || {
             let document = Html::parse_document(&text);
-            
-            // Define selectors here to avoid Send issues
-            let paywall_selectors = [
-                ".paywall", "#paywall", ".subscribe-wall",
-                ".subscription-required", ".paid-content",
-            ];
-            
-            for selector in paywall_selectors {
-                if let Ok(sel) = Selector::parse(selector) {
-                    if document.select(&sel).next().is_some() {
-                        return None;
-                    }
-                }
+            ExtractedPage {
+                content: Self::find_content(&document, &selectors),
+                icon_url: page_url.as_ref().and_then(|base| Self::find_icon_link(&document, base)),
+                thumbnail_url: page_url.as_ref().and_then(|base| Self::find_thumbnail(&document, base)),
             }
+        }).await.ok();
 
-            let content_selectors = [
-                "article", ".article-content", ".post-content",
-                "main", "[role='main']", ".content",
-            ];
+        Ok(page)
+    }
 
-            for selector in content_selectors {
-                if let Ok(sel) = Selector::parse(selector) {
-                    if let Some(element) = document.select(&sel).next() {
-                        return Some(element.text().collect::<Vec<_>>().join(" "));
-                    }
+    // Main text of the page, or None if it sits behind a paywall
+    fn find_content(document: &Html, selectors: &ExtractionSelectors) -> Option<String> {
+        for selector in &selectors.paywall {
+            if let Ok(sel) = Selector::parse(selector) {
+                if document.select(&sel).next().is_some() {
+                    return None;
                 }
             }
+        }
 
-            // Fallback
-            Some(document.select(&Selector::parse("body").unwrap_or_else(|_| Selector::parse("html").unwrap()))
-                .next()
-                .map(|element| element.text().collect::<Vec<_>>().join(" "))
-                .unwrap_or_default())
-        }).await.unwrap_or(None);
+        for selector in &selectors.content {
+            if let Ok(sel) = Selector::parse(selector) {
+                if let Some(element) = document.select(&sel).next() {
+                    return Some(element.text().collect::<Vec<_>>().join(" "));
+                }
+            }
+        }
 
-        Ok(content)
+        // Fallback
+        Some(document.select(&Selector::parse("body").unwrap_or_else(|_| Selector::parse("html").unwrap()))
+            .next()
+            .map(|element| element.text().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default())
     }
 
-    async fn process_search_results(&self, html: String, max_results: usize) -> Vec<SearchResult> {
-        let document = Html::parse_document(&html);
-        let mut results = Vec::new();
-        
-        // Move selector parsing outside of async context
-        let result_selector = Selector::parse(".result").unwrap();
-        let link_selector = Selector::parse(".result__a").unwrap();
-
-        for result in document.select(&result_selector).take(max_results) {
-            if let Some(link) = result.select(&link_selector).next() {
-                let url = link.value().attr("href").unwrap_or_default();
-                let title = link.text().collect::<String>();
-
-                if let Ok(Some(content)) = self.extract_content(url).await {
-                    let reading_time = (content.split_whitespace().count() as u32 / 100).max(1);
-                    let summary = Self::generate_summary(&content);
-                    let favicon_url = Self::get_favicon_url(url);
-
-                    results.push(SearchResult {
-                        url: url.to_string(),
-                        title,
-                        summary,
-                        reading_time,
-                        favicon_url,
-                        is_paywall: false,
-                    });
+    // <link rel="icon"> (or shortcut/apple-touch variants), resolved against the page URL
+    fn find_icon_link(document: &Html, base: &Url) -> Option<String> {
+        let icon_selectors = [
+            "link[rel~='icon']",
+            "link[rel='apple-touch-icon']",
+        ];
+
+        icon_selectors
+            .iter()
+            .filter_map(|selector| Selector::parse(selector).ok())
+            .find_map(|sel| {
+                document
+                    .select(&sel)
+                    .filter_map(|link| link.value().attr("href"))
+                    .find_map(|href| base.join(href.trim()).ok())
+            })
+            .filter(is_http)
+            .map(|url| url.to_string())
+    }
+
+    fn meta_content<'a>(document: &'a Html, selector: &str) -> Option<&'a str> {
+        let sel = Selector::parse(selector).ok()?;
+        document
+            .select(&sel)
+            .filter_map(|meta| meta.value().attr("content"))
+            .map(str::trim)
+            .find(|content| !content.is_empty())
+    }
+
+    // og:image / twitter:image, skipped when the declared size is implausible
+    fn find_thumbnail(document: &Html, base: &Url) -> Option<String> {
+        let width = Self::meta_content(document, "meta[property='og:image:width']")
+            .and_then(|w| w.parse::<u32>().ok());
+        let height = Self::meta_content(document, "meta[property='og:image:height']")
+            .and_then(|h| h.parse::<u32>().ok());
+        if let (Some(width), Some(height)) = (width, height) {
+            let aspect = width.max(height) as f64 / width.min(height).max(1) as f64;
+            if width < MIN_THUMBNAIL_SIZE || height < MIN_THUMBNAIL_SIZE || aspect > MAX_THUMBNAIL_ASPECT {
+                return None;
+            }
+        }
+
+        let image_selectors = [
+            "meta[property='og:image']",
+            "meta[property='og:image:url']",
+            "meta[name='twitter:image']",
+            "meta[property='twitter:image']",
+            "meta[name='twitter:image:src']",
+        ];
+
+        image_selectors
+            .iter()
+            .filter_map(|selector| Self::meta_content(document, selector))
+            .find_map(|content| base.join(content).ok())
+            .filter(is_http)
+            .map(|url| url.to_string())
+    }
+
+    // Fetch the page behind a result and fill in summary, reading time and favicon
+    pub async fn enrich_result(&self, mut result: SearchResult) -> SearchResult {
+        let mut icon_url = None;
+        match self.extract_content(&result.url).await {
+            Ok(Some(page)) => {
+                match page.content {
+                    Some(content) => {
+                        result.reading_time = (content.split_whitespace().count() as u32 / 100).max(1);
+                        result.summary = Self::generate_summary(&content);
+                    }
+                    None => result.is_paywall = true,
                 }
+                icon_url = page.icon_url;
+                result.thumbnail_url = page.thumbnail_url;
             }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to enrich {}: {:?}", result.url, e),
         }
-        
-        results
+        result.favicon_url = icon_url.or_else(|| Self::fallback_favicon_url(&result.url));
+        result
     }
 
     pub async fn search_stream(&self, request: SearchRequest) -> Result<mpsc::Receiver<SearchResult>> {
+        match request.provider {
+            SearchProvider::DuckDuckGo => self.search_duckduckgo(request).await,
+            SearchProvider::Wikipedia | SearchProvider::GitHub | SearchProvider::Brave => {
+                let parsed = ParsedQuery::parse(&request.query);
+                let results = match request.provider {
+                    SearchProvider::Wikipedia => self.search_wikipedia(&parsed, request.max_results).await?,
+                    SearchProvider::Brave => {
+                        let Some(key) = request.api_key.as_deref() else {
+                            anyhow::bail!("No Brave Search API key is stored");
+                        };
+                        self.search_brave(&parsed, request.max_results, key).await?
+                    }
+                    _ => self.search_github(&parsed, request.max_results).await?,
+                };
+
+                let (tx, rx) = mpsc::channel(100);
+                for result in results
+                    .into_iter()
+                    .filter(|r| parsed.matches(&r.url, &r.title))
+                    .take(request.max_results)
+                {
+                    if tx.send(result).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(rx)
+            }
+        }
+    }
+
+    // Plain terms and phrases only; per-site and file type operators mean nothing here
+    fn api_query(parsed: &ParsedQuery) -> String {
+        let mut parts = parsed.terms.clone();
+        parts.extend(parsed.phrases.iter().map(|p| format!("\"{}\"", p)));
+        parts.join(" ")
+    }
+
+    async fn search_wikipedia(&self, parsed: &ParsedQuery, max_results: usize) -> Result<Vec<SearchResult>> {
+        let limit = max_results.to_string();
+        let response: serde_json::Value = self
+            .client
+            .get("https://en.wikipedia.org/w/api.php")
+            .header(reqwest::header::USER_AGENT, "SoFragmentUI")
+            .query(&[
+                ("action", "query"),
+                ("list", "search"),
+                ("format", "json"),
+                ("srlimit", limit.as_str()),
+                ("srsearch", Self::api_query(parsed).as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let hits = response["query"]["search"].as_array().cloned().unwrap_or_default();
+        Ok(hits
+            .iter()
+            .filter_map(|hit| {
+                let title = hit["title"].as_str()?;
+                let mut url = Url::parse("https://en.wikipedia.org/wiki/").ok()?;
+                url.path_segments_mut().ok()?.pop().push(&title.replace(' ', "_"));
+                let summary = strip_tags(hit["snippet"].as_str().unwrap_or_default());
+                Some(api_result(url.to_string(), title.to_string(), summary))
+            })
+            .collect())
+    }
+
+    async fn search_github(&self, parsed: &ParsedQuery, max_results: usize) -> Result<Vec<SearchResult>> {
+        let per_page = max_results.to_string();
+        let response: serde_json::Value = self
+            .client
+            .get("https://api.github.com/search/repositories")
+            .header(reqwest::header::USER_AGENT, "SoFragmentUI")
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .query(&[("q", Self::api_query(parsed).as_str()), ("per_page", per_page.as_str())])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let items = response["items"].as_array().cloned().unwrap_or_default();
+        Ok(items
+            .iter()
+            .filter_map(|item| {
+                let url = item["html_url"].as_str()?.to_string();
+                let title = item["full_name"].as_str()?.to_string();
+                let summary = item["description"].as_str().unwrap_or_default().to_string();
+                Some(api_result(url, title, summary))
+            })
+            .collect())
+    }
+
+    // Brave understands the same operators as DuckDuckGo
+    async fn search_brave(&self, parsed: &ParsedQuery, max_results: usize, key: &str) -> Result<Vec<SearchResult>> {
+        let count = max_results.to_string();
+        let response: serde_json::Value = self
+            .client
+            .get("https://api.search.brave.com/res/v1/web/search")
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header("X-Subscription-Token", key)
+            .query(&[("q", parsed.to_duckduckgo().as_str()), ("count", count.as_str())])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let results = response["web"]["results"].as_array().cloned().unwrap_or_default();
+        Ok(results
+            .iter()
+            .filter_map(|result| {
+                let url = result["url"].as_str()?.to_string();
+                let title = strip_tags(result["title"].as_str()?);
+                let summary = strip_tags(result["description"].as_str().unwrap_or_default());
+                Some(api_result(url, title, summary))
+            })
+            .collect())
+    }
+
+    async fn search_duckduckgo(&self, request: SearchRequest) -> Result<mpsc::Receiver<SearchResult>> {
         let (tx, rx) = mpsc::channel(100);
-        let query = request.query.clone();
+        let parsed = ParsedQuery::parse(&request.query);
+        let query = parsed.to_duckduckgo();
         let max_results = request.max_results;
         let client = self.client.clone();
         let base_url = self.base_url.clone();
@@ -146,23 +613,33 @@ impl SearchClient {
             let document = Html::parse_document(&response);
             if let Ok(result_selector) = Selector::parse(".result") {
                 if let Ok(link_selector) = Selector::parse(".result__a") {
-                    for result in document.select(&result_selector).take(max_results) {
+                    let mut sent = 0;
+                    for result in document.select(&result_selector) {
+                        if sent >= max_results {
+                            break;
+                        }
                         if let Some(link) = result.select(&link_selector).next() {
-                            if let Some(url) = link.value().attr("href") {
+                            if let Some(href) = link.value().attr("href") {
                                 let title = link.text().collect::<String>();
+                                let url = Self::resolve_result_url(href);
+                                if !parsed.matches(&url, &title) {
+                                    continue;
+                                }
                                 let search_result = SearchResult {
-                                    url: url.to_string(),
+                                    url,
                                     title,
                                     summary: String::new(),
                                     reading_time: 0,
                                     favicon_url: None,
                                     is_paywall: false,
+                                    thumbnail_url: None,
                                 };
                                 
                                 // Use blocking_send since we're in a blocking task
                                 if tx_clone.blocking_send(search_result).is_err() {
                                     break;
                                 }
+                                sent += 1;
                             }
                         }
                     }
@@ -173,14 +650,30 @@ impl SearchClient {
         Ok(rx)
     }
 
-    fn get_favicon_url(url: &str) -> Option<String> {
-        Url::parse(url).ok().map(|parsed_url| {
-            format!(
-                "{}://{}/favicon.ico",
-                parsed_url.scheme(),
-                parsed_url.host_str().unwrap_or_default()
-            )
-        })
+    // DuckDuckGo's HTML results link through a redirect: //duckduckgo.com/l/?uddg=<target>
+    fn resolve_result_url(href: &str) -> String {
+        let absolute = if href.starts_with("//") {
+            format!("https:{}", href)
+        } else {
+            href.to_string()
+        };
+
+        Url::parse(&absolute)
+            .ok()
+            .and_then(|url| {
+                url.query_pairs()
+                    .find(|(key, _)| key == "uddg")
+                    .map(|(_, target)| target.into_owned())
+            })
+            .unwrap_or(absolute)
+    }
+
+    // Guessing /favicon.ico 404s for many sites; DuckDuckGo's icon service always answers
+    fn fallback_favicon_url(url: &str) -> Option<String> {
+        Url::parse(url)
+            .ok()
+            .and_then(|parsed_url| parsed_url.host_str().map(str::to_string))
+            .map(|host| format!("https://icons.duckduckgo.com/ip3/{}.ico", host))
     }
 
     fn generate_summary(content: &str) -> String {
@@ -0,0 +1,203 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::analytics::{period_days, top_counts, NamedCount};
+use crate::search::SearchProvider;
+use crate::storage;
+
+// Queries grouped into one topic when their embeddings are at least this similar
+const TOPIC_SIMILARITY: f32 = 0.7;
+// Only the most frequent distinct queries are embedded for topic detection
+pub const MAX_TOPIC_QUERIES: usize = 200;
+const MAX_TOPICS: usize = 10;
+const MAX_DEAD_ENDS: usize = 20;
+
+// Like analytics.json, search history never leaves the local data dir
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchRecord {
+    pub query: String,
+    pub at: DateTime<Utc>,
+    pub provider: SearchProvider,
+    pub result_urls: Vec<String>,
+    // Results opened, bookmarked or snapshotted after the search
+    pub used_urls: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TopicCluster {
+    pub label: String,
+    pub queries: Vec<String>,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeadEndQuery {
+    pub query: String,
+    pub at: DateTime<Utc>,
+    pub result_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchAnalytics {
+    pub enabled: bool,
+    pub period: String,
+    pub total_searches: usize,
+    pub top_queries: Vec<NamedCount>,
+    pub top_domains: Vec<NamedCount>,
+    pub topics: Vec<TopicCluster>,
+    // Set when topics could not be computed, e.g. the embedding model is not installed
+    pub topics_error: Option<String>,
+    pub dead_end_queries: Vec<DeadEndQuery>,
+}
+
+fn domain(url: &str) -> Option<String> {
+    let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+}
+
+fn normalize_query(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+pub struct SearchHistory {
+    path: PathBuf,
+    records: Vec<SearchRecord>,
+}
+
+impl SearchHistory {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("search_history.json");
+        let records = storage::load_json(&path);
+        Self { path, records }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.records)
+    }
+
+    pub fn record(&mut self, query: &str, provider: SearchProvider, result_urls: Vec<String>) -> Result<()> {
+        self.records.push(SearchRecord {
+            query: query.trim().to_string(),
+            at: Utc::now(),
+            provider,
+            result_urls,
+            used_urls: Vec::new(),
+        });
+        self.save()
+    }
+
+    // Credits the most recent search that returned this URL
+    pub fn mark_used(&mut self, url: &str) -> Result<()> {
+        let Some(record) = self
+            .records
+            .iter_mut()
+            .rev()
+            .find(|r| r.result_urls.iter().any(|u| u == url))
+        else {
+            return Ok(());
+        };
+        if !record.used_urls.iter().any(|u| u == url) {
+            record.used_urls.push(url.to_string());
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.records.clear();
+        self.save()
+    }
+
+    // Everything except topics, plus the distinct queries (most frequent first) to embed for them
+    pub fn report(&self, period: &str, enabled: bool) -> Result<(SearchAnalytics, Vec<NamedCount>)> {
+        let cutoff = period_days(period)?.map(|days| Utc::now() - Duration::days(days));
+        let records: Vec<&SearchRecord> = self
+            .records
+            .iter()
+            .filter(|r| cutoff.is_none_or(|cutoff| r.at >= cutoff))
+            .collect();
+
+        let mut queries: HashMap<String, u64> = HashMap::new();
+        let mut domains: HashMap<String, u64> = HashMap::new();
+        for record in &records {
+            *queries.entry(normalize_query(&record.query)).or_default() += 1;
+            for domain in record.used_urls.iter().filter_map(|url| domain(url)) {
+                *domains.entry(domain).or_default() += 1;
+            }
+        }
+
+        let mut dead_end_queries: Vec<DeadEndQuery> = records
+            .iter()
+            .rev()
+            .filter(|r| r.used_urls.is_empty())
+            .map(|r| DeadEndQuery {
+                query: r.query.clone(),
+                at: r.at,
+                result_count: r.result_urls.len(),
+            })
+            .collect();
+        dead_end_queries.truncate(MAX_DEAD_ENDS);
+
+        let distinct = top_counts(queries, MAX_TOPIC_QUERIES);
+        let report = SearchAnalytics {
+            enabled,
+            period: period.to_string(),
+            total_searches: records.len(),
+            top_queries: distinct.iter().take(MAX_TOPICS).cloned().collect(),
+            top_domains: top_counts(domains, MAX_TOPICS),
+            topics: Vec::new(),
+            topics_error: None,
+            dead_end_queries,
+        };
+        Ok((report, distinct))
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+// Greedy clustering: queries arrive most frequent first, so each topic is seeded
+// (and labelled) by its most searched query
+pub fn cluster_topics(queries: &[NamedCount], embeddings: &[Vec<f32>]) -> Vec<TopicCluster> {
+    let mut seeds: Vec<&[f32]> = Vec::new();
+    let mut topics: Vec<TopicCluster> = Vec::new();
+
+    for (query, embedding) in queries.iter().zip(embeddings) {
+        let nearest = seeds
+            .iter()
+            .map(|seed| cosine_similarity(seed, embedding))
+            .enumerate()
+            .filter(|(_, similarity)| *similarity >= TOPIC_SIMILARITY)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match nearest {
+            Some((index, _)) => {
+                topics[index].queries.push(query.name.clone());
+                topics[index].count += query.count;
+            }
+            None => {
+                seeds.push(embedding);
+                topics.push(TopicCluster {
+                    label: query.name.clone(),
+                    queries: vec![query.name.clone()],
+                    count: query.count,
+                });
+            }
+        }
+    }
+
+    topics.sort_by_key(|t| std::cmp::Reverse(t.count));
+    topics.truncate(MAX_TOPICS);
+    topics
+}
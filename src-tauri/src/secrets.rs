@@ -0,0 +1,64 @@
+use anyhow::{bail, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use keyring::Entry;
+
+// Secrets live in the OS keychain / secret service, never in settings.json
+const SERVICE: &str = "com.sofragmentuitauri.app";
+// Key for encrypted storage; never readable or writable through the secret commands
+const STORAGE_KEY: &str = "storage_encryption_key";
+
+fn entry(name: &str) -> Result<Entry> {
+    Ok(Entry::new(SERVICE, name)?)
+}
+
+// For secret names that come from the frontend
+pub fn check_name(name: &str) -> Result<()> {
+    if name == STORAGE_KEY {
+        bail!("{} is reserved", name);
+    }
+    Ok(())
+}
+
+pub fn set_secret(name: &str, value: &str) -> Result<()> {
+    entry(name)?.set_password(value)?;
+    Ok(())
+}
+
+pub fn get_secret(name: &str) -> Result<Option<String>> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn has_secret(name: &str) -> Result<bool> {
+    Ok(get_secret(name)?.is_some())
+}
+
+pub fn delete_secret(name: &str) -> Result<()> {
+    match entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn set_storage_key(key: &[u8]) -> Result<()> {
+    set_secret(STORAGE_KEY, &STANDARD.encode(key))
+}
+
+pub fn get_storage_key() -> Result<Option<Vec<u8>>> {
+    get_secret(STORAGE_KEY)?.map(|key| Ok(STANDARD.decode(key)?)).transpose()
+}
+
+// Only enough of the secret for the user to recognise which key is stored
+pub fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", head, tail)
+}
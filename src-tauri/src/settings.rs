@@ -0,0 +1,63 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::backend::BackendSettings;
+use crate::developer::DeveloperSettings;
+use crate::doh::DohSettings;
+use crate::launcher::AutostartSettings;
+use crate::ollama::{HttpSettings, RetrySettings};
+use crate::power::PowerSettings;
+use crate::redact::RedactionSettings;
+use crate::retention::RetentionSettings;
+use crate::safety::SafetySettings;
+use crate::search::ExtractionLimits;
+use crate::storage;
+use crate::tls::TlsSettings;
+use crate::tools::ToolSettings;
+use crate::trash::TrashSettings;
+
+// User-facing settings persisted in the app data dir.
+// Every field needs a default so older settings files keep loading.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub analytics_enabled: bool,
+    // Substituted for {{user_name}} in prompts; empty uses the OS account name
+    pub user_name: String,
+    pub redaction: RedactionSettings,
+    pub tls: TlsSettings,
+    pub safety: SafetySettings,
+    pub doh: DohSettings,
+    // Ask before fetching any third-party page during enrichment
+    pub ask_before_fetching: bool,
+    pub extraction: ExtractionLimits,
+    pub power: PowerSettings,
+    // Where Ollama runs; empty means http://localhost:11434
+    pub ollama_url: String,
+    pub autostart: AutostartSettings,
+    // Which server chat goes to; model management always talks to Ollama
+    pub backend: BackendSettings,
+    pub retry: RetrySettings,
+    pub http: HttpSettings,
+    pub tools: ToolSettings,
+    // Sent with every chat request unless the conversation sets its own
+    pub stop_sequences: Vec<String>,
+    pub developer: DeveloperSettings,
+    pub trash: TrashSettings,
+    pub retention: RetentionSettings,
+}
+
+impl Settings {
+    fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join("settings.json")
+    }
+
+    pub fn load(data_dir: &Path) -> Self {
+        storage::load_plain_json(&Self::path(data_dir))
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        storage::save_plain_json(&Self::path(data_dir), self)
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+// Messages starting with one of these are handled by the backend instead of the model
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlashCommand {
+    Search(String),
+    Model(Option<String>),
+    Clear,
+    Persona(Option<String>),
+    Ingest(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlashCommandInfo {
+    pub name: String,
+    pub usage: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlashCommandResult {
+    pub command: String,
+    pub message: String,
+}
+
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("search", "/search <query>", "Search the web without asking the model"),
+    ("model", "/model [name]", "Show or switch the chat model"),
+    ("clear", "/clear", "Clear the current conversation"),
+    (
+        "persona",
+        "/persona [instructions | default]",
+        "Show, replace or reset the system prompt of this conversation",
+    ),
+    ("ingest", "/ingest <url>", "Add a web page to the knowledge base"),
+];
+
+pub fn list() -> Vec<SlashCommandInfo> {
+    COMMANDS
+        .iter()
+        .map(|(name, usage, description)| SlashCommandInfo {
+            name: name.to_string(),
+            usage: usage.to_string(),
+            description: description.to_string(),
+        })
+        .collect()
+}
+
+// Returns None for ordinary messages, including ones that merely start with a slash
+// such as "/usr/bin/env: ...", and Some(Err) for malformed commands
+pub fn parse(text: &str) -> Option<Result<SlashCommand, String>> {
+    let rest = text.trim_start().strip_prefix('/')?;
+    let (name, args) = match rest.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (rest.trim(), ""),
+    };
+    let name = name.to_lowercase();
+    if !COMMANDS.iter().any(|(command, _, _)| *command == name) {
+        return None;
+    }
+    let optional = || (!args.is_empty()).then(|| args.to_string());
+    let required = |usage: &str| {
+        if args.is_empty() {
+            Err(format!("Usage: {}", usage))
+        } else {
+            Ok(args.to_string())
+        }
+    };
+
+    let command = match name.as_str() {
+        "search" => required("/search <query>").map(SlashCommand::Search),
+        "model" => Ok(SlashCommand::Model(optional())),
+        "clear" => Ok(SlashCommand::Clear),
+        "persona" => Ok(SlashCommand::Persona(optional())),
+        "ingest" => required("/ingest <url>").map(SlashCommand::Ingest),
+        _ => return None,
+    };
+    Some(command)
+}
@@ -0,0 +1,88 @@
+use anyhow::{bail, Result};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::storage;
+use crate::template::PromptVariables;
+
+// Triggers are written as ";name" in a message and expanded before it is sent
+const TRIGGER_PREFIX: char = ';';
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Snippet {
+    pub trigger: String,
+    pub expansion: String,
+}
+
+pub struct SnippetStore {
+    path: PathBuf,
+    snippets: Vec<Snippet>,
+}
+
+fn normalize_trigger(trigger: &str) -> Result<String> {
+    let name = trigger.trim().trim_start_matches(TRIGGER_PREFIX);
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        bail!("Snippet triggers may only contain letters, digits, '-' and '_'");
+    }
+    Ok(name.to_lowercase())
+}
+
+impl SnippetStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("snippets.json");
+        let snippets = storage::load_json(&path);
+        Self { path, snippets }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.snippets)
+    }
+
+    pub fn list(&self) -> Vec<Snippet> {
+        let mut snippets = self.snippets.clone();
+        snippets.sort_by(|a, b| a.trigger.cmp(&b.trigger));
+        snippets
+    }
+
+    // Creates the snippet, or replaces the expansion of an existing trigger
+    pub fn upsert(&mut self, trigger: &str, expansion: String) -> Result<Snippet> {
+        let trigger = normalize_trigger(trigger)?;
+        let snippet = Snippet { trigger, expansion };
+        match self.snippets.iter_mut().find(|s| s.trigger == snippet.trigger) {
+            Some(existing) => *existing = snippet.clone(),
+            None => self.snippets.push(snippet.clone()),
+        }
+        self.save()?;
+        Ok(snippet)
+    }
+
+    pub fn delete(&mut self, trigger: &str) -> Result<bool> {
+        let trigger = normalize_trigger(trigger)?;
+        let before = self.snippets.len();
+        self.snippets.retain(|s| s.trigger != trigger);
+        let removed = self.snippets.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    // Variables are resolved inside expansions only, not in what the user typed
+    pub fn expand(&self, text: &str, variables: &PromptVariables) -> String {
+        if self.snippets.is_empty() {
+            return text.to_string();
+        }
+
+        let trigger_re = Regex::new(r"(^|\s);([A-Za-z0-9_-]+)\b").unwrap();
+        trigger_re
+            .replace_all(text, |caps: &Captures| {
+                let name = caps[2].to_lowercase();
+                match self.snippets.iter().find(|s| s.trigger == name) {
+                    Some(snippet) => format!("{}{}", &caps[1], variables.render(&snippet.expansion)),
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::ollama::GenerationOptions;
+use crate::storage;
+
+// A saved way to start a conversation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationTemplate {
+    pub id: String,
+    pub name: String,
+    pub system_prompt: Option<String>,
+    // Sent as soon as the conversation is created
+    pub first_message: Option<String>,
+    // Overrides the selected model for conversations started from it
+    pub model: Option<String>,
+    #[serde(default)]
+    pub options: GenerationOptions,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct TemplateStore {
+    path: PathBuf,
+    templates: Vec<ConversationTemplate>,
+}
+
+fn non_empty(text: Option<String>) -> Option<String> {
+    text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+impl TemplateStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("conversation_templates.json");
+        let templates = storage::load_json(&path);
+        Self { path, templates }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.templates)
+    }
+
+    pub fn list(&self) -> Vec<ConversationTemplate> {
+        let mut templates = self.templates.clone();
+        templates.sort_by_key(|t| t.name.to_lowercase());
+        templates
+    }
+
+    pub fn get(&self, id: &str) -> Option<ConversationTemplate> {
+        self.templates.iter().find(|t| t.id == id).cloned()
+    }
+
+    pub fn add(
+        &mut self,
+        name: &str,
+        system_prompt: Option<String>,
+        first_message: Option<String>,
+        model: Option<String>,
+        options: GenerationOptions,
+    ) -> Result<ConversationTemplate> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("Template name is empty");
+        }
+        options.validate()?;
+        let template = ConversationTemplate {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            system_prompt: non_empty(system_prompt),
+            first_message: non_empty(first_message),
+            model: non_empty(model),
+            options,
+            created_at: Utc::now(),
+        };
+        self.templates.push(template.clone());
+        self.save()?;
+        Ok(template)
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<bool> {
+        let before = self.templates.len();
+        self.templates.retain(|t| t.id != id);
+        let removed = self.templates.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::encryption::{self, Locked};
+
+// Read before the storage is unlocked, or meant to be edited by hand
+const PLAINTEXT_FILES: [&str; 3] = ["settings.json", "encryption.json", "extraction_selectors.json"];
+// Everything the stores write into the data directory
+const STORE_EXTENSIONS: [&str; 4] = ["json", "md", "html", "corrupt"];
+
+// Load a JSON file, falling back to the default value if it is missing. A file that
+// cannot be read or parsed is moved aside first, so the next save does not overwrite
+// the user's data with the default. An encrypted file stays put while storage is locked.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    parse_or_default(path, read(path))
+}
+
+// For the files in PLAINTEXT_FILES, which never go through encryption
+pub fn load_plain_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    parse_or_default(path, fs::read(path).map_err(anyhow::Error::from))
+}
+
+fn parse_or_default<T: DeserializeOwned + Default>(path: &Path, bytes: Result<Vec<u8>>) -> T {
+    match bytes.and_then(|bytes| Ok(serde_json::from_slice(&bytes)?)) {
+        Ok(value) => value,
+        Err(e) if e.downcast_ref::<io::Error>().map(io::Error::kind) == Some(io::ErrorKind::NotFound) => {
+            T::default()
+        }
+        Err(e) if e.is::<Locked>() => T::default(),
+        Err(e) => {
+            let aside = set_aside(path);
+            eprintln!("Failed to load {}: {:?}; moved it to {}", path.display(), e, aside.display());
+            T::default()
+        }
+    }
+}
+
+// "facts.json" -> "facts.json.<secs>.corrupt"; an earlier corrupt copy is never replaced
+fn set_aside(path: &Path) -> PathBuf {
+    let aside = with_suffix(path, &format!(".{}.corrupt", now_secs()));
+    if let Err(e) = fs::rename(path, &aside) {
+        eprintln!("Failed to move {} aside: {:?}", path.display(), e);
+    }
+    aside
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write(path, &serde_json::to_vec_pretty(value)?)
+}
+
+pub fn save_plain_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write_atomic(path, &serde_json::to_vec_pretty(value)?)
+}
+
+// Encrypted when storage encryption is enabled; fails while the storage is locked
+pub fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+    write_atomic(path, &encryption::seal(bytes)?)
+}
+
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    encryption::open(&fs::read(path)?)
+}
+
+pub fn read_to_string(path: &Path) -> Result<String> {
+    Ok(String::from_utf8(read(path)?)?)
+}
+
+// Write to a temporary file first so a crash never leaves a half-written store behind
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = with_suffix(path, ".tmp");
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// Encrypts every store file still in plaintext; returns how many were migrated
+pub fn encrypt_all(data_dir: &Path) -> Result<usize> {
+    let mut migrated = 0;
+    let mut dirs = vec![data_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            if !is_store_file(data_dir, &path) {
+                continue;
+            }
+            let bytes = fs::read(&path)?;
+            if !encryption::is_sealed(&bytes) {
+                write(&path, &bytes)?;
+                migrated += 1;
+            }
+        }
+    }
+    Ok(migrated)
+}
+
+fn is_store_file(data_dir: &Path, path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    STORE_EXTENSIONS.contains(&extension) && !(path.parent() == Some(data_dir) && PLAINTEXT_FILES.contains(&name))
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,154 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::LlmBackend;
+use crate::context;
+use crate::ollama::{ChatMessage, ChatRequest, OllamaClient};
+use crate::reasoning::ThinkFilter;
+
+// History past this many estimated tokens gets its oldest part summarized
+pub const SUMMARIZE_AFTER_TOKENS: u64 = 2048;
+// Newest messages always kept as they are
+const KEEP_RECENT_MESSAGES: usize = 4;
+const MAX_SUMMARY_WORDS: usize = 200;
+const MAX_DIGEST_BULLETS: usize = 8;
+
+// Bullet summary and action items of a whole conversation, kept for quick review
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationDigest {
+    pub summary: Vec<String>,
+    pub action_items: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    // History length when written, so the UI can tell that it is out of date
+    pub message_count: usize,
+}
+
+// How many of the oldest messages to fold into the summary, or None while the history
+// is still small enough
+pub fn messages_to_summarize(history: &[ChatMessage]) -> Option<usize> {
+    let tokens: u64 = history.iter().map(context::message_tokens).sum();
+    let count = history.len().saturating_sub(KEEP_RECENT_MESSAGES);
+    (tokens > SUMMARIZE_AFTER_TOKENS && count > 0).then_some(count)
+}
+
+fn transcript(messages: &[ChatMessage]) -> String {
+    messages
+        .iter()
+        .filter(|m| m.role != "system")
+        .map(|m| format!("{}: {}\n", m.role, m.content))
+        .collect()
+}
+
+async fn ask(client: &dyn LlmBackend, model: &str, prompt: String) -> Result<String> {
+    let request = ChatRequest {
+        model: model.to_string(),
+        messages: vec![OllamaClient::create_user_message(prompt)],
+        stream: false,
+        keep_alive: None,
+        options: None,
+        tools: None,
+        logprobs: None,
+        top_logprobs: None,
+    };
+    let reply = client.chat(request).await?;
+
+    // Reasoning models think out loud first
+    let mut think = ThinkFilter::default();
+    let mut text = think.push(&reply.content).visible;
+    text.push_str(&think.finish().visible);
+    Ok(text.trim().to_string())
+}
+
+// Rolls `messages` into the previous summary, if there is one
+pub async fn summarize(
+    client: &dyn LlmBackend,
+    model: &str,
+    previous: Option<&str>,
+    messages: &[ChatMessage],
+) -> Result<String> {
+    let transcript = transcript(messages);
+    let previous = match previous {
+        Some(summary) => format!("Summary so far:\n{}\n\n", summary),
+        None => String::new(),
+    };
+    let prompt = format!(
+        "Summarize this conversation in at most {} words for use as context later on.\n\
+         Keep names, decisions, facts the user shared and open questions; leave out pleasantries.\n\
+         Reply only with the summary.\n\n\
+         {}Conversation:\n{}",
+        MAX_SUMMARY_WORDS, previous, transcript
+    );
+
+    let summary = ask(client, model, prompt).await?;
+    if summary.is_empty() {
+        bail!("The model returned an empty summary");
+    }
+    Ok(summary)
+}
+
+// Bullets under the SUMMARY and ACTION ITEMS headings; "none" means no action items
+fn parse_digest(text: &str) -> (Vec<String>, Vec<String>) {
+    let mut summary = Vec::new();
+    let mut action_items = Vec::new();
+    let mut in_actions = false;
+    for line in text.lines().map(str::trim) {
+        let heading = line.trim_matches(|c: char| c == '#' || c == '*' || c == ':' || c.is_whitespace());
+        match heading.to_uppercase().as_str() {
+            "SUMMARY" => {
+                in_actions = false;
+                continue;
+            }
+            "ACTION ITEMS" => {
+                in_actions = true;
+                continue;
+            }
+            _ => {}
+        }
+        let Some(item) = line.strip_prefix(['-', '*', '•']).map(str::trim) else {
+            continue;
+        };
+        if item.is_empty() || item.eq_ignore_ascii_case("none") {
+            continue;
+        }
+        let list = if in_actions { &mut action_items } else { &mut summary };
+        list.push(item.to_string());
+    }
+    summary.truncate(MAX_DIGEST_BULLETS);
+    action_items.truncate(MAX_DIGEST_BULLETS);
+    (summary, action_items)
+}
+
+// Covers the whole history; `previous` stands in for messages already summarized away
+pub async fn digest(
+    client: &dyn LlmBackend,
+    model: &str,
+    previous: Option<&str>,
+    messages: &[ChatMessage],
+) -> Result<ConversationDigest> {
+    let previous = match previous {
+        Some(summary) => format!("Summary of the earlier part:\n{}\n\n", summary),
+        None => String::new(),
+    };
+    let prompt = format!(
+        "Review this conversation. Reply in exactly this format:\n\
+         SUMMARY:\n- up to {max} bullet points covering what was discussed and decided\n\
+         ACTION ITEMS:\n- up to {max} concrete next steps for the user, or - none\n\n\
+         {previous}Conversation:\n{transcript}",
+        max = MAX_DIGEST_BULLETS,
+        previous = previous,
+        transcript = transcript(messages)
+    );
+
+    let reply = ask(client, model, prompt).await?;
+    let (summary, action_items) = parse_digest(&reply);
+    if summary.is_empty() {
+        bail!("The model did not return a summary");
+    }
+    Ok(ConversationDigest {
+        summary,
+        action_items,
+        created_at: Utc::now(),
+        message_count: messages.len(),
+    })
+}
@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::backend::LlmBackend;
+use crate::ollama::{ChatMessage, ChatRequest, OllamaClient};
+use crate::storage;
+
+// Conversations are re-tagged after every this many exchanges (message and reply)
+pub const AUTO_TAG_EVERY: usize = 2;
+const MAX_NEW_TAGS: usize = 3;
+const MAX_TAG_LEN: usize = 32;
+
+// "#Rust Async " -> "rust-async"; None for anything that is not a usable tag
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag: String = tag
+        .trim()
+        .trim_start_matches('#')
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-')
+        .take(MAX_TAG_LEN)
+        .collect();
+    let tag = tag.trim_matches('-').to_string();
+    (!tag.is_empty() && tag != "none").then_some(tag)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationTags {
+    pub conversation_id: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TagCount {
+    pub tag: String,
+    pub conversations: usize,
+}
+
+// Tags created by hand, so they can be offered before any conversation carries them
+pub struct TagStore {
+    path: PathBuf,
+    tags: Vec<String>,
+}
+
+impl TagStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("conversation_tags.json");
+        let tags = storage::load_json(&path);
+        Self { path, tags }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.tags)
+    }
+
+    pub fn list(&self) -> &[String] {
+        &self.tags
+    }
+
+    // Returns the normalized tag; creating an existing one is not an error
+    pub fn create(&mut self, name: &str) -> Result<String> {
+        let tag = normalize_tag(name).ok_or_else(|| anyhow!("Not a usable tag: {}", name))?;
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag.clone());
+            self.tags.sort();
+            self.save()?;
+        }
+        Ok(tag)
+    }
+
+    pub fn delete(&mut self, tag: &str) -> Result<bool> {
+        let before = self.tags.len();
+        self.tags.retain(|t| t != tag);
+        if self.tags.len() == before {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+}
+
+// Adds tags not already present, keeping the existing order
+pub fn merge_tags(tags: &mut Vec<String>, new_tags: impl IntoIterator<Item = String>) {
+    for tag in new_tags {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+}
+
+// Lightweight classification pass: asks the model for a few short topic tags,
+// preferring tags the conversation already has
+pub async fn suggest_tags(
+    client: &dyn LlmBackend,
+    model: &str,
+    messages: &[ChatMessage],
+    existing: &[String],
+) -> Result<Vec<String>> {
+    let transcript: String = messages
+        .iter()
+        .filter(|m| m.role != "system")
+        .map(|m| format!("{}: {}\n", m.role, m.content))
+        .collect();
+    let existing = if existing.is_empty() {
+        "none".to_string()
+    } else {
+        existing.join(", ")
+    };
+    let prompt = format!(
+        "Give up to {} short topic tags (one or two words each) for this conversation.\n\
+         Existing tags: {}. Reuse them where they fit.\n\
+         Reply only with a comma-separated list of tags, or NONE.\n\n\
+         Conversation:\n{}",
+        MAX_NEW_TAGS, existing, transcript
+    );
+
+    let request = ChatRequest {
+        model: model.to_string(),
+        messages: vec![OllamaClient::create_user_message(prompt)],
+        stream: false,
+        keep_alive: None,
+        options: None,
+        tools: None,
+        logprobs: None,
+        top_logprobs: None,
+    };
+    let reply = client.chat(request).await?;
+
+    let mut tags = Vec::new();
+    merge_tags(
+        &mut tags,
+        reply.content.split([',', '\n']).filter_map(normalize_tag),
+    );
+    tags.truncate(MAX_NEW_TAGS);
+    Ok(tags)
+}
@@ -0,0 +1,56 @@
+use chrono::Local;
+use regex::{Captures, Regex};
+
+// Values for the {{variables}} allowed in system prompts, workspace personas and
+// snippets, resolved when each request is built
+#[derive(Debug, Clone)]
+pub struct PromptVariables {
+    pub date: String,
+    pub user_name: String,
+    pub os: String,
+    pub workspace: String,
+}
+
+fn os_name() -> String {
+    match std::env::consts::OS {
+        "macos" => "macOS".to_string(),
+        "linux" => "Linux".to_string(),
+        "windows" => "Windows".to_string(),
+        other => other.to_string(),
+    }
+}
+
+impl PromptVariables {
+    // An empty user name falls back to the OS account name
+    pub fn collect(user_name: &str, workspace: Option<&str>) -> Self {
+        let user_name = Some(user_name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .unwrap_or_else(|| "the user".to_string());
+        Self {
+            date: Local::now().format("%A, %B %-d, %Y").to_string(),
+            user_name,
+            os: os_name(),
+            workspace: workspace.unwrap_or("none").to_string(),
+        }
+    }
+
+    // Unknown variables are left exactly as written
+    pub fn render(&self, text: &str) -> String {
+        if !text.contains("{{") {
+            return text.to_string();
+        }
+
+        let variable_re = Regex::new(r"\{\{\s*([A-Za-z_]+)\s*\}\}").unwrap();
+        variable_re
+            .replace_all(text, |caps: &Captures| match caps[1].to_lowercase().as_str() {
+                "date" => self.date.clone(),
+                "user_name" => self.user_name.clone(),
+                "os" => self.os.clone(),
+                "workspace" => self.workspace.clone(),
+                _ => caps[0].to_string(),
+            })
+            .into_owned()
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::LlmBackend;
+use crate::ollama::{ChatMessage, ChatRequest, OllamaClient};
+use crate::reasoning::ThinkFilter;
+
+const MAX_TITLE_WORDS: usize = 6;
+// Long first messages are cut; the opening says enough about the topic
+const MAX_TRANSCRIPT_CHARS: usize = 2000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationTitled {
+    pub conversation_id: String,
+    pub name: String,
+}
+
+// "Title: \"Fixing Rust lifetimes.\"" -> "Fixing Rust lifetimes"
+fn clean_title(reply: &str) -> Option<String> {
+    let line = reply.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+    let title = line
+        .split_whitespace()
+        .take(MAX_TITLE_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let title = title.trim_matches(|c: char| c == '"' || c == '\'' || c == '*' || c == '#' || c == '.' || c.is_whitespace());
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+// A 3–6 word title from the first exchange
+pub async fn suggest_title(client: &dyn LlmBackend, model: &str, messages: &[ChatMessage]) -> Result<String> {
+    let transcript: String = messages
+        .iter()
+        .filter(|m| m.role != "system")
+        .map(|m| format!("{}: {}\n", m.role, m.content))
+        .collect::<String>()
+        .chars()
+        .take(MAX_TRANSCRIPT_CHARS)
+        .collect();
+    let prompt = format!(
+        "Write a title of 3 to {} words for this conversation.\n\
+         Reply only with the title, without quotes or punctuation at the end.\n\n\
+         Conversation:\n{}",
+        MAX_TITLE_WORDS, transcript
+    );
+
+    let request = ChatRequest {
+        model: model.to_string(),
+        messages: vec![OllamaClient::create_user_message(prompt)],
+        stream: false,
+        keep_alive: None,
+        options: None,
+        tools: None,
+        logprobs: None,
+        top_logprobs: None,
+    };
+    let reply = client.chat(request).await?;
+
+    let mut think = ThinkFilter::default();
+    let mut text = think.push(&reply.content).visible;
+    text.push_str(&think.finish().visible);
+    match clean_title(&text) {
+        Some(title) => Ok(title),
+        None => bail!("The model returned an empty title"),
+    }
+}
@@ -0,0 +1,41 @@
+use anyhow::{Context, Result};
+use reqwest::{Certificate, ClientBuilder};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TlsSettings {
+    // PEM file with an extra CA to trust, e.g. a homelab root certificate
+    pub ca_cert_path: Option<String>,
+    // Hosts for which certificate verification is skipped entirely
+    pub insecure_hosts: Vec<String>,
+}
+
+impl TlsSettings {
+    pub fn apply(&self, mut builder: ClientBuilder, target_url: &str) -> Result<ClientBuilder> {
+        if let Some(path) = &self.ca_cert_path {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("Failed to read CA certificate: {}", path))?;
+            let cert = Certificate::from_pem(&pem)
+                .with_context(|| format!("Invalid CA certificate: {}", path))?;
+            builder = builder.add_root_certificate(cert);
+        }
+
+        let host = Url::parse(target_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        if let Some(host) = host {
+            if self.insecure_hosts.iter().any(|h| h.eq_ignore_ascii_case(&host)) {
+                eprintln!(
+                    "WARNING: TLS certificate verification is disabled for {}. \
+                     Connections to this host can be intercepted.",
+                    host
+                );
+                builder = builder.danger_accept_invalid_certs(true);
+            }
+        }
+
+        Ok(builder)
+    }
+}
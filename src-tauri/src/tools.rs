@@ -0,0 +1,95 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::ollama::{FunctionDefinition, ToolCall, ToolDefinition};
+
+// Tool-call round trips allowed per message before the model has to answer
+pub const MAX_TOOL_ROUNDS: usize = 3;
+
+// Off by default: models without tool support reject requests that carry tools
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ToolSettings {
+    pub enabled: bool,
+}
+
+// The tools the backend can run on the model's behalf
+#[derive(Debug, Clone)]
+pub enum Tool {
+    WebSearch { query: String },
+    CurrentTime,
+    SaveFact { text: String, topic: Option<String> },
+}
+
+fn function(name: &str, description: &str, parameters: Value) -> ToolDefinition {
+    ToolDefinition {
+        kind: "function".to_string(),
+        function: FunctionDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters,
+        },
+    }
+}
+
+// web_search is left out for conversations without web access
+pub fn definitions(web_allowed: bool) -> Vec<ToolDefinition> {
+    let mut tools = vec![
+        function(
+            "current_time",
+            "Get the current local date and time",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function(
+            "save_fact",
+            "Save a fact worth remembering to the user's fact store",
+            json!({
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string", "description": "The fact, as one sentence" },
+                    "topic": { "type": "string", "description": "Short topic to file it under" }
+                },
+                "required": ["text"]
+            }),
+        ),
+    ];
+    if web_allowed {
+        tools.push(function(
+            "web_search",
+            "Search the web and return the top results with summaries",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "The search query" }
+                },
+                "required": ["query"]
+            }),
+        ));
+    }
+    tools
+}
+
+fn string_argument(arguments: &Value, name: &str) -> Option<String> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+pub fn parse(call: &ToolCall) -> Result<Tool> {
+    let arguments = &call.function.arguments;
+    match call.function.name.as_str() {
+        "web_search" => Ok(Tool::WebSearch {
+            query: string_argument(arguments, "query").context("web_search needs a query")?,
+        }),
+        "current_time" => Ok(Tool::CurrentTime),
+        "save_fact" => Ok(Tool::SaveFact {
+            text: string_argument(arguments, "text").context("save_fact needs text")?,
+            topic: string_argument(arguments, "topic"),
+        }),
+        other => bail!("Unknown tool: {}", other),
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::ollama::ChatMessage;
+use crate::redact::RedactionMap;
+use crate::storage;
+use crate::summary::ConversationDigest;
+
+// A year
+pub const MAX_RETENTION_HOURS: u32 = 24 * 365;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TrashSettings {
+    // How long a cleared history can be restored
+    pub retention_hours: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self { retention_hours: 24 }
+    }
+}
+
+impl TrashSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.retention_hours > MAX_RETENTION_HOURS {
+            bail!("retention_hours can be at most {}", MAX_RETENTION_HOURS);
+        }
+        Ok(())
+    }
+
+    // When a history cleared at `cleared_at` expires; None if that is out of range
+    fn expires_at(&self, cleared_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        cleared_at.checked_add_signed(TimeDelta::try_hours(self.retention_hours.into())?)
+    }
+}
+
+// Everything clear_conversation resets
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClearedHistory {
+    pub messages: Vec<ChatMessage>,
+    pub redactions: RedactionMap,
+    pub tags: Vec<String>,
+    pub exchanges: usize,
+    pub summary: Option<String>,
+    pub digest: Option<ConversationDigest>,
+    pub cleared_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrashEntry {
+    pub conversation_id: String,
+    pub message_count: usize,
+    pub cleared_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// The last cleared history of each conversation, kept in trash.json so a clear can still
+// be undone after a restart; expired entries are dropped whenever the trash is touched
+pub struct Trash {
+    path: PathBuf,
+    entries: HashMap<String, ClearedHistory>,
+}
+
+impl Trash {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("trash.json");
+        let entries = storage::load_json(&path);
+        Self { path, entries }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.entries)
+    }
+
+    fn purge(&mut self, settings: &TrashSettings) -> Result<()> {
+        let now = Utc::now();
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, entry| settings.expires_at(entry.cleared_at).is_none_or(|expires| expires > now));
+        if self.entries.len() != before {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    // A newer clear replaces the previous one; clearing an empty history keeps it
+    pub fn put(&mut self, conversation_id: &str, history: ClearedHistory, settings: &TrashSettings) -> Result<()> {
+        self.purge(settings)?;
+        if history.messages.is_empty() && history.summary.is_none() {
+            return Ok(());
+        }
+        self.entries.insert(conversation_id.to_string(), history);
+        self.save()
+    }
+
+    pub fn take(&mut self, conversation_id: &str, settings: &TrashSettings) -> Result<Option<ClearedHistory>> {
+        self.purge(settings)?;
+        let history = self.entries.remove(conversation_id);
+        if history.is_some() {
+            self.save()?;
+        }
+        Ok(history)
+    }
+
+    pub fn forget(&mut self, conversation_id: &str) -> Result<()> {
+        if self.entries.remove(conversation_id).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    // Most recently cleared first
+    pub fn list(&mut self, settings: &TrashSettings) -> Result<Vec<TrashEntry>> {
+        self.purge(settings)?;
+        let mut entries: Vec<TrashEntry> = self
+            .entries
+            .iter()
+            .map(|(id, entry)| TrashEntry {
+                conversation_id: id.clone(),
+                message_count: entry.messages.len(),
+                cleared_at: entry.cleared_at,
+                expires_at: settings.expires_at(entry.cleared_at).unwrap_or(DateTime::<Utc>::MAX_UTC),
+            })
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.cleared_at));
+        Ok(entries)
+    }
+}
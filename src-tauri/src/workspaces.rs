@@ -0,0 +1,215 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::storage;
+
+// A project-level grouping ("Thesis", "Side project") above individual conversations
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub conversation_ids: Vec<String>,
+    // Knowledge base snapshots saved while the workspace was active
+    pub snapshot_ids: Vec<String>,
+    // Used instead of the default model for this workspace's conversations
+    pub model: Option<String>,
+    // Extra system instructions sent ahead of each conversation in the workspace
+    pub persona: Option<String>,
+    // Web searches are restricted to these domains unless the query names its own site:
+    pub search_sites: Vec<String>,
+    pub archived: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// Editable fields, shared by create and update
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct WorkspaceConfig {
+    pub model: Option<String>,
+    pub persona: Option<String>,
+    pub search_sites: Vec<String>,
+}
+
+impl WorkspaceConfig {
+    fn normalized(self) -> Self {
+        let trimmed = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let mut search_sites: Vec<String> = self
+            .search_sites
+            .iter()
+            .map(|site| site.trim().trim_start_matches("site:").to_lowercase())
+            .filter(|site| !site.is_empty())
+            .collect();
+        search_sites.sort();
+        search_sites.dedup();
+        Self {
+            model: trimmed(self.model),
+            persona: trimmed(self.persona),
+            search_sites,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+struct WorkspaceFile {
+    active: Option<String>,
+    workspaces: Vec<Workspace>,
+}
+
+pub struct WorkspaceStore {
+    path: PathBuf,
+    data: WorkspaceFile,
+}
+
+impl WorkspaceStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("workspaces.json");
+        let data = storage::load_json(&path);
+        Self { path, data }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::save_json(&self.path, &self.data)
+    }
+
+    fn workspace_mut(&mut self, id: &str) -> Result<&mut Workspace> {
+        self.data
+            .workspaces
+            .iter_mut()
+            .find(|w| w.id == id)
+            .with_context(|| format!("No workspace: {}", id))
+    }
+
+    pub fn list(&self, include_archived: bool) -> Vec<Workspace> {
+        let mut workspaces: Vec<Workspace> = self
+            .data
+            .workspaces
+            .iter()
+            .filter(|w| include_archived || !w.archived)
+            .cloned()
+            .collect();
+        workspaces.sort_by_key(|w| w.name.to_lowercase());
+        workspaces
+    }
+
+    pub fn active(&self) -> Option<&Workspace> {
+        let id = self.data.active.as_deref()?;
+        self.data.workspaces.iter().find(|w| w.id == id)
+    }
+
+    pub fn for_conversation(&self, conversation_id: &str) -> Option<&Workspace> {
+        self.data
+            .workspaces
+            .iter()
+            .find(|w| w.conversation_ids.iter().any(|id| id == conversation_id))
+    }
+
+    pub fn create(&mut self, name: &str, config: WorkspaceConfig) -> Result<Workspace> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("Workspace name is empty");
+        }
+        if self.data.workspaces.iter().any(|w| w.name.eq_ignore_ascii_case(name)) {
+            bail!("A workspace named {} already exists", name);
+        }
+        let config = config.normalized();
+        let workspace = Workspace {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            conversation_ids: Vec::new(),
+            snapshot_ids: Vec::new(),
+            model: config.model,
+            persona: config.persona,
+            search_sites: config.search_sites,
+            archived: false,
+            created_at: Utc::now(),
+        };
+        self.data.workspaces.push(workspace.clone());
+        self.save()?;
+        Ok(workspace)
+    }
+
+    pub fn update(&mut self, id: &str, config: WorkspaceConfig) -> Result<Workspace> {
+        let config = config.normalized();
+        let workspace = self.workspace_mut(id)?;
+        workspace.model = config.model;
+        workspace.persona = config.persona;
+        workspace.search_sites = config.search_sites;
+        let workspace = workspace.clone();
+        self.save()?;
+        Ok(workspace)
+    }
+
+    // None leaves every workspace, going back to the ungrouped default
+    pub fn switch(&mut self, id: Option<&str>) -> Result<Option<Workspace>> {
+        let workspace = match id {
+            Some(id) => {
+                let workspace = self.workspace_mut(id)?;
+                if workspace.archived {
+                    bail!("Workspace {} is archived", workspace.name);
+                }
+                Some(workspace.clone())
+            }
+            None => None,
+        };
+        self.data.active = workspace.as_ref().map(|w| w.id.clone());
+        self.save()?;
+        Ok(workspace)
+    }
+
+    // Archived workspaces keep their contents but drop out of the list and cannot be active
+    pub fn set_archived(&mut self, id: &str, archived: bool) -> Result<Workspace> {
+        let workspace = self.workspace_mut(id)?;
+        workspace.archived = archived;
+        let workspace = workspace.clone();
+        if archived && self.data.active.as_deref() == Some(id) {
+            self.data.active = None;
+        }
+        self.save()?;
+        Ok(workspace)
+    }
+
+    // A conversation belongs to at most one workspace, so it is moved out of any other
+    pub fn add_conversation(&mut self, id: &str, conversation_id: &str) -> Result<Workspace> {
+        self.workspace_mut(id)?;
+        for workspace in &mut self.data.workspaces {
+            workspace.conversation_ids.retain(|c| c != conversation_id);
+        }
+        let workspace = self.workspace_mut(id)?;
+        workspace.conversation_ids.push(conversation_id.to_string());
+        let workspace = workspace.clone();
+        self.save()?;
+        Ok(workspace)
+    }
+
+    // Attaches to the active workspace, if any
+    pub fn add_to_active(&mut self, add: impl FnOnce(&mut Workspace)) -> Result<()> {
+        let Some(id) = self.data.active.clone() else {
+            return Ok(());
+        };
+        add(self.workspace_mut(&id)?);
+        self.save()
+    }
+
+    // Drops a deleted conversation or snapshot from whichever workspace held it
+    pub fn forget(&mut self, id: &str) -> Result<()> {
+        let mut changed = false;
+        for workspace in &mut self.data.workspaces {
+            let before = workspace.conversation_ids.len() + workspace.snapshot_ids.len();
+            workspace.conversation_ids.retain(|c| c != id);
+            workspace.snapshot_ids.retain(|s| s != id);
+            changed |= workspace.conversation_ids.len() + workspace.snapshot_ids.len() != before;
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(())
+    }
+}
@@ -100,12 +100,12 @@ const ChatInterface: React.FC = () => {
 
   useEffect(() => {
     // Set up event listener for streaming responses
-    const unlisten = listen<string>('chat-response', (event) => {
+    const unlisten = listen<{ content: string; stream_id: string }>('chat-response', (event) => {
       setMessages(prev => prev.map(msg => {
         if (msg.id === prev[prev.length - 1].id && msg.role === 'assistant') {
           return {
             ...msg,
-            content: msg.content + event.payload
+            content: msg.content + event.payload.content
           };
         }
         return msg;